@@ -1,14 +1,105 @@
 const API_BASE_URL = import.meta.env.VITE_API_URL || "/api";
 
+export interface DeviceAddress {
+  family: "ipv4" | "ipv6";
+  address: string;
+  source:
+    | "arp"
+    | "dhcp"
+    | "ndp"
+    | "netlink"
+    | "mdns"
+    | "ssdp"
+    | "nbns"
+    | "llmnr"
+    | "lease_file"
+    | "lldp"
+    | "cdp"
+    | "tcp";
+  first_seen: string;
+  last_seen: string;
+}
+
+export interface DeviceSegment {
+  segment: string;
+  first_seen: string;
+  last_seen: string;
+}
+
+export interface DeviceService {
+  service_type: string;
+  instance: string;
+  port: number | null;
+  txt: string[];
+  first_seen: string;
+  last_seen: string;
+}
+
+export interface UpnpInfo {
+  uuid: string | null;
+  server: string | null;
+  device_type: string | null;
+  location: string | null;
+  friendly_name: string | null;
+  manufacturer: string | null;
+  model_name: string | null;
+  description_fetched_at: string | null;
+  first_seen: string;
+  last_seen: string;
+}
+
+export interface LinkDiscovery {
+  protocol: "lldp" | "cdp";
+  chassis_id: string | null;
+  system_name: string | null;
+  system_description: string | null;
+  platform: string | null;
+  capabilities: string[];
+  management_address: string | null;
+  port_id: string | null;
+  port_description: string | null;
+  first_seen: string;
+  last_seen: string;
+}
+
+export interface DeviceMac {
+  mac_address: string;
+  first_seen: string;
+  last_seen: string;
+}
+
 export interface Device {
   id: number;
   mac_address: string;
   ip_address: string | null;
   hostname: string | null;
   nickname: string | null;
+  vendor: string | null;
+  locally_administered: boolean;
+  dhcp_client_id: string | null;
+  dhcp_fingerprint: string | null;
   status: "online" | "offline" | "unknown";
   first_seen: string;
   last_seen: string;
+  segment: string | null;
+  vlan_id: number | null;
+  addresses: DeviceAddress[];
+  segments: DeviceSegment[];
+  mac_history: DeviceMac[];
+  services: DeviceService[];
+  upnp: UpnpInfo | null;
+  presence_probes: string[] | null;
+  pinned: boolean;
+  os_family: string | null;
+  device_type: string | null;
+  device_type_source: "fingerprint" | "announced" | null;
+  infrastructure: boolean;
+  classification_confidence: number | null;
+  tcp_signature: string | null;
+  tcp_os: string | null;
+  tcp_os_flavor: string | null;
+  lease: DhcpLease | null;
+  link_discovery: LinkDiscovery | null;
 }
 
 export interface DevicesResponse {
@@ -23,8 +114,18 @@ export interface Rule {
     | "new_device"
     | "device_connected"
     | "device_disconnected"
-    | "device_status_change";
+    | "device_status_change"
+    | "arp_spoofing"
+    | "gratuitous_arp_flood"
+    | "multiple_ip_claims"
+    | "ip_conflict"
+    | "rogue_dhcp_server";
   mac_filter: string | null;
+  segment_filter: string | null;
+  vendor_filter: string | null;
+  os_family_filter: string | null;
+  device_type_filter: string | null;
+  tcp_os_filter: string | null;
   enabled: boolean;
   notification_channels: string[];
   created_at: string;
@@ -40,6 +141,11 @@ export interface CreateRuleRequest {
   description: string | null;
   trigger_type: Rule["trigger_type"];
   mac_filter: string | null;
+  segment_filter: string | null;
+  vendor_filter: string | null;
+  os_family_filter: string | null;
+  device_type_filter: string | null;
+  tcp_os_filter: string | null;
   enabled: boolean;
   notification_channels: string[];
 }
@@ -82,6 +188,7 @@ export type NotificationChannel =
 export interface Config {
   daemon: {
     interface: string;
+    interfaces: string[];
     neighbor_check_interval_secs: number;
     device_timeout_secs: number;
     capture_filter: string | null;
@@ -107,10 +214,20 @@ export interface NotificationChannelsResponse {
   count: number;
 }
 
+export interface DegradedInterface {
+  interface: string;
+  reason: string;
+  since: string;
+}
+
 export interface HealthResponse {
   status: string;
   service: string;
   uptime_seconds: number;
+  capture: {
+    mode: "full" | "reduced";
+    degraded_interfaces: DegradedInterface[];
+  };
   system: {
     cpu_usage_percent: number;
     memory_usage_percent: number;
@@ -133,6 +250,57 @@ export interface LogsResponse {
   count: number;
 }
 
+export interface IpConflict {
+  id: number;
+  ip_address: string;
+  mac_address: string;
+  conflicting_mac_address: string;
+  first_seen: string;
+  last_seen: string;
+}
+
+export interface IpConflictsResponse {
+  conflicts: IpConflict[];
+  count: number;
+}
+
+export interface DhcpLease {
+  mac_address: string;
+  ip_address: string;
+  server_id: string | null;
+  server_mac: string;
+  lease_time_secs: number | null;
+  renewal_time_secs: number | null;
+  rebinding_time_secs: number | null;
+  acquired_at: string;
+  renewed_at: string;
+  renews_at: string | null;
+  rebinds_at: string | null;
+  expires_at: string | null;
+  renewals: number;
+}
+
+export interface LeasesResponse {
+  leases: DhcpLease[];
+  count: number;
+}
+
+export interface UnknownFingerprint {
+  fingerprint: string;
+  vendor_class: string | null;
+  mac_address: string;
+  hostname: string | null;
+  vendor: string | null;
+  times_seen: number;
+  first_seen: string;
+  last_seen: string;
+}
+
+export interface UnknownFingerprintsResponse {
+  fingerprints: UnknownFingerprint[];
+  count: number;
+}
+
 class ApiClient {
   private baseUrl: string;
 
@@ -173,6 +341,32 @@ class ApiClient {
     return this.request<Device>(`/devices/${encodeURIComponent(macAddress)}`);
   }
 
+  async updateDevicePresenceProbes(
+    macAddress: string,
+    presenceProbes: string[] | null,
+  ): Promise<Device> {
+    return this.request<Device>(
+      `/devices/${encodeURIComponent(macAddress)}/probes`,
+      {
+        method: "POST",
+        body: JSON.stringify({ presence_probes: presenceProbes }),
+      },
+    );
+  }
+
+  async updateDevicePinned(
+    macAddress: string,
+    pinned: boolean,
+  ): Promise<Device> {
+    return this.request<Device>(
+      `/devices/${encodeURIComponent(macAddress)}/pin`,
+      {
+        method: "POST",
+        body: JSON.stringify({ pinned }),
+      },
+    );
+  }
+
   async updateDeviceNickname(
     macAddress: string,
     nickname: string | null,
@@ -261,6 +455,12 @@ class ApiClient {
     return this.request<Metrics>("/metrics");
   }
 
+  async startScan(): Promise<void> {
+    await this.request<{ message: string }>("/scan", {
+      method: "POST",
+    });
+  }
+
   async restartDaemon(): Promise<void> {
     await this.request<{ message: string }>("/restart", {
       method: "POST",
@@ -270,6 +470,18 @@ class ApiClient {
   async getLogs(): Promise<LogsResponse> {
     return this.request<LogsResponse>("/logs");
   }
+
+  async getIpConflicts(): Promise<IpConflictsResponse> {
+    return this.request<IpConflictsResponse>("/conflicts");
+  }
+
+  async getLeases(): Promise<LeasesResponse> {
+    return this.request<LeasesResponse>("/leases");
+  }
+
+  async getUnknownFingerprints(): Promise<UnknownFingerprintsResponse> {
+    return this.request<UnknownFingerprintsResponse>("/fingerprints/unknown");
+  }
 }
 
 export const api = new ApiClient(API_BASE_URL);
@@ -309,6 +521,16 @@ export function getTriggerTypeLabel(type: Rule["trigger_type"]): string {
       return "Device Disconnected";
     case "device_status_change":
       return "Status Change";
+    case "arp_spoofing":
+      return "ARP Spoofing";
+    case "gratuitous_arp_flood":
+      return "Gratuitous ARP Flood";
+    case "multiple_ip_claims":
+      return "Many IPs Claimed";
+    case "ip_conflict":
+      return "IP Conflict";
+    case "rogue_dhcp_server":
+      return "Rogue DHCP Server";
     default:
       return type;
   }
@@ -14,13 +14,15 @@ use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
 use tracing::info;
 
-use crate::daemon::Daemon;
+use crate::daemon::{Daemon, IP_CONFLICT_ACTIVE};
 use crate::db::Database;
-use crate::errors::Result;
+use crate::errors::{DaemonError, Result};
 use crate::models::{
-    Config, ConfigUpdateRequest, Device, DeviceNicknameRequest, DeviceStatus, DevicesResponse,
-    ErrorResponse, LogsResponse, Metrics, NotificationChannel, NotificationChannelWithId,
-    NotificationChannelsResponse, Rule, RuleRequest, RulesResponse, SuccessResponse,
+    Config, ConfigUpdateRequest, Device, DeviceNicknameRequest, DevicePinnedRequest,
+    DevicePresenceProbesRequest, DeviceStatus, DevicesResponse, ErrorResponse,
+    IpConflictsResponse, LeasesResponse, LogsResponse, Metrics, NotificationChannel,
+    NotificationChannelWithId, NotificationChannelsResponse, Rule, RuleRequest, RulesResponse,
+    SuccessResponse, UnknownFingerprintsResponse,
 };
 
 #[derive(Embed)]
@@ -52,6 +54,8 @@ pub fn create_router(state: AppState) -> Router {
         .route("/devices", get(get_devices))
         .route("/devices/{mac}", get(get_device))
         .route("/devices/{mac}/nickname", post(update_device_nickname))
+        .route("/devices/{mac}/probes", post(update_device_presence_probes))
+        .route("/devices/{mac}/pin", post(update_device_pinned))
         .route("/rules", get(get_rules).post(create_rule))
         .route("/rules/{id}", get(get_rule).post(update_rule))
         .route("/rules/{id}/delete", post(delete_rule))
@@ -68,6 +72,10 @@ pub fn create_router(state: AppState) -> Router {
         )
         .route("/metrics", get(get_metrics))
         .route("/logs", get(get_logs))
+        .route("/conflicts", get(get_ip_conflicts))
+        .route("/leases", get(get_leases))
+        .route("/fingerprints/unknown", get(get_unknown_fingerprints))
+        .route("/scan", post(start_scan))
         .route("/restart", post(restart_daemon))
         .fallback(api_fallback)
         .with_state(state);
@@ -93,18 +101,18 @@ async fn api_fallback(uri: Uri) -> Response {
 async fn serve_console(uri: Uri) -> Response {
     let path = uri.path().trim_start_matches('/');
 
-    if !path.is_empty() {
-        if let Some(content) = ConsoleAssets::get(path) {
-            let mime = mime_guess::from_path(path)
-                .first_or_octet_stream()
-                .to_string();
-            return (
-                StatusCode::OK,
-                [(header::CONTENT_TYPE, mime)],
-                content.data.to_vec(),
-            )
-                .into_response();
-        }
+    if !path.is_empty()
+        && let Some(content) = ConsoleAssets::get(path)
+    {
+        let mime = mime_guess::from_path(path)
+            .first_or_octet_stream()
+            .to_string();
+        return (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, mime)],
+            content.data.to_vec(),
+        )
+            .into_response();
     }
 
     if !path.is_empty() && !path.contains('.') {
@@ -164,10 +172,23 @@ async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
 
     let cpu_usage = sys.global_cpu_info().cpu_usage() as u32;
 
+    // Interfaces without packet capture are only watched through the kernel
+    // neighbour table, which the daemon keeps running on.
+    let degraded_interfaces = state
+        .daemon
+        .as_ref()
+        .map(|daemon| daemon.degraded_interfaces())
+        .unwrap_or_default();
+    let reduced = !degraded_interfaces.is_empty();
+
     Json(serde_json::json!({
-        "status": "ok",
+        "status": if reduced { "degraded" } else { "ok" },
         "service": "foxd",
         "uptime_seconds": uptime_seconds,
+        "capture": {
+            "mode": if reduced { "reduced" } else { "full" },
+            "degraded_interfaces": degraded_interfaces,
+        },
         "system": {
             "cpu_usage_percent": cpu_usage,
             "memory_usage_percent": memory_usage_percent,
@@ -177,8 +198,39 @@ async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
     }))
 }
 
+async fn with_details(db: &Database, mut device: Device) -> Result<Device> {
+    if let Some(id) = device.id {
+        device.addresses = db.get_device_addresses(id).await?;
+        device.segments = db.get_device_segments(id).await?;
+        device.mac_history = db.get_device_macs(id).await?;
+        device.services = db.get_device_services(id).await?;
+        device.upnp = db.get_device_upnp(id).await?;
+        device.lease = db.get_device_lease(id).await?;
+        device.link_discovery = db.get_device_link_discovery(id).await?;
+    }
+    Ok(device)
+}
+
 async fn get_devices(State(state): State<AppState>) -> Result<Json<DevicesResponse>> {
-    let devices = state.db.get_all_devices().await?;
+    let mut devices = state.db.get_all_devices().await?;
+    let mut addresses = state.db.get_all_device_addresses().await?;
+    let mut segments = state.db.get_all_device_segments().await?;
+    let mut macs = state.db.get_all_device_macs().await?;
+    let mut services = state.db.get_all_device_services().await?;
+    let mut upnp = state.db.get_all_device_upnp().await?;
+    let mut leases = state.db.get_all_device_leases().await?;
+    let mut link_discovery = state.db.get_all_device_link_discovery().await?;
+    for device in &mut devices {
+        if let Some(id) = device.id {
+            device.addresses = addresses.remove(&id).unwrap_or_default();
+            device.segments = segments.remove(&id).unwrap_or_default();
+            device.mac_history = macs.remove(&id).unwrap_or_default();
+            device.services = services.remove(&id).unwrap_or_default();
+            device.upnp = upnp.remove(&id);
+            device.lease = leases.remove(&id);
+            device.link_discovery = link_discovery.remove(&id);
+        }
+    }
     let count = devices.len();
 
     Ok(Json(DevicesResponse { devices, count }))
@@ -193,7 +245,7 @@ async fn get_device(
             crate::errors::DaemonError::NotFound(format!("Device {} not found", mac))
         })?;
 
-    Ok(Json(device))
+    Ok(Json(with_details(&state.db, device).await?))
 }
 
 async fn update_device_nickname(
@@ -201,14 +253,14 @@ async fn update_device_nickname(
     Path(mac): Path<String>,
     Json(request): Json<DeviceNicknameRequest>,
 ) -> Result<Json<Device>> {
-    let _device =
+    let device =
         state.db.get_device_by_mac(&mac).await?.ok_or_else(|| {
             crate::errors::DaemonError::NotFound(format!("Device {} not found", mac))
         })?;
 
     state
         .db
-        .update_device_nickname(&mac, request.nickname)
+        .update_device_nickname(&device.mac_address, request.nickname)
         .await?;
 
     let updated_device = state.db.get_device_by_mac(&mac).await?.ok_or_else(|| {
@@ -220,7 +272,58 @@ async fn update_device_nickname(
         mac, updated_device.nickname
     );
 
-    Ok(Json(updated_device))
+    Ok(Json(with_details(&state.db, updated_device).await?))
+}
+
+async fn update_device_presence_probes(
+    State(state): State<AppState>,
+    Path(mac): Path<String>,
+    Json(request): Json<DevicePresenceProbesRequest>,
+) -> Result<Json<Device>> {
+    let device =
+        state.db.get_device_by_mac(&mac).await?.ok_or_else(|| {
+            crate::errors::DaemonError::NotFound(format!("Device {} not found", mac))
+        })?;
+
+    state
+        .db
+        .update_device_presence_probes(&device.mac_address, request.presence_probes.as_deref())
+        .await?;
+
+    let updated_device = state.db.get_device_by_mac(&mac).await?.ok_or_else(|| {
+        crate::errors::DaemonError::Internal("Failed to retrieve updated device".to_string())
+    })?;
+
+    info!(
+        "Updated presence probes for device {}: {:?}",
+        mac, updated_device.presence_probes
+    );
+
+    Ok(Json(with_details(&state.db, updated_device).await?))
+}
+
+async fn update_device_pinned(
+    State(state): State<AppState>,
+    Path(mac): Path<String>,
+    Json(request): Json<DevicePinnedRequest>,
+) -> Result<Json<Device>> {
+    let device =
+        state.db.get_device_by_mac(&mac).await?.ok_or_else(|| {
+            crate::errors::DaemonError::NotFound(format!("Device {} not found", mac))
+        })?;
+
+    state
+        .db
+        .update_device_pinned(&device.mac_address, request.pinned)
+        .await?;
+
+    let updated_device = state.db.get_device_by_mac(&mac).await?.ok_or_else(|| {
+        crate::errors::DaemonError::Internal("Failed to retrieve updated device".to_string())
+    })?;
+
+    info!("Set pinned for device {} to {}", mac, updated_device.pinned);
+
+    Ok(Json(with_details(&state.db, updated_device).await?))
 }
 
 async fn get_rules(State(state): State<AppState>) -> Result<Json<RulesResponse>> {
@@ -251,6 +354,11 @@ async fn create_rule(
         description: request.description,
         trigger_type: request.trigger_type,
         mac_filter: request.mac_filter,
+        segment_filter: request.segment_filter,
+        vendor_filter: request.vendor_filter,
+        os_family_filter: request.os_family_filter,
+        device_type_filter: request.device_type_filter,
+        tcp_os_filter: request.tcp_os_filter,
         enabled: request.enabled,
         notification_channels: request.notification_channels,
         created_at: now,
@@ -284,6 +392,11 @@ async fn update_rule(
         description: request.description,
         trigger_type: request.trigger_type,
         mac_filter: request.mac_filter,
+        segment_filter: request.segment_filter,
+        vendor_filter: request.vendor_filter,
+        os_family_filter: request.os_family_filter,
+        device_type_filter: request.device_type_filter,
+        tcp_os_filter: request.tcp_os_filter,
         enabled: request.enabled,
         notification_channels: request.notification_channels,
         created_at: existing.created_at,
@@ -331,6 +444,22 @@ async fn update_config(
     let mut config = state.config.write().await;
 
     if let Some(daemon_config) = request.daemon {
+        if let Some(source) = &daemon_config.source {
+            Daemon::validate_source(source)?;
+        }
+        if let Some(filter) = &daemon_config.capture_filter {
+            Daemon::validate_capture_filter(filter, daemon_config.source.as_deref())?;
+        }
+
+        if let Some(daemon) = &state.daemon
+            && daemon_config.capture_filter != config.daemon.capture_filter
+        {
+            daemon
+                .set_capture_filter(daemon_config.capture_filter.clone())
+                .await?;
+            info!("Capture filter updated, restarting packet capture");
+        }
+
         config.daemon = daemon_config;
         info!("Updated daemon configuration (requires restart)");
     }
@@ -509,6 +638,22 @@ async fn get_metrics(State(state): State<AppState>) -> Result<Json<Metrics>> {
     }))
 }
 
+async fn start_scan(State(state): State<AppState>) -> Result<(StatusCode, Json<SuccessResponse>)> {
+    let daemon = state
+        .daemon
+        .as_ref()
+        .ok_or_else(|| DaemonError::Conflict("Daemon is not running".to_string()))?;
+    daemon.start_arp_sweep()?;
+    info!("ARP sweep requested via API");
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(SuccessResponse {
+            message: "Scan started".to_string(),
+        }),
+    ))
+}
+
 async fn restart_daemon() -> Result<Json<SuccessResponse>> {
     info!("Restart requested via API");
 
@@ -528,3 +673,38 @@ async fn get_logs(State(state): State<AppState>) -> Result<Json<LogsResponse>> {
 
     Ok(Json(LogsResponse { logs, count }))
 }
+
+async fn get_ip_conflicts(State(state): State<AppState>) -> Result<Json<IpConflictsResponse>> {
+    let conflicts = state
+        .db
+        .get_ip_conflicts_since(Utc::now() - IP_CONFLICT_ACTIVE)
+        .await?;
+    let count = conflicts.len();
+
+    Ok(Json(IpConflictsResponse { conflicts, count }))
+}
+
+async fn get_leases(State(state): State<AppState>) -> Result<Json<LeasesResponse>> {
+    let mut leases: Vec<_> = state
+        .db
+        .get_all_device_leases()
+        .await?
+        .into_values()
+        .collect();
+    leases.sort_by_key(|lease| std::cmp::Reverse(lease.renewed_at));
+    let count = leases.len();
+
+    Ok(Json(LeasesResponse { leases, count }))
+}
+
+async fn get_unknown_fingerprints(
+    State(state): State<AppState>,
+) -> Result<Json<UnknownFingerprintsResponse>> {
+    let fingerprints = state.db.get_unknown_fingerprints().await?;
+    let count = fingerprints.len();
+
+    Ok(Json(UnknownFingerprintsResponse {
+        fingerprints,
+        count,
+    }))
+}
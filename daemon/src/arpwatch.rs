@@ -0,0 +1,253 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Window for counting gratuitous ARP packets.
+const GRATUITOUS_WINDOW: TimeDelta = TimeDelta::minutes(1);
+/// Window for counting the addresses one MAC claims.
+const CLAIM_WINDOW: TimeDelta = TimeDelta::minutes(10);
+/// Two MACs claiming one address within this window are in conflict.
+const CONFLICT_WINDOW: TimeDelta = TimeDelta::minutes(2);
+/// An ongoing attack raises one alert per cooldown rather than one per packet.
+const ALERT_COOLDOWN: TimeDelta = TimeDelta::minutes(10);
+/// How long the default gateway and DNS servers read from the system are
+/// trusted before they are read again.
+const SYSTEM_IPS_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// Something suspicious seen in ARP traffic, attributed to the sender.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArpAlert {
+    /// The sender claims `ip`, which belongs to `expected`.
+    Spoofing { ip: Ipv4Addr, expected: String },
+    /// The sender sent `count` gratuitous ARP packets within a minute.
+    GratuitousFlood { count: usize },
+    /// The sender claimed all of `ips` within ten minutes.
+    MultipleIps { ips: Vec<Ipv4Addr> },
+    /// The sender claimed or probed for `ip` while `holder` was using it.
+    Conflict { ip: Ipv4Addr, holder: String },
+}
+
+/// ARP state kept across packets: learned bindings of watched addresses,
+/// the latest claimant of each address, recent gratuitous ARP packets and
+/// recent claims per MAC. It only decides what is suspicious; logging and
+/// notifying is up to the caller.
+pub struct ArpWatch {
+    configured_ips: Vec<Ipv4Addr>,
+    system_ips: Vec<Ipv4Addr>,
+    system_ips_read: Option<Instant>,
+    gratuitous_threshold: usize,
+    max_ips_per_mac: usize,
+    /// First MAC seen for each watched address.
+    bindings: HashMap<Ipv4Addr, String>,
+    /// Last MAC to claim each address, and when.
+    holders: HashMap<Ipv4Addr, (String, DateTime<Utc>)>,
+    gratuitous: HashMap<String, VecDeque<DateTime<Utc>>>,
+    claims: HashMap<String, HashMap<Ipv4Addr, DateTime<Utc>>>,
+    alerted: HashMap<String, DateTime<Utc>>,
+}
+
+impl ArpWatch {
+    pub fn new(
+        configured_ips: Vec<Ipv4Addr>,
+        gratuitous_threshold: u32,
+        max_ips_per_mac: u32,
+    ) -> Self {
+        Self {
+            configured_ips,
+            system_ips: Vec::new(),
+            system_ips_read: None,
+            gratuitous_threshold: gratuitous_threshold as usize,
+            max_ips_per_mac: max_ips_per_mac as usize,
+            bindings: HashMap::new(),
+            holders: HashMap::new(),
+            gratuitous: HashMap::new(),
+            claims: HashMap::new(),
+            alerted: HashMap::new(),
+        }
+    }
+
+    /// Checks an ARP packet from `mac`. `pinned` is the MAC of the pinned
+    /// device that owns `sender_ip`, if another device does. Conflicts are
+    /// reported on every packet; the caller decides what is new.
+    pub fn observe(
+        &mut self,
+        mac: &str,
+        sender_ip: Ipv4Addr,
+        target_ip: Ipv4Addr,
+        pinned: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Vec<ArpAlert> {
+        self.prune(now);
+
+        // An ARP probe (RFC 5227) claims nothing yet, but probing for an
+        // address in use means the sender is about to take it.
+        if sender_ip.is_unspecified() {
+            return self
+                .conflict(mac, target_ip)
+                .map(|holder| ArpAlert::Conflict {
+                    ip: target_ip,
+                    holder,
+                })
+                .into_iter()
+                .collect();
+        }
+
+        let mut alerts = Vec::new();
+
+        if let Some(holder) = self.conflict(mac, sender_ip) {
+            alerts.push(ArpAlert::Conflict {
+                ip: sender_ip,
+                holder,
+            });
+        }
+        self.holders.insert(sender_ip, (mac.to_string(), now));
+
+        let expected = match pinned {
+            Some(pinned) => Some(pinned.to_string()),
+            None if self.is_watched(sender_ip) => Some(
+                self.bindings
+                    .entry(sender_ip)
+                    .or_insert_with(|| {
+                        debug!("Learned ARP binding {} -> {}", sender_ip, mac);
+                        mac.to_string()
+                    })
+                    .clone(),
+            ),
+            None => None,
+        };
+        if let Some(expected) = expected
+            && !expected.eq_ignore_ascii_case(mac)
+            && self.should_alert(format!("spoofing {} {}", sender_ip, mac), now)
+        {
+            alerts.push(ArpAlert::Spoofing {
+                ip: sender_ip,
+                expected,
+            });
+        }
+
+        if sender_ip == target_ip && self.gratuitous_threshold > 0 {
+            let sent = self.gratuitous.entry(mac.to_string()).or_default();
+            sent.push_back(now);
+            let count = sent.len();
+            if count > self.gratuitous_threshold
+                && self.should_alert(format!("gratuitous {}", mac), now)
+            {
+                alerts.push(ArpAlert::GratuitousFlood { count });
+            }
+        }
+
+        if self.max_ips_per_mac > 0 {
+            let claimed = self.claims.entry(mac.to_string()).or_default();
+            claimed.insert(sender_ip, now);
+            if claimed.len() > self.max_ips_per_mac {
+                let mut ips: Vec<Ipv4Addr> = claimed.keys().copied().collect();
+                ips.sort();
+                if self.should_alert(format!("claims {}", mac), now) {
+                    alerts.push(ArpAlert::MultipleIps { ips });
+                }
+            }
+        }
+
+        alerts
+    }
+
+    /// The MAC other than `mac` that recently claimed `ip`, if any.
+    fn conflict(&self, mac: &str, ip: Ipv4Addr) -> Option<String> {
+        self.holders
+            .get(&ip)
+            .filter(|(holder, _)| !holder.eq_ignore_ascii_case(mac))
+            .map(|(holder, _)| holder.clone())
+    }
+
+    /// The default gateway, the DNS servers and the configured addresses.
+    fn is_watched(&mut self, ip: Ipv4Addr) -> bool {
+        if self
+            .system_ips_read
+            .is_none_or(|read| read.elapsed() >= SYSTEM_IPS_MAX_AGE)
+        {
+            self.system_ips = system_watch_ips();
+            self.system_ips_read = Some(Instant::now());
+        }
+
+        self.configured_ips.contains(&ip) || self.system_ips.contains(&ip)
+    }
+
+    fn should_alert(&mut self, key: String, now: DateTime<Utc>) -> bool {
+        if self
+            .alerted
+            .get(&key)
+            .is_some_and(|last| now - *last < ALERT_COOLDOWN)
+        {
+            return false;
+        }
+        self.alerted.insert(key, now);
+        true
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>) {
+        self.gratuitous.retain(|_, sent| {
+            while sent.front().is_some_and(|at| now - *at > GRATUITOUS_WINDOW) {
+                sent.pop_front();
+            }
+            !sent.is_empty()
+        });
+        self.claims.retain(|_, claimed| {
+            claimed.retain(|_, at| now - *at <= CLAIM_WINDOW);
+            !claimed.is_empty()
+        });
+        self.holders
+            .retain(|_, (_, at)| now - *at <= CONFLICT_WINDOW);
+        self.alerted.retain(|_, at| now - *at < ALERT_COOLDOWN);
+    }
+}
+
+/// IPv4 default gateways from /proc/net/route and IPv4 name servers from
+/// /etc/resolv.conf. Loopback resolvers such as systemd-resolved's stub are
+/// left out: nothing on the network can claim them.
+pub fn system_watch_ips() -> Vec<Ipv4Addr> {
+    let mut ips = std::fs::read_to_string("/proc/net/route")
+        .map(|routes| default_gateways(&routes))
+        .unwrap_or_default();
+
+    if let Ok(resolv_conf) = std::fs::read_to_string("/etc/resolv.conf") {
+        for ip in name_servers(&resolv_conf) {
+            if !ips.contains(&ip) {
+                ips.push(ip);
+            }
+        }
+    }
+
+    ips
+}
+
+/// Gateways of the default routes in /proc/net/route, which prints each
+/// address as the hex value of its network-order bytes read natively.
+fn default_gateways(routes: &str) -> Vec<Ipv4Addr> {
+    routes
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(1) != Some(&"00000000") {
+                return None;
+            }
+            let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+            let gateway = Ipv4Addr::from(gateway.to_ne_bytes());
+            (!gateway.is_unspecified()).then_some(gateway)
+        })
+        .collect()
+}
+
+fn name_servers(resolv_conf: &str) -> Vec<Ipv4Addr> {
+    resolv_conf
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            (fields.next() == Some("nameserver")).then_some(())?;
+            fields.next()?.parse::<Ipv4Addr>().ok()
+        })
+        .filter(|ip| !ip.is_loopback())
+        .collect()
+}
@@ -1,157 +1,750 @@
 use async_channel::{Receiver, Sender};
-use chrono::Utc;
-use pcap::{Capture, Device as PcapDevice};
+use chrono::{DateTime, Utc};
+use futures::stream::{StreamExt, TryStreamExt};
+use netlink_packet_core::NetlinkPayload;
+use netlink_packet_route::RouteNetlinkMessage;
+use netlink_packet_route::neighbour::{
+    NeighbourAddress, NeighbourAttribute, NeighbourMessage, NeighbourState,
+};
+use netlink_sys::{AsyncSocket, SocketAddr};
+use pnet::packet::Packet;
 use pnet::packet::arp::ArpPacket;
 use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
-use pnet::packet::Packet;
+use pnet::packet::icmpv6::Icmpv6Packet;
+use pnet::packet::icmpv6::Icmpv6Types;
+use pnet::packet::icmpv6::ndp::{
+    NdpOptionTypes, NeighborAdvertFlags, NeighborAdvertPacket, NeighborSolicitPacket,
+    RouterAdvertPacket, RouterSolicitPacket,
+};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv4::Ipv4Packet;
+use pnet::packet::ipv6::Ipv6Packet;
+use pnet::packet::udp::UdpPacket;
+use pnet::util::MacAddr;
+use rtnetlink::constants::RTMGRP_NEIGH;
 use rtnetlink::new_connection;
-use std::net::IpAddr;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Duration;
 use tokio::sync::RwLock;
 use tokio::time::interval;
 use tracing::{debug, error, info, warn};
 
+use crate::arpwatch::{ArpAlert, ArpWatch};
 use crate::db::Database;
 use crate::errors::{DaemonError, Result};
-use crate::models::{Device, DeviceStatus, NetworkEvent, NotificationEvent, Rule, TriggerType};
+use crate::fingerprint::DhcpFingerprints;
+use crate::leasefile::{DirectoryWatcher, LeaseEntry, LeaseFileFormat};
+use crate::models::{
+    AddressFamily, AddressSource, DaemonConfig, DegradedInterface, Device, DeviceAddress,
+    DeviceStatus, DeviceTypeSource, DhcpLease, DhcpMessageType, DiscoveryProtocol, EventOrigin,
+    INFRASTRUCTURE_DEVICE_TYPES, IdentityHints, LinkDiscovery, NeighborState, NetworkEvent,
+    NotificationEvent, PresenceProbe, Rule, TriggerType, UpnpInfo,
+};
+use crate::neighbors::{self, NeighborTable};
 use crate::notifier::Notifier;
+use crate::oui::{OuiRegistry, VendorLookup};
+use crate::probe::{self, ProbeTarget};
+use crate::protocols::{cdp, dhcp, lldp, llmnr, mdns, nbns, ndp, ssdp, tcp, vlan};
+use crate::scanner;
+use crate::signatures::SignatureDatabase;
+use crate::source::{NextFrame, PacketSource, SourceKind};
+use crate::tcpfingerprint::TcpSignatures;
+
+macro_rules! untagged_capture_filter {
+    () => {
+        "arp or (udp port 67 or udp port 68) or udp port 5353 or udp port 1900 or udp port 137 or udp port 5355 or (icmp6 and ip6[40] >= 133 and ip6[40] <= 136) or ether proto 0x88cc or ether dst 01:00:0c:cc:cc:cc"
+    };
+}
+
+/// BPF expression used when `capture_filter` is not configured. `vlan`
+/// shifts the offsets of everything after it, so the untagged match comes
+/// first, followed by single-tagged and double-tagged (QinQ) frames.
+pub const DEFAULT_CAPTURE_FILTER: &str = concat!(
+    untagged_capture_filter!(),
+    " or (vlan and (",
+    untagged_capture_filter!(),
+    " or (vlan and (",
+    untagged_capture_filter!(),
+    "))))"
+);
+
+macro_rules! tcp_syn_capture_filter {
+    () => {
+        "(tcp[tcpflags] & (tcp-syn|tcp-ack) == tcp-syn) or (ip6 and tcp and ip6[53] & 0x12 == 0x02)"
+    };
+}
+
+/// TCP SYNs without ACK, over IPv4 or over IPv6 without extension headers,
+/// in untagged frames. Put in front of a custom `capture_filter` with
+/// `tcp_fingerprinting`; it has no `vlan`, so the offsets in the custom
+/// filter stay as they are.
+pub const TCP_SYN_CAPTURE_FILTER: &str = tcp_syn_capture_filter!();
+
+/// [`DEFAULT_CAPTURE_FILTER`] widened to TCP SYNs, used with
+/// `tcp_fingerprinting`. SYNs are matched at every tagging level, ahead of
+/// the `vlan` that moves on to the next one.
+pub const TCP_FINGERPRINTING_CAPTURE_FILTER: &str = concat!(
+    tcp_syn_capture_filter!(),
+    " or ",
+    untagged_capture_filter!(),
+    " or (vlan and (",
+    tcp_syn_capture_filter!(),
+    " or ",
+    untagged_capture_filter!(),
+    " or (vlan and (",
+    tcp_syn_capture_filter!(),
+    " or ",
+    untagged_capture_filter!(),
+    "))))"
+);
+
+/// A segment paired with its packet source, or with the reason the source
+/// could not be opened.
+type OpenedSource = (String, Result<Box<dyn PacketSource>>);
 
 pub struct Daemon {
     db: Database,
     notifier: Arc<RwLock<Notifier>>,
-    interface: String,
+    /// Monitored interfaces; each one is a separate segment.
+    interfaces: Vec<String>,
+    capture_filter: RwLock<Option<String>>,
+    /// Bumped whenever the capture filter changes so that every running
+    /// capture re-applies it.
+    capture_generation: Arc<AtomicU64>,
     device_timeout: Duration,
     neighbor_check_interval: Duration,
     packets_captured: Arc<std::sync::atomic::AtomicU64>,
     log_cleanup_enabled: bool,
     log_retention_days: i64,
+    /// IPv6 routers seen so far, keyed by MAC; `false` marks a rogue router.
+    ipv6_routers: RwLock<HashMap<String, bool>>,
+    /// DHCP servers seen so far, keyed by MAC; `false` marks a rogue server.
+    dhcp_servers: RwLock<HashMap<String, bool>>,
+    /// Addresses of trusted DHCP servers and the MAC each is pinned to, or
+    /// `None` for a configured address no server has answered from yet.
+    /// Always locked after `dhcp_servers`.
+    dhcp_server_addresses: RwLock<HashMap<String, Option<String>>>,
+    /// MACs of relay agents allowed to forward DHCP replies.
+    dhcp_relays: HashSet<String>,
+    /// Last kernel neighbour state of each address, keyed by MAC.
+    neighbor_states: RwLock<HashMap<String, HashMap<IpAddr, NeighborState>>>,
+    source_kind: SourceKind,
+    /// Source handed in through `with_source`, used instead of `source_kind`.
+    packet_source: std::sync::Mutex<Option<Box<dyn PacketSource>>>,
+    replay_packet_time: bool,
+    /// Timestamp of the last replayed packet when packet time drives the clock.
+    replay_clock: std::sync::Mutex<Option<DateTime<Utc>>>,
+    oui: OuiRegistry,
+    http: reqwest::Client,
+    ssdp_fetch_descriptions: bool,
+    /// When a UPnP description fetch was last started, by device id.
+    ssdp_fetch_attempts: std::sync::Mutex<HashMap<i64, DateTime<Utc>>>,
+    netbios_query_unnamed: bool,
+    /// When a NetBIOS node status query was last sent, by device id.
+    netbios_queries: std::sync::Mutex<HashMap<i64, DateTime<Utc>>>,
+    arp_scan_interval: Option<Duration>,
+    arp_scan_rate: u32,
+    /// Set once `start` is capturing live, which active scanning requires.
+    live: AtomicBool,
+    scan_running: Arc<AtomicBool>,
+    presence_probes: Vec<PresenceProbe>,
+    arp_watch: std::sync::Mutex<ArpWatch>,
+    fingerprints: DhcpFingerprints,
+    lease_files: Vec<PathBuf>,
+    /// Hostnames from the lease files, by MAC, for devices that never told
+    /// foxd their name themselves.
+    lease_hostnames: std::sync::Mutex<HashMap<String, String>>,
+    /// Interfaces watched through the kernel neighbour table because packet
+    /// capture is unavailable on them.
+    degraded_interfaces: std::sync::Mutex<Vec<DegradedInterface>>,
+    tcp_fingerprinting: bool,
+    tcp_signatures: TcpSignatures,
+    /// Last TCP SYN signature handled per MAC, and when.
+    tcp_syns: std::sync::Mutex<HashMap<String, (String, DateTime<Utc>)>>,
 }
 
+/// Largest UPnP device description foxd will download.
+const MAX_UPNP_DESCRIPTION_LEN: usize = 64 * 1024;
+/// How long a fetched UPnP description is trusted before it is fetched again.
+const UPNP_DESCRIPTION_MAX_AGE: chrono::TimeDelta = chrono::TimeDelta::hours(24);
+/// Minimum time between two fetch attempts for the same device.
+const UPNP_FETCH_RETRY: chrono::TimeDelta = chrono::TimeDelta::hours(1);
+/// Minimum time between two NetBIOS node status queries to the same device.
+const NETBIOS_QUERY_RETRY: chrono::TimeDelta = chrono::TimeDelta::hours(1);
+/// How long to let a lease file settle after a change before reading it, as
+/// servers rewrite it in several writes.
+const LEASE_FILE_SETTLE: Duration = Duration::from_millis(500);
+/// Largest value of the Ethernet type field that is an 802.3 length.
+const MAX_802_3_LENGTH: u16 = 1500;
+/// Classification confidence of a device type announced over LLDP or CDP.
+const ANNOUNCED_CONFIDENCE: u8 = 100;
+/// Minimum time between two updates from the same TCP SYN signature of a
+/// device, which opens connections far more often than that.
+const TCP_SYN_RETRY: chrono::TimeDelta = chrono::TimeDelta::minutes(1);
+/// A device seen on several segments keeps the one it was on until it has
+/// not been seen there for this long.
+const SEGMENT_MOVE_AFTER: chrono::TimeDelta = chrono::TimeDelta::minutes(10);
+/// How long an IP conflict stays active after it was last seen.
+pub const IP_CONFLICT_ACTIVE: chrono::TimeDelta = chrono::TimeDelta::hours(1);
+
 impl Daemon {
-    pub fn new(
-        db: Database,
-        notifier: Notifier,
-        interface: String,
-        device_timeout_secs: u64,
-        neighbor_check_interval_secs: u64,
-        log_cleanup_enabled: bool,
-        log_retention_days: u64,
-    ) -> Self {
+    pub fn new(db: Database, notifier: Notifier, config: &DaemonConfig) -> Self {
         Self {
             db,
             notifier: Arc::new(RwLock::new(notifier)),
-            interface,
-            device_timeout: Duration::from_secs(device_timeout_secs),
-            neighbor_check_interval: Duration::from_secs(neighbor_check_interval_secs),
+            interfaces: config.monitored_interfaces(),
+            capture_filter: RwLock::new(config.capture_filter.clone()),
+            capture_generation: Arc::new(AtomicU64::new(0)),
+            device_timeout: Duration::from_secs(config.device_timeout_secs),
+            neighbor_check_interval: Duration::from_secs(config.neighbor_check_interval_secs),
             packets_captured: Arc::new(std::sync::atomic::AtomicU64::new(0)),
-            log_cleanup_enabled,
-            log_retention_days: log_retention_days as i64,
+            log_cleanup_enabled: config.log_cleanup_enabled,
+            log_retention_days: config.log_retention_days as i64,
+            ipv6_routers: RwLock::new(
+                config
+                    .trusted_ipv6_routers
+                    .iter()
+                    .map(|mac| (mac.to_lowercase(), true))
+                    .collect(),
+            ),
+            dhcp_servers: RwLock::new(
+                config
+                    .trusted_dhcp_servers
+                    .iter()
+                    .filter(|server| server.parse::<IpAddr>().is_err())
+                    .map(|mac| (mac.to_lowercase(), true))
+                    .collect(),
+            ),
+            dhcp_server_addresses: RwLock::new(
+                config
+                    .trusted_dhcp_servers
+                    .iter()
+                    .filter_map(|server| server.parse::<IpAddr>().ok())
+                    .map(|ip| (ip.to_string(), None))
+                    .collect(),
+            ),
+            dhcp_relays: config
+                .trusted_dhcp_relays
+                .iter()
+                .map(|mac| mac.to_lowercase())
+                .collect(),
+            neighbor_states: RwLock::new(HashMap::new()),
+            source_kind: config
+                .source
+                .as_deref()
+                .and_then(|source| source.parse().ok())
+                .unwrap_or_default(),
+            packet_source: std::sync::Mutex::new(None),
+            replay_packet_time: config.replay_packet_time,
+            replay_clock: std::sync::Mutex::new(None),
+            oui: OuiRegistry::embedded(),
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(5))
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap_or_default(),
+            ssdp_fetch_descriptions: config.ssdp_fetch_descriptions,
+            ssdp_fetch_attempts: std::sync::Mutex::new(HashMap::new()),
+            netbios_query_unnamed: config.netbios_query_unnamed,
+            netbios_queries: std::sync::Mutex::new(HashMap::new()),
+            arp_scan_interval: (config.arp_scan_interval_secs > 0)
+                .then(|| Duration::from_secs(config.arp_scan_interval_secs)),
+            arp_scan_rate: config.arp_scan_rate,
+            live: AtomicBool::new(false),
+            scan_running: Arc::new(AtomicBool::new(false)),
+            presence_probes: config.presence_probes.clone(),
+            arp_watch: std::sync::Mutex::new(ArpWatch::new(
+                config
+                    .arp_watch_ips
+                    .iter()
+                    .filter_map(|ip| match ip {
+                        IpAddr::V4(ip) => Some(*ip),
+                        IpAddr::V6(_) => None,
+                    })
+                    .collect(),
+                config.gratuitous_arp_threshold,
+                config.arp_max_ips_per_mac,
+            )),
+            fingerprints: DhcpFingerprints::load(config.dhcp_fingerprints_file.as_deref()),
+            lease_files: config.lease_files.clone(),
+            lease_hostnames: std::sync::Mutex::new(HashMap::new()),
+            degraded_interfaces: std::sync::Mutex::new(Vec::new()),
+            tcp_fingerprinting: config.tcp_fingerprinting,
+            tcp_signatures: TcpSignatures::load(config.tcp_signatures_file.as_deref()),
+            tcp_syns: std::sync::Mutex::new(HashMap::new()),
         }
     }
 
-    pub async fn start(self: Arc<Self>) -> Result<()> {
-        info!("Starting Fox daemon on interface: {}", self.interface);
+    /// Replaces the embedded OUI registry, e.g. with one imported through
+    /// `foxd oui-refresh`.
+    pub fn set_oui_registry(&mut self, registry: OuiRegistry) {
+        self.oui = registry;
+    }
 
-        let (event_tx, event_rx) = async_channel::bounded(100);
+    /// Creates a daemon that reads frames from `source` instead of opening
+    /// the one named in the configuration.
+    pub fn with_source(
+        db: Database,
+        notifier: Notifier,
+        config: &DaemonConfig,
+        source: Box<dyn PacketSource>,
+    ) -> Self {
+        let daemon = Self::new(db, notifier, config);
+        *daemon.packet_source.lock().unwrap() = Some(source);
+        daemon
+    }
+
+    /// Checks that a configured `source` is one foxd knows how to read.
+    pub fn validate_source(source: &str) -> Result<()> {
+        source.parse::<SourceKind>().map(|_| ())
+    }
+
+    /// Current time as seen by the daemon: wall-clock time, or the timestamp
+    /// of the last replayed packet when replaying with packet time.
+    fn now(&self) -> DateTime<Utc> {
+        self.replay_clock.lock().unwrap().unwrap_or_else(Utc::now)
+    }
+
+    /// Checks a capture filter against the `source` setting it will be
+    /// applied with; see [`SourceKind::validate_filter`].
+    pub fn validate_capture_filter(filter: &str, source: Option<&str>) -> Result<()> {
+        source
+            .map(str::parse::<SourceKind>)
+            .transpose()?
+            .unwrap_or_default()
+            .validate_filter(filter)
+    }
+
+    /// Replaces the capture filter and has every running capture apply the
+    /// new expression.
+    pub async fn set_capture_filter(&self, filter: Option<String>) -> Result<()> {
+        if let Some(filter) = &filter {
+            self.source_kind.validate_filter(filter)?;
+        }
+
+        *self.capture_filter.write().await = filter;
+        self.capture_generation.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
 
-        let capture_handle = {
+    pub async fn start(self: Arc<Self>) -> Result<()> {
+        let (event_tx, event_rx) = async_channel::bounded(100);
+        let mut tasks = Vec::new();
+
+        let sources = self.open_sources()?;
+        // An interface that cannot be captured on is still a live network,
+        // but only a working live capture sees the answers to what foxd
+        // sends itself.
+        let is_live = sources
+            .iter()
+            .any(|(_, source)| source.as_ref().map_or(true, |source| source.is_live()));
+        let capturing_live = sources
+            .iter()
+            .any(|(_, source)| source.as_ref().is_ok_and(|source| source.is_live()));
+        self.live.store(capturing_live, Ordering::Relaxed);
+
+        for (segment, source) in sources {
             let daemon = Arc::clone(&self);
             let tx = event_tx.clone();
-            tokio::spawn(async move {
-                if let Err(e) = daemon.capture_packets(tx).await {
-                    error!("Packet capture error: {}", e);
+
+            let mut source = match source {
+                Ok(source) => source,
+                Err(e) => {
+                    self.enter_degraded_mode(&segment, &e).await;
+                    tasks.push((
+                        format!("Neighbor table polling on {}", segment),
+                        tokio::spawn(async move {
+                            if let Err(e) = daemon.poll_neighbor_table(segment, tx).await {
+                                error!("Neighbor table polling error: {}", e);
+                            }
+                        }),
+                    ));
+                    continue;
                 }
-            })
-        };
+            };
+
+            info!("Starting Fox daemon ({})", source.describe());
+
+            if source.is_live() {
+                tasks.push((
+                    format!("Packet capture on {}", segment),
+                    tokio::spawn(async move {
+                        if let Err(e) = daemon
+                            .capture_packets(source, segment.clone(), tx.clone())
+                            .await
+                        {
+                            error!("Packet capture error: {}", e);
+                            daemon.enter_degraded_mode(&segment, &e).await;
+                            if let Err(e) = daemon.poll_neighbor_table(segment, tx).await {
+                                error!("Neighbor table polling error: {}", e);
+                            }
+                        }
+                    }),
+                ));
+            } else {
+                tasks.push((
+                    "Packet replay".to_string(),
+                    tokio::spawn(async move {
+                        match daemon.replay(source.as_mut()).await {
+                            Ok(count) => info!("Replayed {} packets", count),
+                            Err(e) => error!("Packet replay error: {}", e),
+                        }
+                        // Keep serving the replayed state until shutdown.
+                        std::future::pending::<()>().await
+                    }),
+                ));
+            }
+        }
 
-        let netlink_handle = {
+        if is_live {
             let daemon = Arc::clone(&self);
             let tx = event_tx.clone();
-            tokio::spawn(async move {
-                if let Err(e) = daemon.monitor_netlink(tx).await {
-                    error!("Netlink monitoring error: {}", e);
-                }
-            })
-        };
+            tasks.push((
+                "Netlink monitoring".to_string(),
+                tokio::spawn(async move {
+                    if let Err(e) = daemon.monitor_netlink(tx).await {
+                        error!("Netlink monitoring error: {}", e);
+                    }
+                }),
+            ));
+        }
 
-        let processor_handle = {
+        if is_live && !self.lease_files.is_empty() {
             let daemon = Arc::clone(&self);
+            let tx = event_tx.clone();
+            tasks.push((
+                "Lease file watcher".to_string(),
+                tokio::spawn(async move {
+                    if let Err(e) = daemon.watch_lease_files(tx).await {
+                        error!("Lease file watcher error: {}", e);
+                    }
+                }),
+            ));
+        }
+
+        let daemon = Arc::clone(&self);
+        tasks.push((
+            "Event processor".to_string(),
             tokio::spawn(async move {
                 daemon.process_events(event_rx).await;
-            })
-        };
+            }),
+        ));
 
-        let timeout_handle = {
+        // Replays on packet time run their own timeout checks.
+        if is_live || !self.replay_packet_time {
             let daemon = Arc::clone(&self);
-            tokio::spawn(async move {
-                daemon.check_device_timeouts().await;
-            })
-        };
+            tasks.push((
+                "Timeout checker".to_string(),
+                tokio::spawn(async move {
+                    daemon.check_device_timeouts().await;
+                }),
+            ));
+        }
+
+        if capturing_live && let Some(scan_interval) = self.arp_scan_interval {
+            let daemon = Arc::clone(&self);
+            tasks.push((
+                "ARP sweep".to_string(),
+                tokio::spawn(async move {
+                    daemon.sweep_periodically(scan_interval).await;
+                }),
+            ));
+        }
 
-        let log_cleanup_handle = {
+        if capturing_live && self.netbios_query_unnamed {
             let daemon = Arc::clone(&self);
+            tasks.push((
+                "NetBIOS name queries".to_string(),
+                tokio::spawn(async move {
+                    daemon.query_unnamed_devices().await;
+                }),
+            ));
+        }
+
+        let daemon = Arc::clone(&self);
+        tasks.push((
+            "Log cleanup".to_string(),
             tokio::spawn(async move {
                 daemon.cleanup_old_logs().await;
-            })
-        };
+            }),
+        ));
 
-        tokio::select! {
-            _ = capture_handle => warn!("Packet capture task ended"),
-            _ = netlink_handle => warn!("Netlink monitoring task ended"),
-            _ = processor_handle => warn!("Event processor task ended"),
-            _ = timeout_handle => warn!("Timeout checker task ended"),
-            _ = log_cleanup_handle => warn!("Log cleanup task ended"),
-        }
+        let (_, index, _) =
+            futures::future::select_all(tasks.iter_mut().map(|(_, handle)| handle)).await;
+        warn!("{} task ended", tasks[index].0);
 
         Ok(())
     }
 
-    async fn capture_packets(&self, tx: Sender<NetworkEvent>) -> Result<()> {
-        info!("Starting packet capture on {}", self.interface);
+    /// The segment events from a replay or an injected source are attributed to.
+    fn primary_segment(&self) -> &str {
+        &self.interfaces[0]
+    }
 
-        let device = PcapDevice::list()?
-            .into_iter()
-            .find(|d| d.name == self.interface)
-            .ok_or_else(|| {
-                DaemonError::PacketCapture(format!("Interface {} not found", self.interface))
-            })?;
-
-        let mut cap = Capture::from_device(device)?
-            .promisc(true)
-            .timeout(1000)
-            .open()?;
-
-        cap.filter("arp or (udp port 67 or udp port 68)", true)?;
-
-        info!("Packet capture started, filter applied");
-
-        let packets_captured = Arc::clone(&self.packets_captured);
-        let tx_clone = tx.clone();
-
-        tokio::task::spawn_blocking(move || {
-            while let Ok(packet) = cap.next_packet() {
-                packets_captured.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-
-                if let Some(ethernet) = EthernetPacket::new(packet.data) {
-                    match ethernet.get_ethertype() {
-                        EtherTypes::Arp => {
-                            if let Some(arp) = ArpPacket::new(ethernet.payload()) {
-                                let event = Self::parse_arp_packet(&arp);
-                                if let Some(event) = event {
-                                    let _ = tx_clone.try_send(event);
-                                }
-                            }
+    /// Returns the source passed to `with_source`, or opens the configured
+    /// one on every monitored interface, paired with its segment. A pcap file
+    /// that cannot be read is fatal; a live capture that cannot be opened,
+    /// e.g. without `CAP_NET_RAW`, is returned as the error for its
+    /// interface so that the interface can be watched another way.
+    fn open_sources(&self) -> Result<Vec<OpenedSource>> {
+        let segment = self.primary_segment().to_string();
+
+        if let Some(source) = self.packet_source.lock().unwrap().take() {
+            return Ok(vec![(segment, Ok(source))]);
+        }
+
+        if let SourceKind::File(_) = self.source_kind {
+            return Ok(vec![(
+                segment.clone(),
+                Ok(self.source_kind.open(&segment)?),
+            )]);
+        }
+
+        Ok(self
+            .interfaces
+            .iter()
+            .map(|interface| (interface.clone(), self.source_kind.open(interface)))
+            .collect())
+    }
+
+    /// Records that `interface` lost packet capture, or never had it. Active
+    /// scanning stops once no interface is captured on, as its replies could
+    /// not be seen.
+    async fn enter_degraded_mode(&self, interface: &str, error: &DaemonError) {
+        warn!(
+            "Packet capture unavailable on {} ({}), tracking presence from the kernel neighbor table only",
+            interface, error
+        );
+
+        let now = self.now();
+        {
+            let mut degraded = self.degraded_interfaces.lock().unwrap();
+            degraded.retain(|degraded| degraded.interface != interface);
+            degraded.push(DegradedInterface {
+                interface: interface.to_string(),
+                reason: error.to_string(),
+                since: now,
+            });
+            if degraded.len() >= self.interfaces.len() {
+                self.live.store(false, Ordering::Relaxed);
+            }
+        }
+
+        let log_entry = crate::models::LogEntry {
+            id: None,
+            timestamp: now,
+            level: crate::models::LogLevel::Warning,
+            category: "system".to_string(),
+            message: format!(
+                "Packet capture unavailable on {}, running in reduced-fidelity mode",
+                interface
+            ),
+            details: Some(error.to_string()),
+        };
+        let _ = self.db.create_log(&log_entry).await;
+    }
+
+    /// Interfaces on which presence comes from the kernel neighbour table
+    /// only, as packet capture is unavailable there.
+    pub fn degraded_interfaces(&self) -> Vec<DegradedInterface> {
+        self.degraded_interfaces.lock().unwrap().clone()
+    }
+
+    /// The configured filter, or the default one, widened to TCP SYNs when
+    /// `tcp_fingerprinting` is on.
+    async fn current_filter(&self) -> String {
+        match (
+            self.capture_filter.read().await.clone(),
+            self.tcp_fingerprinting,
+        ) {
+            (Some(filter), true) => format!("({}) or ({})", TCP_SYN_CAPTURE_FILTER, filter),
+            (Some(filter), false) => filter,
+            (None, true) => TCP_FINGERPRINTING_CAPTURE_FILTER.to_string(),
+            (None, false) => DEFAULT_CAPTURE_FILTER.to_string(),
+        }
+    }
+
+    /// Feeds every frame of a recorded source through the same parsing and
+    /// event handling as a live capture, in order. Returns the number of
+    /// frames read.
+    pub async fn replay(&self, source: &mut dyn PacketSource) -> Result<u64> {
+        let filter = self.current_filter().await;
+        info!("Replaying {} (filter: {})", source.describe(), filter);
+        source.set_filter(&filter)?;
+
+        let check_interval = chrono::Duration::from_std(self.neighbor_check_interval)
+            .map_err(|e| DaemonError::Config(format!("Invalid check interval: {}", e)))?;
+        let mut next_timeout_check: Option<DateTime<Utc>> = None;
+        let mut replayed = 0;
+
+        loop {
+            let (timestamp, parsed) = match source.next_frame()? {
+                NextFrame::Frame(frame) => {
+                    let parsed = EthernetPacket::new(frame.data)
+                        .and_then(|ethernet| Self::parse_ethernet_frame(&ethernet));
+                    (frame.timestamp, parsed)
+                }
+                NextFrame::Idle => continue,
+                NextFrame::Exhausted => break,
+            };
+
+            replayed += 1;
+            self.packets_captured
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+            if self.replay_packet_time
+                && let Some(timestamp) = timestamp
+            {
+                *self.replay_clock.lock().unwrap() = Some(timestamp);
+
+                let due = next_timeout_check.get_or_insert(timestamp + check_interval);
+                if timestamp >= *due {
+                    *due = timestamp + check_interval;
+                    if let Err(e) = self.check_timeouts().await {
+                        error!("Error checking device timeouts: {}", e);
+                    }
+                }
+            }
+
+            if let Some((vlan_ids, event)) = parsed
+                && let Err(e) = self
+                    .handle_event(&EventOrigin::new(self.primary_segment(), &vlan_ids), event)
+                    .await
+            {
+                error!("Error handling event: {}", e);
+            }
+        }
+
+        Ok(replayed)
+    }
+
+    async fn capture_packets(
+        &self,
+        mut source: Box<dyn PacketSource>,
+        segment: String,
+        tx: Sender<(EventOrigin, NetworkEvent)>,
+    ) -> Result<()> {
+        loop {
+            let generation = self.capture_generation.load(Ordering::Relaxed);
+            let filter = self.current_filter().await;
+            source.set_filter(&filter)?;
+
+            info!(
+                "Packet capture started on {} (filter: {})",
+                source.describe(),
+                filter
+            );
+
+            let packets_captured = Arc::clone(&self.packets_captured);
+            let current_generation = Arc::clone(&self.capture_generation);
+            let segment_clone = segment.clone();
+            let tx_clone = tx.clone();
+
+            // Runs until the filter changes (Ok) or the capture fails (Err).
+            source = tokio::task::spawn_blocking(move || -> Result<Box<dyn PacketSource>> {
+                while current_generation.load(Ordering::Relaxed) == generation {
+                    let frame = match source.next_frame()? {
+                        NextFrame::Frame(frame) => frame,
+                        NextFrame::Idle => continue,
+                        NextFrame::Exhausted => {
+                            return Err(DaemonError::PacketCapture(format!(
+                                "{} stopped delivering packets",
+                                source.describe()
+                            )));
                         }
-                        _ => {}
+                    };
+
+                    packets_captured.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+                    if let Some(ethernet) = EthernetPacket::new(frame.data)
+                        && let Some((vlan_ids, event)) = Self::parse_ethernet_frame(&ethernet)
+                    {
+                        let origin = EventOrigin::new(&segment_clone, &vlan_ids);
+                        let _ = tx_clone.try_send((origin, event));
                     }
                 }
+
+                Ok(source)
+            })
+            .await
+            .map_err(|e| DaemonError::Internal(format!("Capture task error: {}", e)))??;
+
+            info!("Applying new capture filter on {}", segment);
+        }
+    }
+
+    /// Decodes a frame into an event, along with the VLAN IDs it was tagged
+    /// with (outermost first).
+    fn parse_ethernet_frame(ethernet: &EthernetPacket) -> Option<(Vec<u16>, NetworkEvent)> {
+        let frame = vlan::strip_tags(ethernet.get_ethertype(), ethernet.payload())?;
+
+        let event = match frame.ethertype {
+            EtherTypes::Arp => Self::parse_arp_packet(&ArpPacket::new(frame.payload)?),
+            EtherTypes::Ipv4 => {
+                let ipv4 = Ipv4Packet::new(frame.payload)?;
+                match ipv4.get_next_level_protocol() {
+                    IpNextHeaderProtocols::Udp => Self::parse_udp_packet(
+                        ethernet.get_source(),
+                        IpAddr::V4(ipv4.get_source()),
+                        &UdpPacket::new(ipv4.payload())?,
+                    ),
+                    IpNextHeaderProtocols::Tcp => Some(NetworkEvent::TcpSyn {
+                        source_mac: ethernet.get_source().to_string(),
+                        source_ip: IpAddr::V4(ipv4.get_source()),
+                        signature: tcp::ipv4_syn_signature(&ipv4)?,
+                    }),
+                    _ => None,
+                }
             }
-        })
-        .await
-        .map_err(|e| DaemonError::Internal(format!("Capture task error: {}", e)))?;
+            EtherTypes::Ipv6 => {
+                let ipv6 = Ipv6Packet::new(frame.payload)?;
+                if ipv6.get_next_header() == IpNextHeaderProtocols::Udp {
+                    Self::parse_udp_packet(
+                        ethernet.get_source(),
+                        IpAddr::V6(ipv6.get_source()),
+                        &UdpPacket::new(ipv6.payload())?,
+                    )
+                } else if ipv6.get_next_header() == IpNextHeaderProtocols::Tcp {
+                    Some(NetworkEvent::TcpSyn {
+                        source_mac: ethernet.get_source().to_string(),
+                        source_ip: IpAddr::V6(ipv6.get_source()),
+                        signature: tcp::ipv6_syn_signature(&ipv6)?,
+                    })
+                } else {
+                    Self::parse_ndp_packet(ethernet.get_source(), &ipv6)
+                }
+            }
+            EtherTypes::Lldp => {
+                let lldpdu = lldp::parse(frame.payload)?;
+                Some(NetworkEvent::LinkAnnouncement {
+                    device_mac: lldpdu
+                        .chassis_mac
+                        .unwrap_or(ethernet.get_source())
+                        .to_string(),
+                    announcement: lldpdu.announcement,
+                })
+            }
+            // CDP runs over 802.3 with LLC/SNAP, where the ethertype field is
+            // the payload length instead.
+            length
+                if length.0 <= MAX_802_3_LENGTH
+                    && ethernet.get_destination() == cdp::MULTICAST_MAC =>
+            {
+                let payload = &frame.payload[..frame.payload.len().min(length.0 as usize)];
+                Some(NetworkEvent::LinkAnnouncement {
+                    device_mac: ethernet.get_source().to_string(),
+                    announcement: cdp::parse(payload)?,
+                })
+            }
+            _ => None,
+        }?;
 
-        Ok(())
+        Some((frame.vlan_ids, event))
     }
 
     fn parse_arp_packet(arp: &ArpPacket) -> Option<NetworkEvent> {
@@ -166,6 +759,7 @@ impl Daemon {
         );
 
         let source_ip = IpAddr::from(arp.get_sender_proto_addr());
+        let target_ip = IpAddr::from(arp.get_target_proto_addr());
 
         let operation = arp.get_operation();
 
@@ -173,198 +767,2109 @@ impl Daemon {
             1 => Some(NetworkEvent::ArpRequest {
                 source_mac,
                 source_ip,
+                target_ip,
             }),
             2 => Some(NetworkEvent::ArpReply {
                 source_mac,
                 source_ip,
+                target_ip,
             }),
             _ => None,
         }
     }
 
-    async fn monitor_netlink(&self, _tx: Sender<NetworkEvent>) -> Result<()> {
-        info!("Starting netlink neighbor monitoring");
-
-        let (connection, handle, _messages) = new_connection()?;
-        tokio::spawn(connection);
+    fn parse_ndp_packet(ethernet_source: MacAddr, ipv6: &Ipv6Packet) -> Option<NetworkEvent> {
+        if ipv6.get_next_header() != IpNextHeaderProtocols::Icmpv6
+            || ipv6.get_hop_limit() != ndp::NDP_HOP_LIMIT
+        {
+            return None;
+        }
 
-        let mut check_interval = interval(self.neighbor_check_interval);
+        let icmpv6 = Icmpv6Packet::new(ipv6.payload())?;
+        let source = ipv6.get_source();
+        let source_ip = (!source.is_unspecified()).then_some(IpAddr::V6(source));
+
+        match icmpv6.get_icmpv6_type() {
+            Icmpv6Types::NeighborSolicit => {
+                let ns = NeighborSolicitPacket::new(ipv6.payload())?;
+                let source_mac =
+                    ndp::link_layer_address(&ns.get_options(), NdpOptionTypes::SourceLLAddr)
+                        .unwrap_or(ethernet_source);
+
+                Some(NetworkEvent::NeighborSolicitation {
+                    source_mac: source_mac.to_string(),
+                    source_ip,
+                    target_ip: IpAddr::V6(ns.get_target_addr()),
+                })
+            }
+            Icmpv6Types::NeighborAdvert => {
+                let na = NeighborAdvertPacket::new(ipv6.payload())?;
+                let source_mac =
+                    ndp::link_layer_address(&na.get_options(), NdpOptionTypes::TargetLLAddr)
+                        .unwrap_or(ethernet_source);
+
+                Some(NetworkEvent::NeighborAdvertisement {
+                    source_mac: source_mac.to_string(),
+                    target_ip: IpAddr::V6(na.get_target_addr()),
+                    is_router: na.get_flags() & NeighborAdvertFlags::Router != 0,
+                })
+            }
+            Icmpv6Types::RouterSolicit => {
+                let rs = RouterSolicitPacket::new(ipv6.payload())?;
+                let source_mac =
+                    ndp::link_layer_address(&rs.get_options(), NdpOptionTypes::SourceLLAddr)
+                        .unwrap_or(ethernet_source);
+
+                Some(NetworkEvent::RouterSolicitation {
+                    source_mac: source_mac.to_string(),
+                    source_ip,
+                })
+            }
+            Icmpv6Types::RouterAdvert => {
+                let ra = RouterAdvertPacket::new(ipv6.payload())?;
+                let options = ra.get_options();
+                let source_mac = ndp::link_layer_address(&options, NdpOptionTypes::SourceLLAddr)
+                    .unwrap_or(ethernet_source);
+
+                Some(NetworkEvent::RouterAdvertisement {
+                    source_mac: source_mac.to_string(),
+                    source_ip: source_ip?,
+                    router_lifetime_secs: ra.get_lifetime(),
+                    prefixes: ndp::prefixes(&options),
+                })
+            }
+            _ => None,
+        }
+    }
 
-        loop {
-            check_interval.tick().await;
+    fn upnp_description_stale(
+        previous: Option<&UpnpInfo>,
+        location: &str,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let Some(previous) = previous else {
+            return true;
+        };
+        previous.location.as_deref() != Some(location)
+            || previous
+                .description_fetched_at
+                .is_none_or(|fetched_at| now - fetched_at > UPNP_DESCRIPTION_MAX_AGE)
+    }
 
-            let mut neighbor_stream = handle.neighbours().get().execute();
-            use futures::stream::TryStreamExt;
+    /// Downloads the description document at `location` in the background
+    /// and stores the device's friendly name, manufacturer and model. Only
+    /// plain HTTP URLs on the announcing host are followed, so a forged
+    /// announcement cannot point foxd at another machine.
+    fn fetch_upnp_description(
+        &self,
+        device_id: i64,
+        source_ip: IpAddr,
+        location: String,
+        now: DateTime<Utc>,
+    ) {
+        let Ok(url) = reqwest::Url::parse(&location) else {
+            debug!("Ignoring unparseable UPnP location {}", location);
+            return;
+        };
+        let host = url
+            .host_str()
+            .and_then(|host| host.trim_matches(['[', ']']).parse::<IpAddr>().ok());
+        if url.scheme() != "http" || host != Some(source_ip) {
+            debug!(
+                "Not fetching UPnP location {} announced by {}",
+                location, source_ip
+            );
+            return;
+        }
 
-            while let Some(msg) = neighbor_stream.try_next().await.ok().flatten() {
-                debug!("Neighbor table entry: ifindex={}", msg.header.ifindex);
-                // Further parsing can be added when needed
+        {
+            let mut attempts = self.ssdp_fetch_attempts.lock().unwrap();
+            if attempts
+                .get(&device_id)
+                .is_some_and(|&attempted| now - attempted < UPNP_FETCH_RETRY)
+            {
+                return;
             }
+            attempts.insert(device_id, now);
         }
-    }
 
-    async fn process_events(&self, rx: Receiver<NetworkEvent>) {
-        info!("Event processor started");
+        let db = self.db.clone();
+        let client = self.http.clone();
+        tokio::spawn(async move {
+            let description = match Self::download_upnp_description(&client, url).await {
+                Ok(description) => description,
+                Err(e) => {
+                    debug!("Failed to fetch UPnP description {}: {}", location, e);
+                    return;
+                }
+            };
 
-        while let Ok(event) = rx.recv().await {
-            if let Err(e) = self.handle_event(event).await {
-                error!("Error handling event: {}", e);
+            if let Err(e) = db
+                .update_device_upnp_description(
+                    device_id,
+                    description.friendly_name.as_deref(),
+                    description.manufacturer.as_deref(),
+                    description.model_name.as_deref(),
+                    now,
+                )
+                .await
+            {
+                warn!("Failed to store UPnP description: {}", e);
+            }
+        });
+    }
+
+    async fn download_upnp_description(
+        client: &reqwest::Client,
+        url: reqwest::Url,
+    ) -> Result<ssdp::DeviceDescription> {
+        let mut response = client.get(url).send().await?.error_for_status()?;
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() > MAX_UPNP_DESCRIPTION_LEN {
+                return Err(DaemonError::Internal(
+                    "UPnP description is too large".to_string(),
+                ));
             }
         }
+
+        Ok(ssdp::parse_description(&String::from_utf8_lossy(&body)))
     }
 
-    async fn handle_event(&self, event: NetworkEvent) -> Result<()> {
-        match event {
-            NetworkEvent::ArpRequest {
-                source_mac,
-                source_ip,
-                ..
+    fn parse_udp_packet(
+        ethernet_source: MacAddr,
+        source_ip: IpAddr,
+        udp: &UdpPacket,
+    ) -> Option<NetworkEvent> {
+        match (udp.get_source(), udp.get_destination()) {
+            (dhcp::CLIENT_PORT, dhcp::SERVER_PORT) if source_ip.is_ipv4() => {
+                Self::parse_dhcp_packet(udp)
             }
-            | NetworkEvent::ArpReply {
-                source_mac,
-                source_ip,
-            } => {
-                self.handle_device_activity(&source_mac, Some(source_ip))
-                    .await?;
+            (dhcp::SERVER_PORT, dhcp::CLIENT_PORT) if source_ip.is_ipv4() => {
+                Self::parse_dhcp_reply(ethernet_source, source_ip, udp)
             }
-            NetworkEvent::DhcpRequest {
-                client_mac,
-                requested_ip,
-            } => {
-                self.handle_device_activity(&client_mac, requested_ip)
-                    .await?;
+            // Relay agents talk to the server from port 67 to port 67, in both
+            // directions.
+            (dhcp::SERVER_PORT, dhcp::SERVER_PORT) if source_ip.is_ipv4() => {
+                Self::parse_dhcp_packet(udp)
+                    .or_else(|| Self::parse_dhcp_reply(ethernet_source, source_ip, udp))
+            }
+            (mdns::PORT, _) => Self::parse_mdns_packet(ethernet_source, source_ip, udp),
+            // NOTIFYs are sent to the SSDP port, search responses come from it.
+            (ssdp::PORT, _) | (_, ssdp::PORT) => {
+                Self::parse_ssdp_packet(ethernet_source, source_ip, udp)
             }
-            NetworkEvent::NeighborAdded { mac, ip, .. }
-            | NetworkEvent::NeighborUpdated { mac, ip, .. } => {
-                self.handle_device_activity(&mac, Some(ip)).await?;
+            (nbns::PORT, _) if source_ip.is_ipv4() => {
+                Self::parse_nbns_packet(ethernet_source, source_ip, udp)
             }
-            NetworkEvent::NeighborRemoved { mac, .. } => {
-                self.handle_device_disconnection(&mac).await?;
+            (llmnr::PORT, _) => {
+                let name = llmnr::responder_name(udp.payload(), source_ip)?;
+                Some(NetworkEvent::LlmnrResponse {
+                    source_mac: ethernet_source.to_string(),
+                    source_ip,
+                    name,
+                })
             }
+            _ => None,
         }
+    }
 
-        Ok(())
+    fn parse_nbns_packet(
+        ethernet_source: MacAddr,
+        source_ip: IpAddr,
+        udp: &UdpPacket,
+    ) -> Option<NetworkEvent> {
+        let source_mac = ethernet_source.to_string();
+
+        match nbns::parse(udp.payload())? {
+            // A name server or proxy may answer on behalf of another host.
+            nbns::Message::Claim { name, address } if IpAddr::V4(address) == source_ip => {
+                Some(NetworkEvent::NetbiosName {
+                    source_mac,
+                    source_ip,
+                    name,
+                })
+            }
+            nbns::Message::Claim { .. } => None,
+            nbns::Message::NodeStatus { name } => Some(NetworkEvent::NetbiosName {
+                source_mac,
+                source_ip,
+                name,
+            }),
+            nbns::Message::Conflict { name } => Some(NetworkEvent::NetbiosConflict {
+                source_mac,
+                source_ip,
+                name,
+            }),
+        }
     }
 
-    async fn handle_device_activity(&self, mac: &str, ip: Option<IpAddr>) -> Result<()> {
-        let now = Utc::now();
+    fn parse_ssdp_packet(
+        ethernet_source: MacAddr,
+        source_ip: IpAddr,
+        udp: &UdpPacket,
+    ) -> Option<NetworkEvent> {
+        let notify = ssdp::parse(udp.payload())?;
+
+        Some(NetworkEvent::SsdpNotify {
+            source_mac: ethernet_source.to_string(),
+            source_ip,
+            uuid: notify.uuid(),
+            device_type: notify.device_type(),
+            server: notify.server,
+            location: notify.location,
+        })
+    }
 
-        let existing_device = self.db.get_device_by_mac(mac).await?;
+    fn parse_mdns_packet(
+        ethernet_source: MacAddr,
+        source_ip: IpAddr,
+        udp: &UdpPacket,
+    ) -> Option<NetworkEvent> {
+        let records = mdns::parse(udp.payload())?;
+        let announcement = mdns::announcement(&records, source_ip);
+
+        Some(NetworkEvent::MdnsResponse {
+            source_mac: ethernet_source.to_string(),
+            source_ip,
+            hostname: announcement.hostname,
+            services: announcement.services,
+        })
+    }
 
-        let is_new = existing_device.is_none();
-        let old_status = existing_device
-            .as_ref()
-            .map(|d| d.status.clone())
-            .unwrap_or(DeviceStatus::Unknown);
+    /// Decodes a client message, sent by the client itself or forwarded by a
+    /// relay agent.
+    fn parse_dhcp_packet(udp: &UdpPacket) -> Option<NetworkEvent> {
+        let message = dhcp::parse(udp.payload())?;
+        if !message.is_client_message {
+            return None;
+        }
 
-        let device = Device {
-            id: existing_device.as_ref().and_then(|d| d.id),
-            mac_address: mac.to_string(),
-            ip_address: ip.map(|i| i.to_string()),
-            hostname: None,
-            nickname: existing_device.as_ref().and_then(|d| d.nickname.clone()),
-            vendor: None,
-            first_seen: existing_device
-                .as_ref()
-                .map(|d| d.first_seen)
-                .unwrap_or(now),
-            last_seen: now,
-            status: DeviceStatus::Online,
+        let requested_ip = match message.message_type {
+            DhcpMessageType::Discover => None,
+            DhcpMessageType::Request => message.requested_ip.or(message.client_ip),
+            DhcpMessageType::Inform | DhcpMessageType::Release => message.client_ip,
+            _ => return None,
         };
 
-        self.db.upsert_device(&device).await?;
-        debug!(
-            "Device activity: {} ({})",
-            mac,
-            ip.map(|i| i.to_string())
-                .unwrap_or_else(|| "no IP".to_string())
-        );
+        Some(NetworkEvent::DhcpRequest {
+            client_mac: message.client_mac,
+            message_type: message.message_type,
+            requested_ip: requested_ip.map(IpAddr::from),
+            hostname: message.hostname,
+            client_id: message.client_id,
+            vendor_class: message.vendor_class,
+            parameter_request_list: message.parameter_request_list,
+            relay_agent: message.relay_agent.map(IpAddr::from),
+        })
+    }
 
-        // Log device activity
-        if is_new {
-            let log_entry = crate::models::LogEntry {
-                id: None,
-                timestamp: now,
-                level: crate::models::LogLevel::Info,
-                category: "device".to_string(),
-                message: format!("New device discovered: {}", mac),
-                details: ip.map(|i| i.to_string()),
-            };
-            let _ = self.db.create_log(&log_entry).await;
-        } else if old_status != DeviceStatus::Online && device.status == DeviceStatus::Online {
-            let log_entry = crate::models::LogEntry {
-                id: None,
-                timestamp: now,
-                level: crate::models::LogLevel::Info,
-                category: "device".to_string(),
-                message: format!("Device connected: {}", mac),
-                details: ip.map(|i| i.to_string()),
-            };
-            let _ = self.db.create_log(&log_entry).await;
+    fn parse_dhcp_reply(
+        ethernet_source: MacAddr,
+        source_ip: IpAddr,
+        udp: &UdpPacket,
+    ) -> Option<NetworkEvent> {
+        let message = dhcp::parse(udp.payload())?;
+        if message.is_client_message
+            || !matches!(
+                message.message_type,
+                DhcpMessageType::Offer | DhcpMessageType::Ack
+            )
+        {
+            return None;
         }
 
-        let rules = self.db.get_enabled_rules().await?;
+        Some(NetworkEvent::DhcpReply {
+            server_mac: ethernet_source.to_string(),
+            server_ip: source_ip,
+            server_id: message.server_id.map(IpAddr::from),
+            client_mac: message.client_mac,
+            message_type: message.message_type,
+            offered_ip: message.your_ip.map(IpAddr::from),
+            lease_time: message.lease_time,
+            renewal_time: message.renewal_time,
+            rebinding_time: message.rebinding_time,
+            relay_agent: message.relay_agent.map(IpAddr::from),
+        })
+    }
 
-        for rule in rules {
-            if let Some(filter) = &rule.mac_filter {
-                if !mac.eq_ignore_ascii_case(filter) {
-                    continue;
-                }
-            }
+    async fn monitor_netlink(&self, tx: Sender<(EventOrigin, NetworkEvent)>) -> Result<()> {
+        info!("Starting netlink neighbor monitoring");
 
-            let should_notify = match rule.trigger_type {
-                TriggerType::NewDevice => is_new,
-                TriggerType::DeviceConnected => {
-                    old_status != DeviceStatus::Online && device.status == DeviceStatus::Online
-                }
-                TriggerType::DeviceStatusChange => old_status != device.status,
-                _ => false,
-            };
+        let (mut connection, handle, mut messages) = new_connection()?;
+        connection
+            .socket_mut()
+            .socket_mut()
+            .bind(&SocketAddr::new(0, RTMGRP_NEIGH))?;
+        tokio::spawn(connection);
 
-            if should_notify {
-                self.send_notification(&rule, &device).await?;
+        let mut interfaces = HashMap::new();
+        for interface in &self.interfaces {
+            let ifindex = handle
+                .link()
+                .get()
+                .match_name(interface.clone())
+                .execute()
+                .try_next()
+                .await?
+                .map(|link| link.header.index)
+                .ok_or_else(|| {
+                    DaemonError::Netlink(format!("Interface {} not found", interface))
+                })?;
+            interfaces.insert(ifindex, interface.clone());
+        }
+
+        // Multicast notifications can be dropped when the socket buffer
+        // overflows, so the full table is re-read periodically as well.
+        let mut resync_interval = interval(self.neighbor_check_interval);
+        let mut known = HashSet::new();
+
+        loop {
+            let events = tokio::select! {
+                _ = resync_interval.tick() => {
+                    let mut events = Vec::new();
+                    let mut neighbours = handle.neighbours().get().execute();
+                    while let Some(msg) = neighbours.try_next().await? {
+                        let event =
+                            Self::parse_neighbour_message(&msg, &interfaces, false, &mut known);
+                        events.extend(event);
+                    }
+                    events
+                }
+                message = messages.next() => {
+                    let Some((message, _)) = message else {
+                        return Err(DaemonError::Netlink(
+                            "Netlink multicast stream closed".to_string(),
+                        ));
+                    };
+
+                    match message.payload {
+                        NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewNeighbour(msg)) => {
+                            Self::parse_neighbour_message(&msg, &interfaces, false, &mut known)
+                                .into_iter()
+                                .collect()
+                        }
+                        NetlinkPayload::InnerMessage(RouteNetlinkMessage::DelNeighbour(msg)) => {
+                            Self::parse_neighbour_message(&msg, &interfaces, true, &mut known)
+                                .into_iter()
+                                .collect()
+                        }
+                        _ => Vec::new(),
+                    }
+                }
+            };
+
+            for event in events {
+                tx.send(event)
+                    .await
+                    .map_err(|e| DaemonError::Internal(format!("Event channel closed: {}", e)))?;
+            }
+        }
+    }
+
+    /// Tracks presence on `segment` from the kernel neighbour table while
+    /// packet capture is unavailable there. Reading the table needs no
+    /// privileges, but only says whether this host recently exchanged
+    /// traffic with a neighbour: DHCP, mDNS, SSDP and ARP checks are lost.
+    async fn poll_neighbor_table(
+        &self,
+        segment: String,
+        tx: Sender<(EventOrigin, NetworkEvent)>,
+    ) -> Result<()> {
+        info!(
+            "Polling the neighbor table of {} every {}s",
+            segment,
+            self.neighbor_check_interval.as_secs()
+        );
+
+        let origin = EventOrigin::new(&segment, &[]);
+        let interface_index = neighbors::interface_index(&segment);
+        let mut table = NeighborTable::new(&segment);
+        let mut poll_interval = interval(self.neighbor_check_interval);
+
+        loop {
+            poll_interval.tick().await;
+
+            let changes;
+            (table, changes) = tokio::task::spawn_blocking(move || {
+                let changes = table.poll();
+                (table, changes)
+            })
+            .await
+            .map_err(|e| DaemonError::Internal(format!("Neighbor table task error: {}", e)))?;
+
+            let changes = match changes {
+                Ok(changes) => changes,
+                Err(e) => {
+                    warn!("Cannot read the neighbor table of {}: {}", segment, e);
+                    continue;
+                }
+            };
+
+            for change in changes {
+                let event = if change.added {
+                    NetworkEvent::NeighborAdded {
+                        mac: change.mac,
+                        ip: change.ip,
+                        interface_index,
+                        state: change.state,
+                    }
+                } else {
+                    NetworkEvent::NeighborUpdated {
+                        mac: change.mac,
+                        ip: change.ip,
+                        interface_index,
+                        state: change.state,
+                    }
+                };
+                tx.send((origin.clone(), event))
+                    .await
+                    .map_err(|e| DaemonError::Internal(format!("Event channel closed: {}", e)))?;
+            }
+        }
+    }
+
+    /// Feeds the configured DHCP lease files into the event stream: every
+    /// lease once at startup, then each lease the server writes as inotify
+    /// reports the file changing. Leases that disappear from a file are
+    /// ignored, so a file read halfway through a rewrite does no harm.
+    async fn watch_lease_files(&self, tx: Sender<(EventOrigin, NetworkEvent)>) -> Result<()> {
+        let mut watcher = DirectoryWatcher::new()?;
+        let mut watched = Vec::new();
+        for path in &self.lease_files {
+            let directory = match path.parent() {
+                Some(directory) if !directory.as_os_str().is_empty() => directory,
+                _ => Path::new("."),
+            };
+            let Some(name) = path.file_name() else {
+                warn!("Not watching lease file {}: no file name", path.display());
+                continue;
+            };
+            if let Err(e) = watcher.watch(directory) {
+                warn!("Not watching lease file {}: {}", path.display(), e);
+                continue;
+            }
+            info!("Watching DHCP lease file {}", path.display());
+            watched.push((directory.join(name), path));
+        }
+
+        let mut known: HashMap<&PathBuf, HashMap<String, LeaseEntry>> = HashMap::new();
+        let mut changed_paths: Vec<PathBuf> =
+            watched.iter().map(|(path, _)| path.clone()).collect();
+        let mut startup = true;
+
+        loop {
+            for (watched_path, path) in &watched {
+                if !changed_paths.contains(watched_path) {
+                    continue;
+                }
+                let entries = Self::read_lease_file(path).await;
+                let known = known.entry(path).or_default();
+
+                for entry in entries {
+                    if known.get(&entry.mac) == Some(&entry) {
+                        continue;
+                    }
+                    known.insert(entry.mac.clone(), entry.clone());
+
+                    let event = NetworkEvent::LeaseFileEntry {
+                        client_mac: entry.mac,
+                        ip: entry.ip,
+                        hostname: entry.hostname,
+                        expires_at: entry.expires_at,
+                        changed: !startup,
+                    };
+                    tx.send((EventOrigin::new(self.primary_segment(), &[]), event))
+                        .await
+                        .map_err(|e| {
+                            DaemonError::Internal(format!("Event channel closed: {}", e))
+                        })?;
+                }
+            }
+            startup = false;
+
+            changed_paths = vec![watcher.changed().await?];
+            // Collect whatever else changes while the server finishes writing.
+            loop {
+                tokio::select! {
+                    more = watcher.changed() => changed_paths.push(more?),
+                    _ = tokio::time::sleep(LEASE_FILE_SETTLE) => break,
+                }
+            }
+        }
+    }
+
+    async fn read_lease_file(path: &Path) -> Vec<LeaseEntry> {
+        let contents = match tokio::fs::read_to_string(path).await {
+            Ok(contents) => contents,
+            Err(e) => {
+                debug!("Cannot read lease file {}: {}", path.display(), e);
+                return Vec::new();
+            }
+        };
+
+        match LeaseFileFormat::detect(&contents) {
+            Some(format) => {
+                let entries = format.parse(&contents);
+                debug!(
+                    "Read {} leases from {} lease file {}",
+                    entries.len(),
+                    format,
+                    path.display()
+                );
+                entries
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Converts an RTM_NEWNEIGH/RTM_DELNEIGH message for one of the monitored
+    /// interfaces (keyed by index) into a neighbor event and its origin.
+    /// `known` tracks which entries have already been reported so that
+    /// updates can be told apart from additions.
+    fn parse_neighbour_message(
+        msg: &NeighbourMessage,
+        interfaces: &HashMap<u32, String>,
+        deleted: bool,
+        known: &mut HashSet<(u32, String, IpAddr)>,
+    ) -> Option<(EventOrigin, NetworkEvent)> {
+        let origin = EventOrigin::new(interfaces.get(&msg.header.ifindex)?, &[]);
+
+        let mut mac = None;
+        let mut ip = None;
+        for attr in &msg.attributes {
+            match attr {
+                NeighbourAttribute::LinkLocalAddress(addr) if addr.len() == 6 => {
+                    mac = Some(MacAddr::new(
+                        addr[0], addr[1], addr[2], addr[3], addr[4], addr[5],
+                    ));
+                }
+                NeighbourAttribute::Destination(NeighbourAddress::Inet(addr)) => {
+                    ip = Some(IpAddr::V4(*addr));
+                }
+                NeighbourAttribute::Destination(NeighbourAddress::Inet6(addr)) => {
+                    ip = Some(IpAddr::V6(*addr));
+                }
+                _ => {}
+            }
+        }
+
+        // Incomplete entries have no link-layer address yet.
+        let mac = mac?.to_string();
+        let ip = ip?;
+        let interface_index = msg.header.ifindex;
+
+        if deleted {
+            known.remove(&(interface_index, mac.clone(), ip));
+            let event = NetworkEvent::NeighborRemoved {
+                mac,
+                ip,
+                interface_index,
+            };
+            return Some((origin, event));
+        }
+
+        let state = match msg.header.state {
+            NeighbourState::Reachable => NeighborState::Reachable,
+            NeighbourState::Stale => NeighborState::Stale,
+            NeighbourState::Delay => NeighborState::Delay,
+            NeighbourState::Probe => NeighborState::Probe,
+            NeighbourState::Failed => NeighborState::Failed,
+            NeighbourState::Permanent | NeighbourState::Noarp => NeighborState::Permanent,
+            _ => NeighborState::Other,
+        };
+
+        let event = if known.insert((interface_index, mac.clone(), ip)) {
+            NetworkEvent::NeighborAdded {
+                mac,
+                ip,
+                interface_index,
+                state,
+            }
+        } else {
+            NetworkEvent::NeighborUpdated {
+                mac,
+                ip,
+                interface_index,
+                state,
+            }
+        };
+
+        Some((origin, event))
+    }
+
+    async fn process_events(&self, rx: Receiver<(EventOrigin, NetworkEvent)>) {
+        info!("Event processor started");
+
+        while let Ok((origin, event)) = rx.recv().await {
+            if let Err(e) = self.handle_event(&origin, event).await {
+                error!("Error handling event: {}", e);
+            }
+        }
+    }
+
+    async fn handle_event(&self, origin: &EventOrigin, event: NetworkEvent) -> Result<()> {
+        match event {
+            NetworkEvent::ArpRequest {
+                source_mac,
+                source_ip,
+                target_ip,
+            }
+            | NetworkEvent::ArpReply {
+                source_mac,
+                source_ip,
+                target_ip,
+            } => {
+                // ARP probes come from 0.0.0.0 while the sender has no address.
+                self.handle_device_activity(
+                    origin,
+                    &source_mac,
+                    Some(source_ip).filter(|ip| !ip.is_unspecified()),
+                    AddressSource::Arp,
+                    &IdentityHints::default(),
+                )
+                .await?;
+
+                if let (IpAddr::V4(source_ip), IpAddr::V4(target_ip)) = (source_ip, target_ip) {
+                    self.check_arp(&source_mac, source_ip, target_ip).await?;
+                }
+            }
+            NetworkEvent::DhcpRequest {
+                client_mac,
+                message_type,
+                requested_ip,
+                hostname,
+                client_id,
+                vendor_class,
+                parameter_request_list,
+                relay_agent,
+            } => {
+                debug!(
+                    "DHCP {} from {} (hostname: {:?}, client-id: {:?}, vendor class: {:?}, relay: {:?})",
+                    message_type, client_mac, hostname, client_id, vendor_class, relay_agent
+                );
+
+                // A relayed client sits on the relay's network; give it a
+                // segment of its own instead of the capture interface.
+                let relayed_origin = relay_agent.map(|relay| EventOrigin {
+                    segment: format!("relay-{}", relay),
+                    vlan_id: None,
+                });
+                let origin = relayed_origin.as_ref().unwrap_or(origin);
+
+                let hints = IdentityHints {
+                    hostname,
+                    client_id,
+                    dhcp_fingerprint: parameter_request_list.map(|codes| {
+                        codes
+                            .iter()
+                            .map(|code| code.to_string())
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    }),
+                    vendor_class,
+                    ..Default::default()
+                };
+
+                if message_type == DhcpMessageType::Release {
+                    if let Some(id) = self
+                        .db
+                        .get_device_by_mac(&client_mac)
+                        .await?
+                        .and_then(|device| device.id)
+                    {
+                        self.db.end_device_lease(id, self.now()).await?;
+                    }
+                    self.handle_device_disconnection(&client_mac).await?;
+                } else {
+                    self.handle_device_activity(
+                        origin,
+                        &client_mac,
+                        requested_ip,
+                        AddressSource::Dhcp,
+                        &hints,
+                    )
+                    .await?;
+                }
+            }
+            NetworkEvent::DhcpReply {
+                server_mac,
+                server_ip,
+                server_id,
+                client_mac,
+                message_type,
+                offered_ip,
+                lease_time,
+                renewal_time,
+                rebinding_time,
+                relay_agent,
+            } => {
+                debug!(
+                    "DHCP {} from server {} ({}, id {:?}) to {}, address {:?}, lease {:?}s, relay {:?}",
+                    message_type,
+                    server_mac,
+                    server_ip,
+                    server_id,
+                    client_mac,
+                    offered_ip,
+                    lease_time,
+                    relay_agent
+                );
+
+                self.handle_device_activity(
+                    origin,
+                    &server_mac,
+                    Some(server_ip),
+                    AddressSource::Dhcp,
+                    &IdentityHints::default(),
+                )
+                .await?;
+                let trusted = self
+                    .check_dhcp_server(
+                        &server_mac,
+                        server_ip,
+                        server_id,
+                        &client_mac,
+                        message_type,
+                        offered_ip,
+                    )
+                    .await?;
+
+                // The client of a relayed reply is on the relay's network, as
+                // for its request.
+                let relayed_origin = relay_agent.map(|relay| EventOrigin {
+                    segment: format!("relay-{}", relay),
+                    vlan_id: None,
+                });
+                let client_origin = relayed_origin.as_ref().unwrap_or(origin);
+
+                // An ACK without a lease time answers a DHCPINFORM. A lease
+                // from a rogue server says nothing about the client.
+                if message_type == DhcpMessageType::Ack
+                    && trusted
+                    && let (Some(ip), Some(lease_time)) = (offered_ip, lease_time)
+                {
+                    let device_id = self
+                        .handle_device_activity(
+                            client_origin,
+                            &client_mac,
+                            Some(ip),
+                            AddressSource::Dhcp,
+                            &IdentityHints::default(),
+                        )
+                        .await?;
+                    let now = self.now();
+                    let after = |secs: u32| now + chrono::TimeDelta::seconds(secs.into());
+                    // u32::MAX is an infinite lease. Clients without T1 and
+                    // T2 use half and seven eighths of the lease (RFC 2131).
+                    let lease_time = (lease_time != u32::MAX).then_some(lease_time);
+                    let renewal_time = lease_time.map(|t| renewal_time.unwrap_or(t / 2));
+                    let rebinding_time =
+                        lease_time.map(|t| rebinding_time.unwrap_or((u64::from(t) * 7 / 8) as u32));
+                    let lease = DhcpLease {
+                        mac_address: client_mac,
+                        ip_address: ip.to_string(),
+                        server_id: server_id.map(|id| id.to_string()),
+                        server_mac,
+                        lease_time_secs: lease_time,
+                        renewal_time_secs: renewal_time,
+                        rebinding_time_secs: rebinding_time,
+                        acquired_at: now,
+                        renewed_at: now,
+                        renews_at: renewal_time.map(after),
+                        rebinds_at: rebinding_time.map(after),
+                        expires_at: lease_time.map(after),
+                        renewals: 0,
+                    };
+                    self.record_lease(device_id, lease).await?;
+                }
+            }
+            NetworkEvent::LeaseFileEntry {
+                client_mac,
+                ip,
+                hostname,
+                expires_at,
+                changed,
+            } => {
+                debug!(
+                    "Lease file entry for {}: {} ({:?}) until {:?}",
+                    client_mac, ip, hostname, expires_at
+                );
+
+                if let Some(hostname) = &hostname {
+                    self.lease_hostnames
+                        .lock()
+                        .unwrap()
+                        .insert(client_mac.clone(), hostname.clone());
+
+                    // Name a known device right away, without taking the
+                    // file's word that it is online.
+                    if !changed
+                        && let Some(device) = self.db.get_device_by_mac(&client_mac).await?
+                        && device.hostname.is_none()
+                    {
+                        self.db
+                            .update_device_hostname(&device.mac_address, hostname)
+                            .await?;
+                    }
+                }
+
+                // Only a lease the server just wrote says the client is there
+                // now; the rest of the file only names devices.
+                if changed && expires_at.is_none_or(|expires_at| expires_at > self.now()) {
+                    // The file does not say where the client is, so keep the
+                    // segment it was last seen on.
+                    let origin = match self.db.get_device_by_mac(&client_mac).await? {
+                        Some(Device {
+                            segment: Some(segment),
+                            vlan_id,
+                            ..
+                        }) => EventOrigin { segment, vlan_id },
+                        _ => origin.clone(),
+                    };
+                    let hints = IdentityHints {
+                        hostname,
+                        ..Default::default()
+                    };
+                    self.handle_device_activity(
+                        &origin,
+                        &client_mac,
+                        Some(ip),
+                        AddressSource::LeaseFile,
+                        &hints,
+                    )
+                    .await?;
+                }
+            }
+            NetworkEvent::MdnsResponse {
+                source_mac,
+                source_ip,
+                hostname,
+                services,
+            } => {
+                debug!(
+                    "mDNS response from {} ({}), hostname {:?}, {} services",
+                    source_mac,
+                    source_ip,
+                    hostname,
+                    services.len()
+                );
+
+                let hints = IdentityHints {
+                    hostname,
+                    ..Default::default()
+                };
+                let device_id = self
+                    .handle_device_activity(
+                        origin,
+                        &source_mac,
+                        Some(source_ip),
+                        AddressSource::Mdns,
+                        &hints,
+                    )
+                    .await?;
+
+                let now = self.now();
+                for service in &services {
+                    self.db
+                        .upsert_device_service(device_id, service, now)
+                        .await?;
+                }
+            }
+            NetworkEvent::LinkAnnouncement {
+                device_mac,
+                announcement,
+            } => {
+                debug!(
+                    "{} announcement from {} ({:?}), port {:?}, capabilities {:?}",
+                    announcement.protocol,
+                    device_mac,
+                    announcement.system_name,
+                    announcement.port_id,
+                    announcement.capabilities
+                );
+
+                let hints = IdentityHints {
+                    hostname: announcement.system_name.clone(),
+                    announced_device_type: announcement.device_type().map(str::to_string),
+                    ..Default::default()
+                };
+                let source = match announcement.protocol {
+                    DiscoveryProtocol::Lldp => AddressSource::Lldp,
+                    DiscoveryProtocol::Cdp => AddressSource::Cdp,
+                };
+                let management_address = announcement
+                    .management_address
+                    .as_deref()
+                    .and_then(|address| address.parse().ok());
+                let device_id = self
+                    .handle_device_activity(origin, &device_mac, management_address, source, &hints)
+                    .await?;
+
+                let now = self.now();
+                let discovery = LinkDiscovery {
+                    announcement,
+                    first_seen: now,
+                    last_seen: now,
+                };
+                self.db
+                    .upsert_device_link_discovery(device_id, &discovery)
+                    .await?;
+            }
+            NetworkEvent::TcpSyn {
+                source_mac,
+                source_ip,
+                signature,
+            } => {
+                // A custom capture filter may let SYNs through regardless.
+                if !self.tcp_fingerprinting {
+                    return Ok(());
+                }
+                {
+                    let now = self.now();
+                    let mut syns = self.tcp_syns.lock().unwrap();
+                    if syns.get(&source_mac).is_some_and(|(previous, seen)| {
+                        *previous == signature && now - *seen < TCP_SYN_RETRY
+                    }) {
+                        return Ok(());
+                    }
+                    syns.insert(source_mac.clone(), (signature.clone(), now));
+                }
+                debug!("TCP SYN from {} ({}): {}", source_mac, source_ip, signature);
+
+                let hints = IdentityHints {
+                    tcp_signature: Some(signature),
+                    ..Default::default()
+                };
+                self.handle_device_activity(
+                    origin,
+                    &source_mac,
+                    Some(source_ip),
+                    AddressSource::Tcp,
+                    &hints,
+                )
+                .await?;
+            }
+            NetworkEvent::SsdpNotify {
+                source_mac,
+                source_ip,
+                uuid,
+                server,
+                device_type,
+                location,
+            } => {
+                debug!(
+                    "SSDP announcement from {} ({}), server {:?}",
+                    source_mac, source_ip, server
+                );
+
+                let device_id = self
+                    .handle_device_activity(
+                        origin,
+                        &source_mac,
+                        Some(source_ip),
+                        AddressSource::Ssdp,
+                        &IdentityHints::default(),
+                    )
+                    .await?;
+
+                let previous = self.db.get_device_upnp(device_id).await?;
+                let now = self.now();
+                let info = UpnpInfo {
+                    uuid,
+                    server,
+                    device_type,
+                    location,
+                    friendly_name: None,
+                    manufacturer: None,
+                    model_name: None,
+                    description_fetched_at: None,
+                    first_seen: now,
+                    last_seen: now,
+                };
+                self.db.upsert_device_upnp(device_id, &info).await?;
+
+                // Fetching from a replayed capture would send requests to
+                // addresses that were on another network at another time.
+                if self.ssdp_fetch_descriptions
+                    && self.live.load(Ordering::Relaxed)
+                    && let Some(location) = info.location
+                    && Self::upnp_description_stale(previous.as_ref(), &location, now)
+                {
+                    self.fetch_upnp_description(device_id, source_ip, location, now);
+                }
+            }
+            NetworkEvent::NetbiosName {
+                source_mac,
+                source_ip,
+                name,
+            } => {
+                debug!("NetBIOS name {} from {} ({})", name, source_mac, source_ip);
+                self.handle_name_claim(origin, &source_mac, source_ip, name, AddressSource::Nbns)
+                    .await?;
+            }
+            NetworkEvent::LlmnrResponse {
+                source_mac,
+                source_ip,
+                name,
+            } => {
+                debug!("LLMNR name {} from {} ({})", name, source_mac, source_ip);
+                self.handle_name_claim(origin, &source_mac, source_ip, name, AddressSource::Llmnr)
+                    .await?;
+            }
+            NetworkEvent::NetbiosConflict {
+                source_mac,
+                source_ip,
+                name,
+            } => {
+                warn!(
+                    "NetBIOS name conflict: {} ({}) refused a registration of {}",
+                    source_mac, source_ip, name
+                );
+
+                let log_entry = crate::models::LogEntry {
+                    id: None,
+                    timestamp: self.now(),
+                    level: crate::models::LogLevel::Warning,
+                    category: "device".to_string(),
+                    message: format!("NetBIOS name conflict on {}", name),
+                    details: Some(format!("name owned by {} ({})", source_mac, source_ip)),
+                };
+                self.db.create_log(&log_entry).await?;
+            }
+            NetworkEvent::NeighborSolicitation {
+                source_mac,
+                source_ip,
+                target_ip,
+            } => {
+                // A solicitation from the unspecified address is a DAD probe for
+                // target_ip, which is not yet assigned to the sender.
+                debug!("NDP NS from {} for {}", source_mac, target_ip);
+                self.handle_device_activity(
+                    origin,
+                    &source_mac,
+                    source_ip,
+                    AddressSource::Ndp,
+                    &IdentityHints::default(),
+                )
+                .await?;
+            }
+            NetworkEvent::NeighborAdvertisement {
+                source_mac,
+                target_ip,
+                is_router,
+            } => {
+                debug!(
+                    "NDP NA from {} for {} (router: {})",
+                    source_mac, target_ip, is_router
+                );
+                self.handle_device_activity(
+                    origin,
+                    &source_mac,
+                    Some(target_ip),
+                    AddressSource::Ndp,
+                    &IdentityHints::default(),
+                )
+                .await?;
+            }
+            NetworkEvent::RouterSolicitation {
+                source_mac,
+                source_ip,
+            } => {
+                self.handle_device_activity(
+                    origin,
+                    &source_mac,
+                    source_ip,
+                    AddressSource::Ndp,
+                    &IdentityHints::default(),
+                )
+                .await?;
+            }
+            NetworkEvent::RouterAdvertisement {
+                source_mac,
+                source_ip,
+                router_lifetime_secs,
+                prefixes,
+            } => {
+                debug!(
+                    "NDP RA from {} ({}), lifetime {}s, prefixes {:?}",
+                    source_mac, source_ip, router_lifetime_secs, prefixes
+                );
+                self.handle_device_activity(
+                    origin,
+                    &source_mac,
+                    Some(source_ip),
+                    AddressSource::Ndp,
+                    &IdentityHints::default(),
+                )
+                .await?;
+                self.check_router_advertisement(&source_mac, source_ip, &prefixes)
+                    .await?;
+            }
+            NetworkEvent::NeighborAdded { mac, ip, state, .. }
+            | NetworkEvent::NeighborUpdated { mac, ip, state, .. } => {
+                self.neighbor_states
+                    .write()
+                    .await
+                    .entry(mac.clone())
+                    .or_default()
+                    .insert(ip, state);
+
+                match state {
+                    // Only a confirmed entry proves the device is there; stale
+                    // and probing entries are left to the timeout checker.
+                    // Permanent entries are configured, not observed.
+                    NeighborState::Reachable => {
+                        self.handle_device_activity(
+                            origin,
+                            &mac,
+                            Some(ip),
+                            AddressSource::Netlink,
+                            &IdentityHints::default(),
+                        )
+                        .await?;
+                    }
+                    NeighborState::Failed => {
+                        debug!("Neighbor {} ({}) unreachable", mac, ip);
+                        self.handle_neighbor_lost(&mac, ip).await?;
+                    }
+                    _ => debug!("Neighbor {} ({}) is {:?}", mac, ip, state),
+                }
+            }
+            NetworkEvent::NeighborRemoved { mac, ip, .. } => {
+                debug!("Neighbor {} ({}) removed", mac, ip);
+                if let Some(states) = self.neighbor_states.write().await.get_mut(&mac) {
+                    states.remove(&ip);
+                }
+                self.handle_neighbor_lost(&mac, ip).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Takes a device offline once one of its neighbour entries failed or
+    /// went away, unless another of its addresses is still reachable or
+    /// stale.
+    async fn handle_neighbor_lost(&self, mac: &str, ip: IpAddr) -> Result<()> {
+        let other_address_alive =
+            self.neighbor_states
+                .read()
+                .await
+                .get(mac)
+                .is_some_and(|states| {
+                    states.iter().any(|(address, state)| {
+                        *address != ip
+                            && matches!(state, NeighborState::Reachable | NeighborState::Stale)
+                    })
+                });
+        if other_address_alive {
+            debug!("Device {} is still reachable at another address", mac);
+            return Ok(());
+        }
+
+        self.handle_device_disconnection(mac).await
+    }
+
+    /// Records activity from a device and returns its id.
+    async fn handle_device_activity(
+        &self,
+        origin: &EventOrigin,
+        mac: &str,
+        ip: Option<IpAddr>,
+        source: AddressSource,
+        hints: &IdentityHints,
+    ) -> Result<i64> {
+        let now = self.now();
+
+        let (vendor, locally_administered) = match self.oui.lookup(mac) {
+            VendorLookup::Vendor(vendor) => (Some(vendor), false),
+            VendorLookup::LocallyAdministered => (None, true),
+            VendorLookup::Unknown => (None, false),
+        };
+
+        let mut existing_device = self.db.get_device_by_mac(mac).await?;
+        if locally_administered {
+            existing_device = self
+                .correlate_identity(mac, ip, hints, existing_device, now)
+                .await?;
+        }
+
+        // The MAC may be one the device used before; make it current again.
+        if let Some(device) = &existing_device
+            && device.mac_address != mac
+            && let Some(id) = device.id
+        {
+            self.db.update_device_mac(id, mac).await?;
+        }
+
+        let (segment, vlan_id) = self
+            .device_segment(existing_device.as_ref(), origin, now)
+            .await?;
+
+        let is_new = existing_device.is_none();
+        let old_status = existing_device
+            .as_ref()
+            .map(|d| d.status.clone())
+            .unwrap_or(DeviceStatus::Unknown);
+
+        // A device type announced over LLDP or CDP stands until the device
+        // announces another; the DHCP fingerprint can still name the OS.
+        let announced_device_type = hints.announced_device_type.clone().or_else(|| {
+            existing_device
+                .as_ref()
+                .filter(|d| d.device_type_source == Some(DeviceTypeSource::Announced))
+                .and_then(|d| d.device_type.clone())
+        });
+
+        // Only DHCP requests carry a fingerprint; other traffic keeps the
+        // classification the device already has.
+        let classification = (hints.dhcp_fingerprint.is_some() || hints.vendor_class.is_some())
+            .then(|| {
+                let options: Option<Vec<u8>> = hints
+                    .dhcp_fingerprint
+                    .as_ref()
+                    .and_then(|list| list.split(',').map(|code| code.parse().ok()).collect());
+                self.fingerprints
+                    .classify(options.as_deref(), hints.vendor_class.as_deref())
+            })
+            .flatten();
+
+        // Unknown TCP signatures keep the OS an earlier SYN matched.
+        let tcp_classification = hints
+            .tcp_signature
+            .as_deref()
+            .and_then(|signature| self.tcp_signatures.classify(signature));
+        if let (Some(classification), Some(signature)) = (&tcp_classification, &hints.tcp_signature)
+        {
+            debug!(
+                "TCP signature {} of {} matches {} {}{}",
+                signature,
+                mac,
+                classification.os,
+                classification.flavor,
+                if classification.fuzzy { " (fuzzy)" } else { "" }
+            );
+        }
+
+        let (device_type, device_type_source) = match (&announced_device_type, &classification) {
+            (Some(device_type), _) => {
+                (Some(device_type.clone()), Some(DeviceTypeSource::Announced))
+            }
+            (None, Some(c)) => (
+                Some(c.device_type.clone()),
+                Some(DeviceTypeSource::Fingerprint),
+            ),
+            (None, None) => existing_device
+                .as_ref()
+                .map(|d| (d.device_type.clone(), d.device_type_source))
+                .unwrap_or_default(),
+        };
+
+        let device = Device {
+            id: existing_device.as_ref().and_then(|d| d.id),
+            mac_address: mac.to_string(),
+            ip_address: ip
+                .filter(|i| i.is_ipv4())
+                .map(|i| i.to_string())
+                .or_else(|| existing_device.as_ref().and_then(|d| d.ip_address.clone())),
+            hostname: hints
+                .hostname
+                .clone()
+                .or_else(|| existing_device.as_ref().and_then(|d| d.hostname.clone()))
+                .or_else(|| self.lease_hostnames.lock().unwrap().get(mac).cloned()),
+            nickname: existing_device.as_ref().and_then(|d| d.nickname.clone()),
+            vendor,
+            first_seen: existing_device
+                .as_ref()
+                .map(|d| d.first_seen)
+                .unwrap_or(now),
+            last_seen: now,
+            status: DeviceStatus::Online,
+            segment: Some(segment),
+            vlan_id,
+            locally_administered,
+            dhcp_client_id: hints.client_id.clone().or_else(|| {
+                existing_device
+                    .as_ref()
+                    .and_then(|d| d.dhcp_client_id.clone())
+            }),
+            dhcp_fingerprint: hints.dhcp_fingerprint.clone().or_else(|| {
+                existing_device
+                    .as_ref()
+                    .and_then(|d| d.dhcp_fingerprint.clone())
+            }),
+            addresses: Vec::new(),
+            segments: Vec::new(),
+            mac_history: Vec::new(),
+            services: Vec::new(),
+            upnp: None,
+            lease: None,
+            link_discovery: None,
+            presence_probes: existing_device
+                .as_ref()
+                .and_then(|d| d.presence_probes.clone()),
+            pinned: existing_device.as_ref().is_some_and(|d| d.pinned),
+            os_family: classification
+                .as_ref()
+                .map(|c| c.os_family.clone())
+                .or_else(|| existing_device.as_ref().and_then(|d| d.os_family.clone())),
+            infrastructure: device_type
+                .as_deref()
+                .is_some_and(|t| INFRASTRUCTURE_DEVICE_TYPES.contains(&t)),
+            device_type,
+            device_type_source,
+            classification_confidence: match announced_device_type {
+                Some(_) => Some(ANNOUNCED_CONFIDENCE),
+                None => classification.as_ref().map(|c| c.confidence).or_else(|| {
+                    existing_device
+                        .as_ref()
+                        .and_then(|d| d.classification_confidence)
+                }),
+            },
+            tcp_signature: hints.tcp_signature.clone().or_else(|| {
+                existing_device
+                    .as_ref()
+                    .and_then(|d| d.tcp_signature.clone())
+            }),
+            tcp_os: match &tcp_classification {
+                Some(c) => Some(c.os.clone()),
+                None => existing_device.as_ref().and_then(|d| d.tcp_os.clone()),
+            },
+            tcp_os_flavor: match &tcp_classification {
+                Some(c) => Some(c.flavor.clone()).filter(|flavor| !flavor.is_empty()),
+                None => existing_device
+                    .as_ref()
+                    .and_then(|d| d.tcp_os_flavor.clone()),
+            },
+        };
+
+        let device_id = self.db.upsert_device(&device).await?;
+        if classification.is_none()
+            && (hints.dhcp_fingerprint.is_some() || hints.vendor_class.is_some())
+        {
+            self.db
+                .record_unknown_fingerprint(
+                    hints.dhcp_fingerprint.as_deref().unwrap_or(""),
+                    hints.vendor_class.as_deref(),
+                    &device,
+                    now,
+                )
+                .await?;
+        }
+        if let Some(ip) = &ip {
+            self.db
+                .upsert_device_address(device_id, ip, source, now)
+                .await?;
+        }
+        self.db.upsert_device_mac(device_id, mac, now).await?;
+        self.check_device_segment(device_id, mac, &origin.segment, now)
+            .await?;
+        debug!(
+            "Device activity: {} ({})",
+            mac,
+            ip.map(|i| i.to_string())
+                .unwrap_or_else(|| "no IP".to_string())
+        );
+
+        // Log device activity
+        if is_new {
+            let log_entry = crate::models::LogEntry {
+                id: None,
+                timestamp: now,
+                level: crate::models::LogLevel::Info,
+                category: "device".to_string(),
+                message: format!("New device discovered: {} ({})", mac, device.vendor_label()),
+                details: ip.map(|i| i.to_string()),
+            };
+            let _ = self.db.create_log(&log_entry).await;
+        } else if old_status != DeviceStatus::Online && device.status == DeviceStatus::Online {
+            let log_entry = crate::models::LogEntry {
+                id: None,
+                timestamp: now,
+                level: crate::models::LogLevel::Info,
+                category: "device".to_string(),
+                message: format!("Device connected: {}", mac),
+                details: ip.map(|i| i.to_string()),
+            };
+            let _ = self.db.create_log(&log_entry).await;
+        }
+
+        let rules = self.db.get_enabled_rules().await?;
+
+        for rule in rules {
+            if !Self::rule_applies(&rule, &device) {
+                continue;
             }
+
+            let should_notify = match rule.trigger_type {
+                TriggerType::NewDevice => is_new,
+                TriggerType::DeviceConnected => {
+                    old_status != DeviceStatus::Online && device.status == DeviceStatus::Online
+                }
+                TriggerType::DeviceStatusChange => old_status != device.status,
+                _ => false,
+            };
+
+            if should_notify {
+                self.send_notification(&rule, &device).await?;
+            }
+        }
+
+        Ok(device_id)
+    }
+
+    /// Matches a randomized MAC to a device already known under another MAC,
+    /// so that MAC rotation does not show up as a new device. Returns the
+    /// device the MAC belongs to, which is `existing` if no match is found.
+    async fn correlate_identity(
+        &self,
+        mac: &str,
+        ip: Option<IpAddr>,
+        hints: &IdentityHints,
+        existing: Option<Device>,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Device>> {
+        // A known MAC is only re-examined when it was first seen without DHCP
+        // (e.g. ARP before DHCP) and DHCP now identifies it.
+        if let Some(device) = &existing
+            && (device.mac_address != mac
+                || device.dhcp_client_id.is_some()
+                || device.dhcp_fingerprint.is_some()
+                || (hints.client_id.is_none() && hints.dhcp_fingerprint.is_none()))
+        {
+            return Ok(existing);
+        }
+
+        let own_id = existing.as_ref().and_then(|d| d.id);
+        let mut addresses = self.db.get_all_device_addresses().await?;
+        let best = self
+            .db
+            .get_all_devices()
+            .await?
+            .into_iter()
+            .filter(|candidate| candidate.id.is_some() && candidate.id != own_id)
+            .filter_map(|candidate| {
+                let candidate_addresses = candidate
+                    .id
+                    .and_then(|id| addresses.remove(&id))
+                    .unwrap_or_default();
+                let (rank, reason) =
+                    self.identity_match(&candidate, &candidate_addresses, ip, hints, now)?;
+                Some((rank, reason, candidate))
+            })
+            // Devices come most recently seen first, so ties go to the newest.
+            .min_by_key(|(rank, _, _)| *rank);
+
+        let Some((_, reason, mut device)) = best else {
+            return Ok(existing);
+        };
+        let id = device.id.unwrap_or_default();
+
+        if let Some(own_id) = own_id {
+            self.db.merge_devices(id, own_id).await?;
+        }
+        self.db.update_device_mac(id, mac).await?;
+
+        info!(
+            "Device {} is now using MAC {} (matched by {})",
+            device.mac_address, mac, reason
+        );
+        let log_entry = crate::models::LogEntry {
+            id: None,
+            timestamp: now,
+            level: crate::models::LogLevel::Info,
+            category: "device".to_string(),
+            message: format!("Device {} is now using MAC {}", device.mac_address, mac),
+            details: Some(format!("Matched by {}", reason)),
+        };
+        let _ = self.db.create_log(&log_entry).await;
+
+        device.mac_address = mac.to_string();
+        Ok(Some(device))
+    }
+
+    /// How strongly `hints` tie a new MAC to `candidate`, strongest (lowest
+    /// rank) first, or `None` if they do not.
+    fn identity_match(
+        &self,
+        candidate: &Device,
+        candidate_addresses: &[DeviceAddress],
+        ip: Option<IpAddr>,
+        hints: &IdentityHints,
+        now: DateTime<Utc>,
+    ) -> Option<(u8, &'static str)> {
+        let same = |a: &Option<String>, b: &Option<String>| matches!((a, b), (Some(a), Some(b)) if a.eq_ignore_ascii_case(b));
+        let conflicts = |a: &Option<String>, b: &Option<String>| matches!((a, b), (Some(a), Some(b)) if !a.eq_ignore_ascii_case(b));
+
+        if same(&hints.client_id, &candidate.dhcp_client_id) {
+            return Some((0, "DHCP client identifier"));
+        }
+
+        // Identical models left at their default hostname share both, so
+        // they only tie the MAC to a randomized device that has gone silent,
+        // as one that just rotated its MAC would have. Going offline moves
+        // `last_seen` forward, so the status says so as well.
+        let silent = candidate.status != DeviceStatus::Online
+            || (now - candidate.last_seen).num_seconds() > self.device_timeout.as_secs() as i64;
+        if candidate.locally_administered
+            && silent
+            && same(&hints.hostname, &candidate.hostname)
+            && same(&hints.dhcp_fingerprint, &candidate.dhcp_fingerprint)
+        {
+            return Some((1, "hostname and DHCP fingerprint"));
+        }
+
+        // An address is only carried over between randomized MACs, and only
+        // if it is the last one the candidate used in that family, nothing
+        // else the device said contradicts it and the hostname or DHCP
+        // fingerprint matches too: another phone may well be handed the
+        // address a departed one released.
+        let ip = ip?;
+        let family = AddressFamily::from(&ip);
+        let last_address = candidate_addresses
+            .iter()
+            .find(|address| address.family == family)?;
+        if !candidate.locally_administered
+            || last_address.address != ip.to_string()
+            || conflicts(&hints.hostname, &candidate.hostname)
+            || conflicts(&hints.dhcp_fingerprint, &candidate.dhcp_fingerprint)
+        {
+            return None;
+        }
+
+        if same(&hints.hostname, &candidate.hostname) {
+            Some((2, "IP address and hostname"))
+        } else if same(&hints.dhcp_fingerprint, &candidate.dhcp_fingerprint) {
+            Some((2, "IP address and DHCP fingerprint"))
+        } else {
+            None
+        }
+    }
+
+    /// Whether the rule's device filters select this device.
+    fn rule_applies(rule: &Rule, device: &Device) -> bool {
+        rule.mac_filter
+            .as_ref()
+            .is_none_or(|filter| device.mac_address.eq_ignore_ascii_case(filter))
+            && rule
+                .segment_filter
+                .as_ref()
+                .is_none_or(|filter| device.segment.as_ref() == Some(filter))
+            && rule.vendor_filter.as_ref().is_none_or(|filter| {
+                device
+                    .vendor
+                    .as_ref()
+                    .is_some_and(|vendor| vendor.to_lowercase().contains(&filter.to_lowercase()))
+            })
+            && rule.os_family_filter.as_ref().is_none_or(|filter| {
+                device
+                    .os_family
+                    .as_ref()
+                    .is_some_and(|os_family| os_family.eq_ignore_ascii_case(filter))
+            })
+            && rule.device_type_filter.as_ref().is_none_or(|filter| {
+                device
+                    .device_type
+                    .as_ref()
+                    .is_some_and(|device_type| device_type.eq_ignore_ascii_case(filter))
+            })
+            && rule.tcp_os_filter.as_ref().is_none_or(|filter| {
+                device
+                    .tcp_os
+                    .as_ref()
+                    .is_some_and(|tcp_os| tcp_os.eq_ignore_ascii_case(filter))
+            })
+    }
+
+    /// Attaches a name announced over NBNS or LLMNR to the sender, logging a
+    /// conflict when the name is new for the device but another device is
+    /// already known by it.
+    async fn handle_name_claim(
+        &self,
+        origin: &EventOrigin,
+        mac: &str,
+        ip: IpAddr,
+        name: String,
+        source: AddressSource,
+    ) -> Result<()> {
+        let previous_name = self
+            .db
+            .get_device_by_mac(mac)
+            .await?
+            .and_then(|device| device.hostname);
+
+        let hints = IdentityHints {
+            hostname: Some(name.clone()),
+            ..Default::default()
+        };
+        let device_id = self
+            .handle_device_activity(origin, mac, Some(ip), source, &hints)
+            .await?;
+
+        if previous_name.is_some_and(|previous| previous.eq_ignore_ascii_case(&name)) {
+            return Ok(());
+        }
+
+        let others: Vec<String> = self
+            .db
+            .get_devices_by_hostname(&name)
+            .await?
+            .into_iter()
+            .filter(|device| device.id != Some(device_id))
+            .map(|device| device.mac_address)
+            .collect();
+        if others.is_empty() {
+            return Ok(());
         }
 
+        warn!(
+            "Name {} claimed by {} is also used by {}",
+            name,
+            mac,
+            others.join(", ")
+        );
+
+        let log_entry = crate::models::LogEntry {
+            id: None,
+            timestamp: self.now(),
+            level: crate::models::LogLevel::Warning,
+            category: "device".to_string(),
+            message: format!("Name conflict: {} claimed by {}", name, mac),
+            details: Some(format!(
+                "{} ({}), also used by {}",
+                name,
+                source,
+                others.join(", ")
+            )),
+        };
+        self.db.create_log(&log_entry).await?;
+
         Ok(())
     }
 
-    async fn handle_device_disconnection(&self, mac: &str) -> Result<()> {
-        if let Some(device) = self.db.get_device_by_mac(mac).await? {
-            if device.status != DeviceStatus::Offline {
-                self.db
-                    .update_device_status(mac, DeviceStatus::Offline)
-                    .await?;
+    /// The segment (and VLAN) to show for a device seen on `origin`. A device
+    /// on two segments at once stays on the one it was on, rather than
+    /// switching with every packet; it moves once it has been quiet there for
+    /// [`SEGMENT_MOVE_AFTER`]. Every segment is recorded either way.
+    async fn device_segment(
+        &self,
+        existing_device: Option<&Device>,
+        origin: &EventOrigin,
+        now: DateTime<Utc>,
+    ) -> Result<(String, Option<u16>)> {
+        let seen = (origin.segment.clone(), origin.vlan_id);
+        let Some((id, segment, vlan_id)) = existing_device.and_then(|d| {
+            Some((d.id?, d.segment.clone()?, d.vlan_id))
+                .filter(|(_, segment, _)| *segment != origin.segment)
+        }) else {
+            return Ok(seen);
+        };
+
+        let still_there = self
+            .db
+            .get_device_segments(id)
+            .await?
+            .iter()
+            .any(|s| s.segment == segment && now - s.last_seen < SEGMENT_MOVE_AFTER);
+        Ok(if still_there {
+            (segment, vlan_id)
+        } else {
+            seen
+        })
+    }
 
-                debug!("Device disconnected: {}", mac);
+    /// Records the segment a device was seen on and logs a warning the first
+    /// time a known MAC shows up on another segment, which is either a device
+    /// bridged across segments or a duplicated/spoofed address.
+    async fn check_device_segment(
+        &self,
+        device_id: i64,
+        mac: &str,
+        segment: &str,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let segments = self.db.get_device_segments(device_id).await?;
+        self.db
+            .upsert_device_segment(device_id, segment, now)
+            .await?;
+
+        if segments.is_empty() || segments.iter().any(|s| s.segment == segment) {
+            return Ok(());
+        }
 
-                // Log disconnection
-                let log_entry = crate::models::LogEntry {
-                    id: None,
-                    timestamp: Utc::now(),
-                    level: crate::models::LogLevel::Warning,
-                    category: "device".to_string(),
-                    message: format!("Device disconnected: {}", mac),
-                    details: device.ip_address.clone(),
-                };
-                let _ = self.db.create_log(&log_entry).await;
+        let other_segments: Vec<&str> = segments.iter().map(|s| s.segment.as_str()).collect();
+        warn!(
+            "Device {} seen on {} and also on {}",
+            mac,
+            segment,
+            other_segments.join(", ")
+        );
 
-                let rules = self.db.get_enabled_rules().await?;
+        let log_entry = crate::models::LogEntry {
+            id: None,
+            timestamp: now,
+            level: crate::models::LogLevel::Warning,
+            category: "device".to_string(),
+            message: format!("Device {} seen on multiple segments", mac),
+            details: Some(format!(
+                "new segment: {}, known segments: {}",
+                segment,
+                other_segments.join(", ")
+            )),
+        };
+        self.db.create_log(&log_entry).await?;
 
-                for rule in rules {
-                    if let Some(filter) = &rule.mac_filter {
-                        if !mac.eq_ignore_ascii_case(filter) {
-                            continue;
-                        }
-                    }
+        Ok(())
+    }
 
-                    if rule.trigger_type == TriggerType::DeviceDisconnected
-                        || rule.trigger_type == TriggerType::DeviceStatusChange
-                    {
-                        let mut updated_device = device.clone();
-                        updated_device.status = DeviceStatus::Offline;
-                        self.send_notification(&rule, &updated_device).await?;
-                    }
+    /// Pins the first IPv6 router seen (unless routers are configured) and
+    /// logs a security warning the first time any other router advertises.
+    async fn check_router_advertisement(
+        &self,
+        mac: &str,
+        ip: IpAddr,
+        prefixes: &[String],
+    ) -> Result<()> {
+        let mut routers = self.ipv6_routers.write().await;
+        if routers.contains_key(mac) {
+            return Ok(());
+        }
+
+        if routers.is_empty() {
+            info!("Learned IPv6 router {} ({})", mac, ip);
+            routers.insert(mac.to_string(), true);
+            return Ok(());
+        }
+
+        routers.insert(mac.to_string(), false);
+        drop(routers);
+
+        warn!("Rogue IPv6 router advertisement from {} ({})", mac, ip);
+
+        let log_entry = crate::models::LogEntry {
+            id: None,
+            timestamp: self.now(),
+            level: crate::models::LogLevel::Warning,
+            category: "security".to_string(),
+            message: format!("Rogue IPv6 router advertisement from {}", mac),
+            details: Some(format!("source: {}, prefixes: {}", ip, prefixes.join(", "))),
+        };
+        self.db.create_log(&log_entry).await?;
+
+        Ok(())
+    }
+
+    /// Runs an IPv4 ARP packet through the ARP watch, then logs each alert
+    /// and notifies the rules for its trigger.
+    async fn check_arp(&self, mac: &str, source_ip: Ipv4Addr, target_ip: Ipv4Addr) -> Result<()> {
+        let Some(device) = self.db.get_device_by_mac(mac).await? else {
+            return Ok(());
+        };
+
+        // A pinned device answering for its own address is no spoofing, even
+        // from a MAC it rotated away from.
+        let pinned = self
+            .db
+            .get_pinned_devices_by_ipv4(&source_ip.to_string())
+            .await?
+            .into_iter()
+            .find(|owner| owner.id != device.id)
+            .map(|owner| owner.mac_address);
+
+        let alerts = self.arp_watch.lock().unwrap().observe(
+            mac,
+            source_ip,
+            target_ip,
+            pinned.as_deref(),
+            self.now(),
+        );
+
+        for alert in alerts {
+            let (trigger, message, details) = match &alert {
+                ArpAlert::Conflict { ip, holder } => {
+                    self.record_ip_conflict(mac, &device, *ip, holder).await?;
+                    continue;
+                }
+                ArpAlert::Spoofing { ip, expected } => (
+                    TriggerType::ArpSpoofing,
+                    format!("Possible ARP spoofing: {} claims {}", mac, ip),
+                    format!("{} belongs to {}", ip, expected),
+                ),
+                ArpAlert::GratuitousFlood { count } => (
+                    TriggerType::GratuitousArpFlood,
+                    format!("Gratuitous ARP flood from {}", mac),
+                    format!("{} gratuitous ARP packets within a minute", count),
+                ),
+                ArpAlert::MultipleIps { ips } => (
+                    TriggerType::MultipleIpClaims,
+                    format!("{} claims {} IPv4 addresses", mac, ips.len()),
+                    ips.iter()
+                        .map(|ip| ip.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+            };
+
+            warn!("{} ({})", message, details);
+
+            let log_entry = crate::models::LogEntry {
+                id: None,
+                timestamp: self.now(),
+                level: crate::models::LogLevel::Warning,
+                category: "security".to_string(),
+                message: message.clone(),
+                details: Some(details),
+            };
+            self.db.create_log(&log_entry).await?;
+
+            for rule in self.db.get_enabled_rules().await? {
+                if rule.trigger_type == trigger && Self::rule_applies(&rule, &device) {
+                    self.notify(&rule, &device, message.clone()).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records a conflict between `mac` and the MAC that held `ip`, logging
+    /// it and notifying `ip_conflict` rules when it was not already active.
+    async fn record_ip_conflict(
+        &self,
+        mac: &str,
+        device: &Device,
+        ip: Ipv4Addr,
+        holder: &str,
+    ) -> Result<()> {
+        let Some(holder_device) = self.db.get_device_by_mac(holder).await? else {
+            return Ok(());
+        };
+        // A device that rotated its MAC keeps its address.
+        if holder_device.id == device.id {
+            return Ok(());
+        }
+
+        let now = self.now();
+        let last_seen = self
+            .db
+            .upsert_ip_conflict(&ip.to_string(), holder, mac, now)
+            .await?;
+        if last_seen.is_some_and(|last_seen| now - last_seen <= IP_CONFLICT_ACTIVE) {
+            return Ok(());
+        }
+
+        warn!("IP conflict: {} claimed by {} and {}", ip, holder, mac);
+
+        let message = format!("IP conflict: {} claimed by {} and {}", ip, holder, mac);
+        let log_entry = crate::models::LogEntry {
+            id: None,
+            timestamp: now,
+            level: crate::models::LogLevel::Warning,
+            category: "device".to_string(),
+            message: message.clone(),
+            details: Some(format!("{} held {} when {} claimed it", holder, ip, mac)),
+        };
+        self.db.create_log(&log_entry).await?;
+
+        for rule in self.db.get_enabled_rules().await? {
+            if rule.trigger_type != TriggerType::IpConflict {
+                continue;
+            }
+            if Self::rule_applies(&rule, device) {
+                self.notify(&rule, device, message.clone()).await?;
+            } else if Self::rule_applies(&rule, &holder_device) {
+                self.notify(&rule, &holder_device, message.clone()).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pins the first DHCP server seen (unless servers are configured) and
+    /// raises an alert the first time any other server answers a client. A
+    /// reply is trusted by the MAC it came from: a rogue server can send from
+    /// the real one's IP or copy its server identifier, and doing so from
+    /// another MAC gives it away. A server configured by IP is pinned to the
+    /// MAC of the first reply from that IP. A learned server is kept in the
+    /// database so that a restart does not hand trust to whichever server
+    /// answers first. Returns whether the reply is trusted.
+    async fn check_dhcp_server(
+        &self,
+        mac: &str,
+        ip: IpAddr,
+        server_id: Option<IpAddr>,
+        client_mac: &str,
+        message_type: DhcpMessageType,
+        offered_ip: Option<IpAddr>,
+    ) -> Result<bool> {
+        if self.dhcp_relays.contains(mac) {
+            return Ok(true);
+        }
+
+        let mut servers = self.dhcp_servers.write().await;
+        let mut addresses = self.dhcp_server_addresses.write().await;
+        if servers.is_empty() && addresses.is_empty() {
+            for (learned_mac, learned_ip) in self.db.get_learned_dhcp_servers().await? {
+                servers.insert(learned_mac.clone(), true);
+                addresses.insert(learned_ip, Some(learned_mac));
+            }
+        }
+
+        let ip_key = ip.to_string();
+        if !servers.contains_key(mac)
+            && let Some(pinned @ None) = addresses.get_mut(&ip_key)
+        {
+            info!("Pinned DHCP server {} to {}", ip, mac);
+            *pinned = Some(mac.to_string());
+            servers.insert(mac.to_string(), true);
+        }
+
+        if let Some(&trusted) = servers.get(mac) {
+            return Ok(trusted);
+        }
+
+        if servers.is_empty() && addresses.is_empty() {
+            info!("Learned DHCP server {} ({})", mac, ip);
+            self.db.save_learned_dhcp_server(mac, &ip_key).await?;
+            servers.insert(mac.to_string(), true);
+            addresses.insert(ip_key, Some(mac.to_string()));
+            return Ok(true);
+        }
+
+        // The trusted server whose IP the reply came from or names.
+        let impersonated = [Some(ip), server_id]
+            .into_iter()
+            .flatten()
+            .find_map(|address| addresses.get(&address.to_string()).cloned().flatten());
+
+        servers.insert(mac.to_string(), false);
+        drop(addresses);
+        drop(servers);
+
+        let server_id = server_id
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| "none".to_string());
+        let offered_ip = offered_ip
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "none".to_string());
+
+        error!(
+            "Rogue DHCP server {} ({}, server id {})",
+            mac, ip, server_id
+        );
+
+        let message = format!("Rogue DHCP server {} ({})", mac, ip);
+        let log_entry = crate::models::LogEntry {
+            id: None,
+            timestamp: self.now(),
+            level: crate::models::LogLevel::Error,
+            category: "security".to_string(),
+            message: message.clone(),
+            details: Some(format!(
+                "server id: {}, {} to {} for {}{}",
+                server_id,
+                message_type,
+                client_mac,
+                offered_ip,
+                impersonated
+                    .map(|pinned| format!(", posing as {}", pinned))
+                    .unwrap_or_default()
+            )),
+        };
+        self.db.create_log(&log_entry).await?;
+
+        let Some(device) = self.db.get_device_by_mac(mac).await? else {
+            return Ok(false);
+        };
+        for rule in self.db.get_enabled_rules().await? {
+            if rule.trigger_type == TriggerType::RogueDhcpServer
+                && Self::rule_applies(&rule, &device)
+            {
+                self.notify(
+                    &rule,
+                    &device,
+                    format!(
+                        "{}, server id {}, {} for {} to {}",
+                        message, server_id, message_type, offered_ip, client_mac
+                    ),
+                )
+                .await?;
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Stores a lease from a DHCPACK. An ACK for the address the device
+    /// already holds, before that lease expired, is a renewal.
+    async fn record_lease(&self, device_id: i64, mut lease: DhcpLease) -> Result<()> {
+        if let Some(previous) = self.db.get_device_lease(device_id).await?
+            && previous.ip_address == lease.ip_address
+            && previous.is_active(lease.renewed_at)
+        {
+            lease.acquired_at = previous.acquired_at;
+            lease.renewals = previous.renewals + 1;
+        }
+
+        debug!(
+            "DHCP lease for {}: {} until {:?} (renewal {})",
+            lease.mac_address, lease.ip_address, lease.expires_at, lease.renewals
+        );
+        self.db.upsert_device_lease(device_id, &lease).await
+    }
+
+    async fn handle_device_disconnection(&self, mac: &str) -> Result<()> {
+        self.disconnect_device(mac, self.now(), None).await
+    }
+
+    /// Marks a device offline as of `at`, noting `reason` in the log.
+    async fn disconnect_device(
+        &self,
+        mac: &str,
+        at: DateTime<Utc>,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        // A MAC the device has since rotated away from says nothing about it.
+        if let Some(device) = self.db.get_device_by_mac(mac).await?
+            && device.mac_address == mac
+            && device.status != DeviceStatus::Offline
+        {
+            self.db
+                .update_device_status(mac, DeviceStatus::Offline, at)
+                .await?;
+
+            debug!("Device disconnected: {}", mac);
+
+            // Log disconnection
+            let log_entry = crate::models::LogEntry {
+                id: None,
+                timestamp: at,
+                level: crate::models::LogLevel::Warning,
+                category: "device".to_string(),
+                message: match reason {
+                    Some(reason) => format!("Device disconnected: {} ({})", mac, reason),
+                    None => format!("Device disconnected: {}", mac),
+                },
+                details: device.ip_address.clone(),
+            };
+            let _ = self.db.create_log(&log_entry).await;
+
+            let rules = self.db.get_enabled_rules().await?;
+
+            for rule in rules {
+                if !Self::rule_applies(&rule, &device) {
+                    continue;
+                }
+
+                if rule.trigger_type == TriggerType::DeviceDisconnected
+                    || rule.trigger_type == TriggerType::DeviceStatusChange
+                {
+                    let mut updated_device = device.clone();
+                    updated_device.status = DeviceStatus::Offline;
+                    self.send_notification(&rule, &updated_device).await?;
                 }
             }
         }
@@ -384,36 +2889,285 @@ impl Daemon {
         }
     }
 
+    async fn query_unnamed_devices(&self) {
+        let mut query_interval = interval(self.neighbor_check_interval);
+
+        loop {
+            query_interval.tick().await;
+
+            if let Err(e) = self.send_node_status_queries().await {
+                error!("Error sending NetBIOS node status queries: {}", e);
+            }
+        }
+    }
+
+    /// Asks online IPv4 devices without a hostname for their NetBIOS names.
+    /// The answers are picked up by the packet capture like any other NBNS
+    /// response.
+    async fn send_node_status_queries(&self) -> Result<()> {
+        let devices = self.db.get_devices_by_status(DeviceStatus::Online).await?;
+        let now = self.now();
+        let mut targets = Vec::new();
+
+        {
+            let mut queries = self.netbios_queries.lock().unwrap();
+            for device in devices {
+                let (Some(id), None) = (device.id, &device.hostname) else {
+                    continue;
+                };
+                let Some(ip) = device
+                    .ip_address
+                    .as_deref()
+                    .and_then(|ip| ip.parse::<Ipv4Addr>().ok())
+                else {
+                    continue;
+                };
+                if queries
+                    .get(&id)
+                    .is_some_and(|&queried| now - queried < NETBIOS_QUERY_RETRY)
+                {
+                    continue;
+                }
+                queries.insert(id, now);
+                targets.push((id, ip));
+            }
+        }
+
+        if targets.is_empty() {
+            return Ok(());
+        }
+
+        let socket = tokio::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+        for (id, ip) in targets {
+            debug!("Sending NetBIOS node status query to {}", ip);
+            // Answers are matched by sender, so the transaction ID only has
+            // to differ between queries; device ids past 16 bits wrap.
+            let transaction_id = u16::try_from(id.rem_euclid(1 << 16)).unwrap_or_default();
+            let request = nbns::node_status_request(transaction_id);
+            if let Err(e) = socket.send_to(&request, (ip, nbns::PORT)).await {
+                debug!("Failed to send NetBIOS node status query to {}: {}", ip, e);
+            }
+        }
+
+        // Keep the port open for a moment so the answers are not refused.
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        Ok(())
+    }
+
+    async fn sweep_periodically(&self, scan_interval: Duration) {
+        let mut sweep_interval = interval(scan_interval);
+
+        loop {
+            sweep_interval.tick().await;
+
+            // Capture can fail after startup; replies would go unseen.
+            if !self.live.load(Ordering::Relaxed) {
+                debug!("Skipping scheduled ARP sweep, packet capture is unavailable");
+                continue;
+            }
+            if self.scan_running.swap(true, Ordering::AcqRel) {
+                debug!("Skipping scheduled ARP sweep, one is already running");
+                continue;
+            }
+            self.arp_sweep().await;
+        }
+    }
+
+    /// Starts an ARP sweep of every monitored interface in the background.
+    /// Fails if a sweep is already running or the daemon is not capturing
+    /// live, since replies could not be seen.
+    pub fn start_arp_sweep(self: &Arc<Self>) -> Result<()> {
+        if !self.live.load(Ordering::Relaxed) {
+            return Err(DaemonError::Conflict(
+                "Scanning needs a live packet capture".to_string(),
+            ));
+        }
+        if self.scan_running.swap(true, Ordering::AcqRel) {
+            return Err(DaemonError::Conflict(
+                "A scan is already running".to_string(),
+            ));
+        }
+
+        let daemon = Arc::clone(self);
+        tokio::spawn(async move {
+            daemon.arp_sweep().await;
+        });
+
+        Ok(())
+    }
+
+    /// Sweeps the monitored interfaces one after another. The caller must
+    /// have set `scan_running`, which is cleared when the sweep ends.
+    async fn arp_sweep(&self) {
+        for interface in &self.interfaces {
+            if self
+                .degraded_interfaces
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|degraded| &degraded.interface == interface)
+            {
+                debug!(
+                    "Not sweeping {}, packet capture is unavailable there",
+                    interface
+                );
+                continue;
+            }
+            info!("Starting ARP sweep on {}", interface);
+
+            let name = interface.clone();
+            let rate = self.arp_scan_rate;
+            match tokio::task::spawn_blocking(move || scanner::sweep(&name, rate)).await {
+                Ok(Ok(sent)) => info!("ARP sweep on {} sent {} requests", interface, sent),
+                Ok(Err(e)) => error!("ARP sweep on {} failed: {}", interface, e),
+                Err(e) => error!("ARP sweep on {} panicked: {}", interface, e),
+            }
+        }
+
+        self.scan_running.store(false, Ordering::Release);
+    }
+
     async fn check_timeouts(&self) -> Result<()> {
         let devices = self.db.get_devices_by_status(DeviceStatus::Online).await?;
-        let now = Utc::now();
+        let mut leases = self.db.get_all_device_leases().await?;
+        let now = self.now();
 
+        // Quiet devices, with the expiry of the lease they let run out.
+        let mut timed_out = Vec::new();
         for device in devices {
+            let lease = device.id.and_then(|id| leases.remove(&id));
+
+            // A device quiet since its lease ran out has probably left, and
+            // the expiry says when. Renewals are unicast, though, so one we
+            // missed is ruled out by probing the device first.
+            if let Some(expires_at) = lease.as_ref().and_then(|lease| lease.expires_at)
+                && expires_at <= now
+                && device.last_seen <= expires_at
+            {
+                timed_out.push((device, Some(expires_at)));
+                continue;
+            }
+
             let time_since_seen = now.signed_duration_since(device.last_seen);
+            if time_since_seen.num_seconds() as u64 <= self.device_timeout.as_secs() {
+                continue;
+            }
+
+            // A device that renews its lease on schedule is still there
+            // until a renewal is overdue, however quiet it is in between.
+            if let Some(lease) = lease
+                && lease.renewals > 0
+                && let Some(renews_at) = lease.renews_at
+                && now <= renews_at + self.device_timeout
+            {
+                debug!(
+                    "Device {} is quiet but renews its DHCP lease, next at {}",
+                    device.mac_address, renews_at
+                );
+                continue;
+            }
+
+            timed_out.push((device, None));
+        }
+
+        // Probe every quiet device at once so one slow device does not hold
+        // up the rest.
+        let answers = futures::future::join_all(
+            timed_out
+                .iter()
+                .map(|(device, _)| self.probe_presence(device)),
+        )
+        .await;
+
+        for ((device, lease_expired_at), answer) in timed_out.iter().zip(answers) {
+            if let Some(probe) = answer {
+                debug!(
+                    "Device {} answered a {} probe, keeping it online",
+                    device.mac_address, probe
+                );
+                self.db
+                    .update_device_status(&device.mac_address, DeviceStatus::Online, self.now())
+                    .await?;
+                continue;
+            }
 
-            if time_since_seen.num_seconds() as u64 > self.device_timeout.as_secs() {
+            if let Some(expires_at) = *lease_expired_at {
                 info!(
-                    "Device {} timed out (last seen {} seconds ago)",
-                    device.mac_address,
-                    time_since_seen.num_seconds()
+                    "DHCP lease of {} expired at {} without renewal",
+                    device.mac_address, expires_at
                 );
-                self.handle_device_disconnection(&device.mac_address)
+                self.disconnect_device(&device.mac_address, expires_at, Some("DHCP lease expired"))
                     .await?;
+                continue;
             }
+
+            info!(
+                "Device {} timed out (last seen {} seconds ago)",
+                device.mac_address,
+                now.signed_duration_since(device.last_seen).num_seconds()
+            );
+            self.handle_device_disconnection(&device.mac_address)
+                .await?;
         }
 
         Ok(())
     }
 
+    /// Checks whether a device that went quiet is still there, using its own
+    /// probe list or the configured one. Returns the probe it answered.
+    /// Only live captures probe; a replayed device is long gone.
+    async fn probe_presence(&self, device: &Device) -> Option<PresenceProbe> {
+        let probes = device
+            .presence_probes
+            .as_deref()
+            .unwrap_or(&self.presence_probes);
+        if probes.is_empty() || !self.live.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let mac = device.mac_address.parse().ok()?;
+        let ipv6 = match device.id {
+            Some(id) => self
+                .db
+                .get_device_addresses(id)
+                .await
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|address| match address.address.parse() {
+                    Ok(IpAddr::V6(ip)) => Some(ip),
+                    _ => None,
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let target = ProbeTarget {
+            mac,
+            ipv4: device.ip_address.as_deref().and_then(|ip| ip.parse().ok()),
+            ipv6,
+        };
+
+        let interface = device
+            .segment
+            .as_deref()
+            .unwrap_or_else(|| self.primary_segment());
+        probe::probe(interface, &target, probes).await
+    }
+
     async fn send_notification(&self, rule: &Rule, device: &Device) -> Result<()> {
+        let message = format!(
+            "Rule '{}' triggered for device {}",
+            rule.name, device.mac_address
+        );
+        self.notify(rule, device, message).await
+    }
+
+    async fn notify(&self, rule: &Rule, device: &Device, message: String) -> Result<()> {
         let event = NotificationEvent {
-            timestamp: Utc::now(),
+            timestamp: self.now(),
             event_type: rule.trigger_type.clone(),
             device: device.clone(),
-            message: format!(
-                "Rule '{}' triggered for device {}",
-                rule.name, device.mac_address
-            ),
+            message,
         };
 
         let notifier = self.notifier.read().await;
@@ -1,11 +1,18 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
 use sqlx::Row;
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::str::FromStr;
 use tracing::info;
 
 use crate::errors::{DaemonError, Result};
-use crate::models::{Device, DeviceStatus, LogEntry, LogLevel, Rule, TriggerType};
+use crate::models::{
+    AddressFamily, AddressSource, AdvertisedService, Device, DeviceAddress, DeviceMac,
+    DeviceSegment, DeviceService, DeviceStatus, DeviceTypeSource, IpConflict, LogEntry, LogLevel, PresenceProbe,
+    DhcpLease, DiscoveryProtocol, LinkAnnouncement, LinkDiscovery, Rule, TriggerType,
+    UnknownFingerprint, UpnpInfo, INFRASTRUCTURE_DEVICE_TYPES,
+};
 
 #[derive(Clone)]
 pub struct Database {
@@ -32,6 +39,25 @@ impl Database {
         Ok(db)
     }
 
+    /// Opens an empty database that lives only as long as this handle, for
+    /// replays and tests. Every SQLite in-memory connection is a database of
+    /// its own, so the pool keeps exactly one connection open.
+    pub async fn in_memory() -> Result<Self> {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")?.foreign_keys(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?;
+
+        let db = Self { pool };
+        db.run_migrations().await?;
+        Ok(db)
+    }
+
     async fn run_migrations(&self) -> Result<()> {
         sqlx::query(
             r#"
@@ -44,13 +70,45 @@ impl Database {
                 vendor TEXT,
                 first_seen TEXT NOT NULL,
                 last_seen TEXT NOT NULL,
-                status TEXT NOT NULL DEFAULT 'unknown'
+                status TEXT NOT NULL DEFAULT 'unknown',
+                segment TEXT,
+                vlan_id INTEGER,
+                locally_administered INTEGER NOT NULL DEFAULT 0,
+                dhcp_client_id TEXT,
+                dhcp_fingerprint TEXT,
+                presence_probes TEXT,
+                pinned INTEGER NOT NULL DEFAULT 0,
+                os_family TEXT,
+                device_type TEXT,
+                device_type_source TEXT,
+                classification_confidence INTEGER,
+                tcp_signature TEXT,
+                tcp_os TEXT,
+                tcp_os_flavor TEXT
             )
             "#,
         )
         .execute(&self.pool)
         .await?;
 
+        self.add_column_if_missing("devices", "segment", "TEXT").await?;
+        self.add_column_if_missing("devices", "vlan_id", "INTEGER").await?;
+        self.add_column_if_missing("devices", "locally_administered", "INTEGER NOT NULL DEFAULT 0")
+            .await?;
+        self.add_column_if_missing("devices", "dhcp_client_id", "TEXT").await?;
+        self.add_column_if_missing("devices", "dhcp_fingerprint", "TEXT").await?;
+        self.add_column_if_missing("devices", "presence_probes", "TEXT").await?;
+        self.add_column_if_missing("devices", "pinned", "INTEGER NOT NULL DEFAULT 0")
+            .await?;
+        self.add_column_if_missing("devices", "os_family", "TEXT").await?;
+        self.add_column_if_missing("devices", "device_type", "TEXT").await?;
+        self.add_column_if_missing("devices", "device_type_source", "TEXT").await?;
+        self.add_column_if_missing("devices", "classification_confidence", "INTEGER")
+            .await?;
+        self.add_column_if_missing("devices", "tcp_signature", "TEXT").await?;
+        self.add_column_if_missing("devices", "tcp_os", "TEXT").await?;
+        self.add_column_if_missing("devices", "tcp_os_flavor", "TEXT").await?;
+
         sqlx::query(
             r#"
             CREATE INDEX IF NOT EXISTS idx_devices_mac ON devices(mac_address);
@@ -60,6 +118,212 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS device_addresses (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                device_id INTEGER NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+                family TEXT NOT NULL,
+                address TEXT NOT NULL,
+                source TEXT NOT NULL,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                UNIQUE(device_id, address)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_device_addresses_device ON device_addresses(device_id);
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Carry over addresses stored in the single ip_address column by
+        // older versions, then keep only IPv4 there.
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO device_addresses (device_id, family, address, source, first_seen, last_seen)
+            SELECT id, CASE WHEN instr(ip_address, ':') > 0 THEN 'ipv6' ELSE 'ipv4' END,
+                   ip_address, 'arp', last_seen, last_seen
+            FROM devices
+            WHERE ip_address IS NOT NULL
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("UPDATE devices SET ip_address = NULL WHERE instr(ip_address, ':') > 0")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS device_segments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                device_id INTEGER NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+                segment TEXT NOT NULL,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                UNIQUE(device_id, segment)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS device_macs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                device_id INTEGER NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+                mac_address TEXT NOT NULL UNIQUE,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_device_macs_device ON device_macs(device_id);
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Devices from older versions start their history with their own MAC.
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO device_macs (device_id, mac_address, first_seen, last_seen)
+            SELECT id, mac_address, first_seen, last_seen
+            FROM devices
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS device_services (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                device_id INTEGER NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+                service_type TEXT NOT NULL,
+                instance TEXT NOT NULL,
+                port INTEGER,
+                txt TEXT NOT NULL,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                UNIQUE(device_id, service_type, instance)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS device_upnp (
+                device_id INTEGER PRIMARY KEY REFERENCES devices(id) ON DELETE CASCADE,
+                uuid TEXT,
+                server TEXT,
+                device_type TEXT,
+                location TEXT,
+                friendly_name TEXT,
+                manufacturer TEXT,
+                model_name TEXT,
+                description_fetched_at TEXT,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS ip_conflicts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                ip_address TEXT NOT NULL,
+                mac_address TEXT NOT NULL,
+                conflicting_mac_address TEXT NOT NULL,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                UNIQUE(ip_address, mac_address, conflicting_mac_address)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS device_leases (
+                device_id INTEGER PRIMARY KEY REFERENCES devices(id) ON DELETE CASCADE,
+                ip_address TEXT NOT NULL,
+                server_id TEXT,
+                server_mac TEXT NOT NULL,
+                lease_time_secs INTEGER,
+                renewal_time_secs INTEGER,
+                rebinding_time_secs INTEGER,
+                acquired_at TEXT NOT NULL,
+                renewed_at TEXT NOT NULL,
+                renews_at TEXT,
+                rebinds_at TEXT,
+                expires_at TEXT,
+                renewals INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS device_link_discovery (
+                device_id INTEGER PRIMARY KEY REFERENCES devices(id) ON DELETE CASCADE,
+                protocol TEXT NOT NULL,
+                chassis_id TEXT,
+                system_name TEXT,
+                system_description TEXT,
+                platform TEXT,
+                capabilities TEXT NOT NULL,
+                management_address TEXT,
+                port_id TEXT,
+                port_description TEXT,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS unknown_fingerprints (
+                fingerprint TEXT NOT NULL,
+                vendor_class TEXT NOT NULL DEFAULT '',
+                mac_address TEXT NOT NULL,
+                hostname TEXT,
+                vendor TEXT,
+                times_seen INTEGER NOT NULL DEFAULT 1,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                PRIMARY KEY (fingerprint, vendor_class)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS rules (
@@ -71,6 +335,64 @@ impl Database {
                 enabled INTEGER NOT NULL DEFAULT 1,
                 notification_channels TEXT NOT NULL,
                 created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                segment_filter TEXT,
+                vendor_filter TEXT,
+                os_family_filter TEXT,
+                device_type_filter TEXT,
+                tcp_os_filter TEXT
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        self.add_column_if_missing("rules", "segment_filter", "TEXT").await?;
+        self.add_column_if_missing("rules", "vendor_filter", "TEXT").await?;
+        self.add_column_if_missing("rules", "os_family_filter", "TEXT").await?;
+        self.add_column_if_missing("rules", "device_type_filter", "TEXT").await?;
+        self.add_column_if_missing("rules", "tcp_os_filter", "TEXT").await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_rules_enabled ON rules(enabled);
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS logs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                level TEXT NOT NULL,
+                category TEXT NOT NULL,
+                message TEXT NOT NULL,
+                details TEXT
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_logs_timestamp ON logs(timestamp DESC);
+            CREATE INDEX IF NOT EXISTS idx_logs_level ON logs(level);
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS notification_channels (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                channel_type TEXT NOT NULL,
+                config TEXT NOT NULL,
+                created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL
             )
             "#,
@@ -80,212 +402,1339 @@ impl Database {
 
         sqlx::query(
             r#"
-            CREATE INDEX IF NOT EXISTS idx_rules_enabled ON rules(enabled);
+            CREATE INDEX IF NOT EXISTS idx_notification_channels_name ON notification_channels(name);
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS dhcp_servers (
+                mac_address TEXT PRIMARY KEY,
+                ip_address TEXT NOT NULL,
+                learned_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS oui_registry (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                data BLOB NOT NULL,
+                entries INTEGER NOT NULL,
+                imported_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        info!("Database migrations completed");
+        Ok(())
+    }
+
+    /// Adds a column to a table created by an older version of foxd.
+    async fn add_column_if_missing(
+        &self,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<()> {
+        let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(&self.pool)
+            .await?;
+
+        if !columns.iter().any(|row| row.get::<String, _>("name") == column) {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn upsert_device(&self, device: &Device) -> Result<i64> {
+        let status_str = device.status.to_string();
+        let first_seen = device.first_seen.to_rfc3339();
+        let last_seen = device.last_seen.to_rfc3339();
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO devices (mac_address, ip_address, hostname, nickname, vendor, first_seen, last_seen, status, segment, vlan_id, locally_administered, dhcp_client_id, dhcp_fingerprint, presence_probes, pinned, os_family, device_type, device_type_source, classification_confidence, tcp_signature, tcp_os, tcp_os_flavor)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(mac_address) DO UPDATE SET
+                ip_address = excluded.ip_address,
+                hostname = excluded.hostname,
+                vendor = excluded.vendor,
+                last_seen = excluded.last_seen,
+                status = excluded.status,
+                segment = excluded.segment,
+                vlan_id = excluded.vlan_id,
+                locally_administered = excluded.locally_administered,
+                dhcp_client_id = excluded.dhcp_client_id,
+                dhcp_fingerprint = excluded.dhcp_fingerprint,
+                os_family = excluded.os_family,
+                device_type = excluded.device_type,
+                device_type_source = excluded.device_type_source,
+                classification_confidence = excluded.classification_confidence,
+                tcp_signature = excluded.tcp_signature,
+                tcp_os = excluded.tcp_os,
+                tcp_os_flavor = excluded.tcp_os_flavor
+            RETURNING id
+            "#,
+        )
+        .bind(&device.mac_address)
+        .bind(&device.ip_address)
+        .bind(&device.hostname)
+        .bind(&device.nickname)
+        .bind(&device.vendor)
+        .bind(&first_seen)
+        .bind(&last_seen)
+        .bind(&status_str)
+        .bind(&device.segment)
+        .bind(device.vlan_id)
+        .bind(device.locally_administered)
+        .bind(&device.dhcp_client_id)
+        .bind(&device.dhcp_fingerprint)
+        .bind(device.presence_probes.as_deref().map(encode_presence_probes))
+        .bind(device.pinned)
+        .bind(&device.os_family)
+        .bind(&device.device_type)
+        .bind(device.device_type_source.map(|source| source.to_string()))
+        .bind(device.classification_confidence)
+        .bind(&device.tcp_signature)
+        .bind(&device.tcp_os)
+        .bind(&device.tcp_os_flavor)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.get(0))
+    }
+
+    /// Looks a device up by its current MAC or any MAC it used before.
+    pub async fn get_device_by_mac(&self, mac: &str) -> Result<Option<Device>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, mac_address, ip_address, hostname, nickname, vendor, first_seen, last_seen, status, segment, vlan_id,
+                   locally_administered, dhcp_client_id, dhcp_fingerprint, presence_probes, pinned,
+                   os_family, device_type, device_type_source, classification_confidence,
+                   tcp_signature, tcp_os, tcp_os_flavor
+            FROM devices
+            WHERE mac_address = ?
+               OR id = (SELECT device_id FROM device_macs WHERE mac_address = ?)
+            ORDER BY mac_address = ? DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(mac)
+        .bind(mac)
+        .bind(mac)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Some(self.row_to_device(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_all_devices(&self) -> Result<Vec<Device>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, mac_address, ip_address, hostname, nickname, vendor, first_seen, last_seen, status, segment, vlan_id,
+                   locally_administered, dhcp_client_id, dhcp_fingerprint, presence_probes, pinned,
+                   os_family, device_type, device_type_source, classification_confidence,
+                   tcp_signature, tcp_os, tcp_os_flavor
+            FROM devices
+            ORDER BY last_seen DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| self.row_to_device(row))
+            .collect()
+    }
+
+    pub async fn get_devices_by_status(&self, status: DeviceStatus) -> Result<Vec<Device>> {
+        let status_str = status.to_string();
+        let rows = sqlx::query(
+            r#"
+            SELECT id, mac_address, ip_address, hostname, nickname, vendor, first_seen, last_seen, status, segment, vlan_id,
+                   locally_administered, dhcp_client_id, dhcp_fingerprint, presence_probes, pinned,
+                   os_family, device_type, device_type_source, classification_confidence,
+                   tcp_signature, tcp_os, tcp_os_flavor
+            FROM devices
+            WHERE status = ?
+            ORDER BY last_seen DESC
+            "#,
+        )
+        .bind(&status_str)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| self.row_to_device(row))
+            .collect()
+    }
+
+    /// Returns the devices known by `hostname`, ignoring case.
+    pub async fn get_devices_by_hostname(&self, hostname: &str) -> Result<Vec<Device>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, mac_address, ip_address, hostname, nickname, vendor, first_seen, last_seen, status, segment, vlan_id,
+                   locally_administered, dhcp_client_id, dhcp_fingerprint, presence_probes, pinned,
+                   os_family, device_type, device_type_source, classification_confidence,
+                   tcp_signature, tcp_os, tcp_os_flavor
+            FROM devices
+            WHERE hostname = ? COLLATE NOCASE
+            ORDER BY last_seen DESC
+            "#,
+        )
+        .bind(hostname)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| self.row_to_device(row))
+            .collect()
+    }
+
+    /// Returns the pinned devices whose current IPv4 address is `ip`.
+    pub async fn get_pinned_devices_by_ipv4(&self, ip: &str) -> Result<Vec<Device>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, mac_address, ip_address, hostname, nickname, vendor, first_seen, last_seen, status, segment, vlan_id,
+                   locally_administered, dhcp_client_id, dhcp_fingerprint, presence_probes, pinned,
+                   os_family, device_type, device_type_source, classification_confidence,
+                   tcp_signature, tcp_os, tcp_os_flavor
+            FROM devices
+            WHERE pinned = 1 AND ip_address = ?
+            "#,
+        )
+        .bind(ip)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| self.row_to_device(row))
+            .collect()
+    }
+
+    pub async fn update_device_status(
+        &self,
+        mac: &str,
+        status: DeviceStatus,
+        timestamp: DateTime<Utc>,
+    ) -> Result<()> {
+        let status_str = status.to_string();
+        let last_seen = timestamp.to_rfc3339();
+
+        sqlx::query(
+            r#"
+            UPDATE devices
+            SET status = ?, last_seen = ?
+            WHERE mac_address = ?
+            "#,
+        )
+        .bind(&status_str)
+        .bind(&last_seen)
+        .bind(mac)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Makes `mac` the current MAC of a device.
+    pub async fn update_device_mac(&self, device_id: i64, mac: &str) -> Result<()> {
+        sqlx::query("UPDATE devices SET mac_address = ? WHERE id = ?")
+            .bind(mac)
+            .bind(device_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Folds the device `from_id` into `into_id`: its addresses, segments,
+    /// MAC history, services, UPnP details, lease and LLDP/CDP details move
+    /// over and the device itself is deleted.
+    pub async fn merge_devices(&self, into_id: i64, from_id: i64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for table in [
+            "device_addresses",
+            "device_segments",
+            "device_macs",
+            "device_services",
+            "device_upnp",
+            "device_leases",
+            "device_link_discovery",
+        ] {
+            sqlx::query(&format!(
+                "UPDATE OR IGNORE {} SET device_id = ? WHERE device_id = ?",
+                table
+            ))
+            .bind(into_id)
+            .bind(from_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query("DELETE FROM devices WHERE id = ?")
+            .bind(from_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn update_device_hostname(&self, mac: &str, hostname: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE devices
+            SET hostname = ?
+            WHERE mac_address = ?
+            "#,
+        )
+        .bind(hostname)
+        .bind(mac)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_device_nickname(&self, mac: &str, nickname: Option<String>) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE devices
+            SET nickname = ?
+            WHERE mac_address = ?
+            "#,
+        )
+        .bind(&nickname)
+        .bind(mac)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sets the probes tried before the device is marked offline; `None`
+    /// goes back to the configured default.
+    pub async fn update_device_presence_probes(
+        &self,
+        mac: &str,
+        probes: Option<&[PresenceProbe]>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE devices
+            SET presence_probes = ?
+            WHERE mac_address = ?
+            "#,
+        )
+        .bind(probes.map(encode_presence_probes))
+        .bind(mac)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Pins or unpins the device's IPv4 address to its MAC for ARP spoofing
+    /// detection.
+    pub async fn update_device_pinned(&self, mac: &str, pinned: bool) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE devices
+            SET pinned = ?
+            WHERE mac_address = ?
+            "#,
+        )
+        .bind(pinned)
+        .bind(mac)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    fn row_to_device(&self, row: sqlx::sqlite::SqliteRow) -> Result<Device> {
+        let status_str: String = row.get("status");
+        let status = match status_str.as_str() {
+            "online" => DeviceStatus::Online,
+            "offline" => DeviceStatus::Offline,
+            _ => DeviceStatus::Unknown,
+        };
+
+        let first_seen_str: String = row.get("first_seen");
+        let last_seen_str: String = row.get("last_seen");
+
+        Ok(Device {
+            id: Some(row.get("id")),
+            mac_address: row.get("mac_address"),
+            ip_address: row.get("ip_address"),
+            hostname: row.get("hostname"),
+            nickname: row.get("nickname"),
+            vendor: row.get("vendor"),
+            first_seen: chrono::DateTime::parse_from_rfc3339(&first_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+            last_seen: chrono::DateTime::parse_from_rfc3339(&last_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+            status,
+            segment: row.get("segment"),
+            vlan_id: row.get("vlan_id"),
+            locally_administered: row.get("locally_administered"),
+            dhcp_client_id: row.get("dhcp_client_id"),
+            dhcp_fingerprint: row.get("dhcp_fingerprint"),
+            addresses: Vec::new(),
+            segments: Vec::new(),
+            mac_history: Vec::new(),
+            services: Vec::new(),
+            upnp: None,
+            lease: None,
+            link_discovery: None,
+            presence_probes: row
+                .get::<Option<String>, _>("presence_probes")
+                .map(|probes| decode_presence_probes(&probes)),
+            pinned: row.get("pinned"),
+            os_family: row.get("os_family"),
+            infrastructure: row
+                .get::<Option<String>, _>("device_type")
+                .is_some_and(|t| INFRASTRUCTURE_DEVICE_TYPES.contains(&t.as_str())),
+            device_type: row.get("device_type"),
+            device_type_source: row
+                .get::<Option<String>, _>("device_type_source")
+                .and_then(|source| match source.as_str() {
+                    "fingerprint" => Some(DeviceTypeSource::Fingerprint),
+                    "announced" => Some(DeviceTypeSource::Announced),
+                    _ => None,
+                }),
+            classification_confidence: row.get("classification_confidence"),
+            tcp_signature: row.get("tcp_signature"),
+            tcp_os: row.get("tcp_os"),
+            tcp_os_flavor: row.get("tcp_os_flavor"),
+        })
+    }
+
+    pub async fn upsert_device_address(
+        &self,
+        device_id: i64,
+        ip: &IpAddr,
+        source: AddressSource,
+        seen: DateTime<Utc>,
+    ) -> Result<()> {
+        let seen_str = seen.to_rfc3339();
+
+        sqlx::query(
+            r#"
+            INSERT INTO device_addresses (device_id, family, address, source, first_seen, last_seen)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(device_id, address) DO UPDATE SET
+                source = excluded.source,
+                last_seen = excluded.last_seen
+            "#,
+        )
+        .bind(device_id)
+        .bind(AddressFamily::from(ip).to_string())
+        .bind(ip.to_string())
+        .bind(source.to_string())
+        .bind(&seen_str)
+        .bind(&seen_str)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_device_addresses(&self, device_id: i64) -> Result<Vec<DeviceAddress>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT device_id, family, address, source, first_seen, last_seen
+            FROM device_addresses
+            WHERE device_id = ?
+            ORDER BY last_seen DESC
+            "#,
+        )
+        .bind(device_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| self.row_to_device_address(row))
+            .collect()
+    }
+
+    /// Returns all known addresses grouped by device id.
+    pub async fn get_all_device_addresses(&self) -> Result<HashMap<i64, Vec<DeviceAddress>>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT device_id, family, address, source, first_seen, last_seen
+            FROM device_addresses
+            ORDER BY last_seen DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut addresses: HashMap<i64, Vec<DeviceAddress>> = HashMap::new();
+        for row in rows {
+            let device_id: i64 = row.get("device_id");
+            addresses
+                .entry(device_id)
+                .or_default()
+                .push(self.row_to_device_address(row)?);
+        }
+
+        Ok(addresses)
+    }
+
+    fn row_to_device_address(&self, row: sqlx::sqlite::SqliteRow) -> Result<DeviceAddress> {
+        let family_str: String = row.get("family");
+        let family = match family_str.as_str() {
+            "ipv6" => AddressFamily::Ipv6,
+            _ => AddressFamily::Ipv4,
+        };
+
+        let source_str: String = row.get("source");
+        let source = match source_str.as_str() {
+            "dhcp" => AddressSource::Dhcp,
+            "ndp" => AddressSource::Ndp,
+            "netlink" => AddressSource::Netlink,
+            "mdns" => AddressSource::Mdns,
+            "ssdp" => AddressSource::Ssdp,
+            "nbns" => AddressSource::Nbns,
+            "llmnr" => AddressSource::Llmnr,
+            "lease_file" => AddressSource::LeaseFile,
+            "lldp" => AddressSource::Lldp,
+            "cdp" => AddressSource::Cdp,
+            "tcp" => AddressSource::Tcp,
+            _ => AddressSource::Arp,
+        };
+
+        let first_seen_str: String = row.get("first_seen");
+        let last_seen_str: String = row.get("last_seen");
+
+        Ok(DeviceAddress {
+            family,
+            address: row.get("address"),
+            source,
+            first_seen: chrono::DateTime::parse_from_rfc3339(&first_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+            last_seen: chrono::DateTime::parse_from_rfc3339(&last_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+        })
+    }
+
+    pub async fn upsert_device_segment(
+        &self,
+        device_id: i64,
+        segment: &str,
+        seen: DateTime<Utc>,
+    ) -> Result<()> {
+        let seen_str = seen.to_rfc3339();
+
+        sqlx::query(
+            r#"
+            INSERT INTO device_segments (device_id, segment, first_seen, last_seen)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(device_id, segment) DO UPDATE SET
+                last_seen = excluded.last_seen
+            "#,
+        )
+        .bind(device_id)
+        .bind(segment)
+        .bind(&seen_str)
+        .bind(&seen_str)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_device_segments(&self, device_id: i64) -> Result<Vec<DeviceSegment>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT device_id, segment, first_seen, last_seen
+            FROM device_segments
+            WHERE device_id = ?
+            ORDER BY last_seen DESC
+            "#,
+        )
+        .bind(device_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| self.row_to_device_segment(row))
+            .collect()
+    }
+
+    /// Returns all known segments grouped by device id.
+    pub async fn get_all_device_segments(&self) -> Result<HashMap<i64, Vec<DeviceSegment>>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT device_id, segment, first_seen, last_seen
+            FROM device_segments
+            ORDER BY last_seen DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut segments: HashMap<i64, Vec<DeviceSegment>> = HashMap::new();
+        for row in rows {
+            let device_id: i64 = row.get("device_id");
+            segments
+                .entry(device_id)
+                .or_default()
+                .push(self.row_to_device_segment(row)?);
+        }
+
+        Ok(segments)
+    }
+
+    fn row_to_device_segment(&self, row: sqlx::sqlite::SqliteRow) -> Result<DeviceSegment> {
+        let first_seen_str: String = row.get("first_seen");
+        let last_seen_str: String = row.get("last_seen");
+
+        Ok(DeviceSegment {
+            segment: row.get("segment"),
+            first_seen: chrono::DateTime::parse_from_rfc3339(&first_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+            last_seen: chrono::DateTime::parse_from_rfc3339(&last_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+        })
+    }
+
+    pub async fn upsert_device_mac(
+        &self,
+        device_id: i64,
+        mac: &str,
+        seen: DateTime<Utc>,
+    ) -> Result<()> {
+        let seen_str = seen.to_rfc3339();
+
+        sqlx::query(
+            r#"
+            INSERT INTO device_macs (device_id, mac_address, first_seen, last_seen)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(mac_address) DO UPDATE SET
+                device_id = excluded.device_id,
+                last_seen = excluded.last_seen
+            "#,
+        )
+        .bind(device_id)
+        .bind(mac)
+        .bind(&seen_str)
+        .bind(&seen_str)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_device_macs(&self, device_id: i64) -> Result<Vec<DeviceMac>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT device_id, mac_address, first_seen, last_seen
+            FROM device_macs
+            WHERE device_id = ?
+            ORDER BY last_seen DESC
+            "#,
+        )
+        .bind(device_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| self.row_to_device_mac(row))
+            .collect()
+    }
+
+    /// Returns the MAC history of every device, grouped by device id.
+    pub async fn get_all_device_macs(&self) -> Result<HashMap<i64, Vec<DeviceMac>>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT device_id, mac_address, first_seen, last_seen
+            FROM device_macs
+            ORDER BY last_seen DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut macs: HashMap<i64, Vec<DeviceMac>> = HashMap::new();
+        for row in rows {
+            let device_id: i64 = row.get("device_id");
+            macs.entry(device_id)
+                .or_default()
+                .push(self.row_to_device_mac(row)?);
+        }
+
+        Ok(macs)
+    }
+
+    fn row_to_device_mac(&self, row: sqlx::sqlite::SqliteRow) -> Result<DeviceMac> {
+        let first_seen_str: String = row.get("first_seen");
+        let last_seen_str: String = row.get("last_seen");
+
+        Ok(DeviceMac {
+            mac_address: row.get("mac_address"),
+            first_seen: chrono::DateTime::parse_from_rfc3339(&first_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+            last_seen: chrono::DateTime::parse_from_rfc3339(&last_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+        })
+    }
+
+    pub async fn upsert_device_service(
+        &self,
+        device_id: i64,
+        service: &AdvertisedService,
+        seen: DateTime<Utc>,
+    ) -> Result<()> {
+        let txt_json = serde_json::to_string(&service.txt)?;
+        let seen_str = seen.to_rfc3339();
+
+        sqlx::query(
+            r#"
+            INSERT INTO device_services (device_id, service_type, instance, port, txt, first_seen, last_seen)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(device_id, service_type, instance) DO UPDATE SET
+                port = COALESCE(excluded.port, device_services.port),
+                txt = CASE WHEN excluded.txt = '[]' THEN device_services.txt ELSE excluded.txt END,
+                last_seen = excluded.last_seen
+            "#,
+        )
+        .bind(device_id)
+        .bind(&service.service_type)
+        .bind(&service.instance)
+        .bind(service.port)
+        .bind(&txt_json)
+        .bind(&seen_str)
+        .bind(&seen_str)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_device_services(&self, device_id: i64) -> Result<Vec<DeviceService>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT device_id, service_type, instance, port, txt, first_seen, last_seen
+            FROM device_services
+            WHERE device_id = ?
+            ORDER BY service_type, instance
+            "#,
+        )
+        .bind(device_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| self.row_to_device_service(row))
+            .collect()
+    }
+
+    /// Returns all advertised services grouped by device id.
+    pub async fn get_all_device_services(&self) -> Result<HashMap<i64, Vec<DeviceService>>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT device_id, service_type, instance, port, txt, first_seen, last_seen
+            FROM device_services
+            ORDER BY service_type, instance
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut services: HashMap<i64, Vec<DeviceService>> = HashMap::new();
+        for row in rows {
+            let device_id: i64 = row.get("device_id");
+            services
+                .entry(device_id)
+                .or_default()
+                .push(self.row_to_device_service(row)?);
+        }
+
+        Ok(services)
+    }
+
+    fn row_to_device_service(&self, row: sqlx::sqlite::SqliteRow) -> Result<DeviceService> {
+        let txt_json: String = row.get("txt");
+        let first_seen_str: String = row.get("first_seen");
+        let last_seen_str: String = row.get("last_seen");
+
+        Ok(DeviceService {
+            service: AdvertisedService {
+                service_type: row.get("service_type"),
+                instance: row.get("instance"),
+                port: row.get("port"),
+                txt: serde_json::from_str(&txt_json)?,
+            },
+            first_seen: chrono::DateTime::parse_from_rfc3339(&first_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+            last_seen: chrono::DateTime::parse_from_rfc3339(&last_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+        })
+    }
+
+    /// Records an SSDP announcement. Fields missing from `info` keep their
+    /// stored values; the description fields are left alone.
+    pub async fn upsert_device_upnp(&self, device_id: i64, info: &UpnpInfo) -> Result<()> {
+        let first_seen = info.first_seen.to_rfc3339();
+        let last_seen = info.last_seen.to_rfc3339();
+
+        sqlx::query(
+            r#"
+            INSERT INTO device_upnp (device_id, uuid, server, device_type, location, first_seen, last_seen)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(device_id) DO UPDATE SET
+                uuid = COALESCE(excluded.uuid, device_upnp.uuid),
+                server = COALESCE(excluded.server, device_upnp.server),
+                device_type = COALESCE(excluded.device_type, device_upnp.device_type),
+                location = COALESCE(excluded.location, device_upnp.location),
+                last_seen = excluded.last_seen
             "#,
         )
+        .bind(device_id)
+        .bind(&info.uuid)
+        .bind(&info.server)
+        .bind(&info.device_type)
+        .bind(&info.location)
+        .bind(&first_seen)
+        .bind(&last_seen)
         .execute(&self.pool)
         .await?;
 
+        Ok(())
+    }
+
+    pub async fn update_device_upnp_description(
+        &self,
+        device_id: i64,
+        friendly_name: Option<&str>,
+        manufacturer: Option<&str>,
+        model_name: Option<&str>,
+        fetched_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let fetched_at = fetched_at.to_rfc3339();
+
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS logs (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                timestamp TEXT NOT NULL,
-                level TEXT NOT NULL,
-                category TEXT NOT NULL,
-                message TEXT NOT NULL,
-                details TEXT
-            )
+            UPDATE device_upnp
+            SET friendly_name = ?, manufacturer = ?, model_name = ?, description_fetched_at = ?
+            WHERE device_id = ?
             "#,
         )
+        .bind(friendly_name)
+        .bind(manufacturer)
+        .bind(model_name)
+        .bind(&fetched_at)
+        .bind(device_id)
         .execute(&self.pool)
         .await?;
 
-        sqlx::query(
+        Ok(())
+    }
+
+    pub async fn get_device_upnp(&self, device_id: i64) -> Result<Option<UpnpInfo>> {
+        let row = sqlx::query(
             r#"
-            CREATE INDEX IF NOT EXISTS idx_logs_timestamp ON logs(timestamp DESC);
-            CREATE INDEX IF NOT EXISTS idx_logs_level ON logs(level);
+            SELECT device_id, uuid, server, device_type, location, friendly_name, manufacturer,
+                   model_name, description_fetched_at, first_seen, last_seen
+            FROM device_upnp
+            WHERE device_id = ?
             "#,
         )
-        .execute(&self.pool)
+        .bind(device_id)
+        .fetch_optional(&self.pool)
         .await?;
 
-        sqlx::query(
+        row.map(|row| self.row_to_upnp_info(row)).transpose()
+    }
+
+    /// Returns the UPnP details of every device that announced any, keyed by
+    /// device id.
+    pub async fn get_all_device_upnp(&self) -> Result<HashMap<i64, UpnpInfo>> {
+        let rows = sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS notification_channels (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                channel_type TEXT NOT NULL,
-                config TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
+            SELECT device_id, uuid, server, device_type, location, friendly_name, manufacturer,
+                   model_name, description_fetched_at, first_seen, last_seen
+            FROM device_upnp
             "#,
         )
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
 
+        rows.into_iter()
+            .map(|row| Ok((row.get("device_id"), self.row_to_upnp_info(row)?)))
+            .collect()
+    }
+
+    fn row_to_upnp_info(&self, row: sqlx::sqlite::SqliteRow) -> Result<UpnpInfo> {
+        let fetched_at_str: Option<String> = row.get("description_fetched_at");
+        let first_seen_str: String = row.get("first_seen");
+        let last_seen_str: String = row.get("last_seen");
+
+        Ok(UpnpInfo {
+            uuid: row.get("uuid"),
+            server: row.get("server"),
+            device_type: row.get("device_type"),
+            location: row.get("location"),
+            friendly_name: row.get("friendly_name"),
+            manufacturer: row.get("manufacturer"),
+            model_name: row.get("model_name"),
+            description_fetched_at: fetched_at_str
+                .map(|s| chrono::DateTime::parse_from_rfc3339(&s))
+                .transpose()
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .map(|t| t.with_timezone(&Utc)),
+            first_seen: chrono::DateTime::parse_from_rfc3339(&first_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+            last_seen: chrono::DateTime::parse_from_rfc3339(&last_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+        })
+    }
+
+    /// Stores the lease a device was just granted, replacing its previous one.
+    pub async fn upsert_device_lease(&self, device_id: i64, lease: &DhcpLease) -> Result<()> {
         sqlx::query(
             r#"
-            CREATE INDEX IF NOT EXISTS idx_notification_channels_name ON notification_channels(name);
+            INSERT INTO device_leases (device_id, ip_address, server_id, server_mac, lease_time_secs,
+                                       renewal_time_secs, rebinding_time_secs, acquired_at, renewed_at,
+                                       renews_at, rebinds_at, expires_at, renewals)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(device_id) DO UPDATE SET
+                ip_address = excluded.ip_address,
+                server_id = excluded.server_id,
+                server_mac = excluded.server_mac,
+                lease_time_secs = excluded.lease_time_secs,
+                renewal_time_secs = excluded.renewal_time_secs,
+                rebinding_time_secs = excluded.rebinding_time_secs,
+                acquired_at = excluded.acquired_at,
+                renewed_at = excluded.renewed_at,
+                renews_at = excluded.renews_at,
+                rebinds_at = excluded.rebinds_at,
+                expires_at = excluded.expires_at,
+                renewals = excluded.renewals
             "#,
         )
+        .bind(device_id)
+        .bind(&lease.ip_address)
+        .bind(&lease.server_id)
+        .bind(&lease.server_mac)
+        .bind(lease.lease_time_secs)
+        .bind(lease.renewal_time_secs)
+        .bind(lease.rebinding_time_secs)
+        .bind(lease.acquired_at.to_rfc3339())
+        .bind(lease.renewed_at.to_rfc3339())
+        .bind(lease.renews_at.map(|t| t.to_rfc3339()))
+        .bind(lease.rebinds_at.map(|t| t.to_rfc3339()))
+        .bind(lease.expires_at.map(|t| t.to_rfc3339()))
+        .bind(lease.renewals)
         .execute(&self.pool)
         .await?;
 
-        info!("Database migrations completed");
         Ok(())
     }
 
-    pub async fn upsert_device(&self, device: &Device) -> Result<i64> {
-        let status_str = device.status.to_string();
-        let first_seen = device.first_seen.to_rfc3339();
-        let last_seen = device.last_seen.to_rfc3339();
+    /// Ends a device's lease at `timestamp`, e.g. when it sends a DHCPRELEASE.
+    /// A lease that already ended is left alone.
+    pub async fn end_device_lease(&self, device_id: i64, timestamp: DateTime<Utc>) -> Result<()> {
+        let ended = timestamp.to_rfc3339();
 
-        let result = sqlx::query(
+        sqlx::query(
             r#"
-            INSERT INTO devices (mac_address, ip_address, hostname, nickname, vendor, first_seen, last_seen, status)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(mac_address) DO UPDATE SET
-                ip_address = excluded.ip_address,
-                hostname = excluded.hostname,
-                vendor = excluded.vendor,
-                last_seen = excluded.last_seen,
-                status = excluded.status
-            RETURNING id
+            UPDATE device_leases
+            SET expires_at = ?, renews_at = NULL, rebinds_at = NULL
+            WHERE device_id = ? AND (expires_at IS NULL OR expires_at > ?)
             "#,
         )
-        .bind(&device.mac_address)
-        .bind(&device.ip_address)
-        .bind(&device.hostname)
-        .bind(&device.nickname)
-        .bind(&device.vendor)
-        .bind(&first_seen)
-        .bind(&last_seen)
-        .bind(&status_str)
-        .fetch_one(&self.pool)
+        .bind(&ended)
+        .bind(device_id)
+        .bind(&ended)
+        .execute(&self.pool)
         .await?;
 
-        Ok(result.get(0))
+        Ok(())
     }
 
-    pub async fn get_device_by_mac(&self, mac: &str) -> Result<Option<Device>> {
+    pub async fn get_device_lease(&self, device_id: i64) -> Result<Option<DhcpLease>> {
         let row = sqlx::query(
             r#"
-            SELECT id, mac_address, ip_address, hostname, nickname, vendor, first_seen, last_seen, status
-            FROM devices
-            WHERE mac_address = ?
+            SELECT l.device_id, d.mac_address, l.ip_address, l.server_id, l.server_mac,
+                   l.lease_time_secs, l.renewal_time_secs, l.rebinding_time_secs, l.acquired_at,
+                   l.renewed_at, l.renews_at, l.rebinds_at, l.expires_at, l.renewals
+            FROM device_leases l
+            JOIN devices d ON d.id = l.device_id
+            WHERE l.device_id = ?
             "#,
         )
-        .bind(mac)
+        .bind(device_id)
         .fetch_optional(&self.pool)
         .await?;
 
-        match row {
-            Some(row) => Ok(Some(self.row_to_device(row)?)),
-            None => Ok(None),
-        }
+        row.map(|row| self.row_to_lease(row)).transpose()
     }
 
-    pub async fn get_all_devices(&self) -> Result<Vec<Device>> {
+    /// Returns the lease of every device that was granted one, keyed by
+    /// device id.
+    pub async fn get_all_device_leases(&self) -> Result<HashMap<i64, DhcpLease>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, mac_address, ip_address, hostname, nickname, vendor, first_seen, last_seen, status
-            FROM devices
-            ORDER BY last_seen DESC
+            SELECT l.device_id, d.mac_address, l.ip_address, l.server_id, l.server_mac,
+                   l.lease_time_secs, l.renewal_time_secs, l.rebinding_time_secs, l.acquired_at,
+                   l.renewed_at, l.renews_at, l.rebinds_at, l.expires_at, l.renewals
+            FROM device_leases l
+            JOIN devices d ON d.id = l.device_id
             "#,
         )
         .fetch_all(&self.pool)
         .await?;
 
         rows.into_iter()
-            .map(|row| self.row_to_device(row))
+            .map(|row| Ok((row.get("device_id"), self.row_to_lease(row)?)))
             .collect()
     }
 
-    pub async fn get_devices_by_status(&self, status: DeviceStatus) -> Result<Vec<Device>> {
-        let status_str = status.to_string();
+    fn row_to_lease(&self, row: sqlx::sqlite::SqliteRow) -> Result<DhcpLease> {
+        let parse = |s: String| {
+            chrono::DateTime::parse_from_rfc3339(&s)
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))
+        };
+        let acquired_at_str: String = row.get("acquired_at");
+        let renewed_at_str: String = row.get("renewed_at");
+        let renews_at_str: Option<String> = row.get("renews_at");
+        let rebinds_at_str: Option<String> = row.get("rebinds_at");
+        let expires_at_str: Option<String> = row.get("expires_at");
+
+        Ok(DhcpLease {
+            mac_address: row.get("mac_address"),
+            ip_address: row.get("ip_address"),
+            server_id: row.get("server_id"),
+            server_mac: row.get("server_mac"),
+            lease_time_secs: row.get("lease_time_secs"),
+            renewal_time_secs: row.get("renewal_time_secs"),
+            rebinding_time_secs: row.get("rebinding_time_secs"),
+            acquired_at: parse(acquired_at_str)?,
+            renewed_at: parse(renewed_at_str)?,
+            renews_at: renews_at_str.map(parse).transpose()?,
+            rebinds_at: rebinds_at_str.map(parse).transpose()?,
+            expires_at: expires_at_str.map(parse).transpose()?,
+            renewals: row.get("renewals"),
+        })
+    }
+
+    /// Stores the latest LLDP or CDP announcement from a device, replacing the
+    /// previous one.
+    pub async fn upsert_device_link_discovery(
+        &self,
+        device_id: i64,
+        discovery: &LinkDiscovery,
+    ) -> Result<()> {
+        let announcement = &discovery.announcement;
+        let capabilities_json = serde_json::to_string(&announcement.capabilities)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO device_link_discovery (device_id, protocol, chassis_id, system_name,
+                system_description, platform, capabilities, management_address, port_id,
+                port_description, first_seen, last_seen)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(device_id) DO UPDATE SET
+                protocol = excluded.protocol,
+                chassis_id = excluded.chassis_id,
+                system_name = excluded.system_name,
+                system_description = excluded.system_description,
+                platform = excluded.platform,
+                capabilities = excluded.capabilities,
+                management_address = excluded.management_address,
+                port_id = excluded.port_id,
+                port_description = excluded.port_description,
+                last_seen = excluded.last_seen
+            "#,
+        )
+        .bind(device_id)
+        .bind(announcement.protocol.to_string())
+        .bind(&announcement.chassis_id)
+        .bind(&announcement.system_name)
+        .bind(&announcement.system_description)
+        .bind(&announcement.platform)
+        .bind(&capabilities_json)
+        .bind(&announcement.management_address)
+        .bind(&announcement.port_id)
+        .bind(&announcement.port_description)
+        .bind(discovery.first_seen.to_rfc3339())
+        .bind(discovery.last_seen.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_device_link_discovery(&self, device_id: i64) -> Result<Option<LinkDiscovery>> {
+        let row = sqlx::query(
+            r#"
+            SELECT device_id, protocol, chassis_id, system_name, system_description, platform,
+                   capabilities, management_address, port_id, port_description, first_seen,
+                   last_seen
+            FROM device_link_discovery
+            WHERE device_id = ?
+            "#,
+        )
+        .bind(device_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|row| self.row_to_link_discovery(row)).transpose()
+    }
+
+    /// Returns the LLDP/CDP details of every device that announced any, keyed
+    /// by device id.
+    pub async fn get_all_device_link_discovery(&self) -> Result<HashMap<i64, LinkDiscovery>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, mac_address, ip_address, hostname, nickname, vendor, first_seen, last_seen, status
-            FROM devices
-            WHERE status = ?
-            ORDER BY last_seen DESC
+            SELECT device_id, protocol, chassis_id, system_name, system_description, platform,
+                   capabilities, management_address, port_id, port_description, first_seen,
+                   last_seen
+            FROM device_link_discovery
             "#,
         )
-        .bind(&status_str)
         .fetch_all(&self.pool)
         .await?;
 
         rows.into_iter()
-            .map(|row| self.row_to_device(row))
+            .map(|row| Ok((row.get("device_id"), self.row_to_link_discovery(row)?)))
             .collect()
     }
 
-    pub async fn update_device_status(&self, mac: &str, status: DeviceStatus) -> Result<()> {
-        let status_str = status.to_string();
-        let last_seen = Utc::now().to_rfc3339();
+    fn row_to_link_discovery(&self, row: sqlx::sqlite::SqliteRow) -> Result<LinkDiscovery> {
+        let protocol_str: String = row.get("protocol");
+        let capabilities_json: String = row.get("capabilities");
+        let first_seen_str: String = row.get("first_seen");
+        let last_seen_str: String = row.get("last_seen");
 
-        sqlx::query(
+        Ok(LinkDiscovery {
+            announcement: LinkAnnouncement {
+                protocol: match protocol_str.as_str() {
+                    "cdp" => DiscoveryProtocol::Cdp,
+                    _ => DiscoveryProtocol::Lldp,
+                },
+                chassis_id: row.get("chassis_id"),
+                system_name: row.get("system_name"),
+                system_description: row.get("system_description"),
+                platform: row.get("platform"),
+                capabilities: serde_json::from_str(&capabilities_json)?,
+                management_address: row.get("management_address"),
+                port_id: row.get("port_id"),
+                port_description: row.get("port_description"),
+            },
+            first_seen: chrono::DateTime::parse_from_rfc3339(&first_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+            last_seen: chrono::DateTime::parse_from_rfc3339(&last_seen_str)
+                .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                .with_timezone(&Utc),
+        })
+    }
+
+    /// Records a conflict between two MACs over `ip`, in either order, and
+    /// returns when it was last seen before, if it was.
+    pub async fn upsert_ip_conflict(
+        &self,
+        ip: &str,
+        mac: &str,
+        conflicting_mac: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let seen = timestamp.to_rfc3339();
+
+        let existing = sqlx::query(
             r#"
-            UPDATE devices
-            SET status = ?, last_seen = ?
-            WHERE mac_address = ?
+            SELECT id, last_seen
+            FROM ip_conflicts
+            WHERE ip_address = ?
+              AND ((mac_address = ? AND conflicting_mac_address = ?)
+                OR (mac_address = ? AND conflicting_mac_address = ?))
             "#,
         )
-        .bind(&status_str)
-        .bind(&last_seen)
+        .bind(ip)
         .bind(mac)
-        .execute(&self.pool)
+        .bind(conflicting_mac)
+        .bind(conflicting_mac)
+        .bind(mac)
+        .fetch_optional(&self.pool)
         .await?;
 
-        Ok(())
+        match existing {
+            Some(row) => {
+                let id: i64 = row.get("id");
+                let last_seen_str: String = row.get("last_seen");
+                let last_seen = chrono::DateTime::parse_from_rfc3339(&last_seen_str)
+                    .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                    .with_timezone(&Utc);
+
+                sqlx::query("UPDATE ip_conflicts SET last_seen = ? WHERE id = ?")
+                    .bind(&seen)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+
+                Ok(Some(last_seen))
+            }
+            None => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO ip_conflicts (ip_address, mac_address, conflicting_mac_address, first_seen, last_seen)
+                    VALUES (?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(ip)
+                .bind(mac)
+                .bind(conflicting_mac)
+                .bind(&seen)
+                .bind(&seen)
+                .execute(&self.pool)
+                .await?;
+
+                Ok(None)
+            }
+        }
     }
 
-    pub async fn update_device_nickname(&self, mac: &str, nickname: Option<String>) -> Result<()> {
-        sqlx::query(
+    /// Returns the conflicts seen since `since`, most recent first.
+    pub async fn get_ip_conflicts_since(&self, since: DateTime<Utc>) -> Result<Vec<IpConflict>> {
+        let rows = sqlx::query(
             r#"
-            UPDATE devices
-            SET nickname = ?
-            WHERE mac_address = ?
+            SELECT id, ip_address, mac_address, conflicting_mac_address, first_seen, last_seen
+            FROM ip_conflicts
+            WHERE last_seen >= ?
+            ORDER BY last_seen DESC
             "#,
         )
-        .bind(&nickname)
-        .bind(mac)
-        .execute(&self.pool)
+        .bind(since.to_rfc3339())
+        .fetch_all(&self.pool)
         .await?;
 
-        Ok(())
+        rows.into_iter()
+            .map(|row| self.row_to_ip_conflict(row))
+            .collect()
     }
 
-    fn row_to_device(&self, row: sqlx::sqlite::SqliteRow) -> Result<Device> {
-        let status_str: String = row.get("status");
-        let status = match status_str.as_str() {
-            "online" => DeviceStatus::Online,
-            "offline" => DeviceStatus::Offline,
-            _ => DeviceStatus::Unknown,
-        };
-
+    fn row_to_ip_conflict(&self, row: sqlx::sqlite::SqliteRow) -> Result<IpConflict> {
         let first_seen_str: String = row.get("first_seen");
         let last_seen_str: String = row.get("last_seen");
 
-        Ok(Device {
+        Ok(IpConflict {
             id: Some(row.get("id")),
-            mac_address: row.get("mac_address"),
             ip_address: row.get("ip_address"),
-            hostname: row.get("hostname"),
-            nickname: row.get("nickname"),
-            vendor: row.get("vendor"),
+            mac_address: row.get("mac_address"),
+            conflicting_mac_address: row.get("conflicting_mac_address"),
             first_seen: chrono::DateTime::parse_from_rfc3339(&first_seen_str)
                 .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
                 .with_timezone(&Utc),
             last_seen: chrono::DateTime::parse_from_rfc3339(&last_seen_str)
                 .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
                 .with_timezone(&Utc),
-            status,
         })
     }
 
+    /// Records a DHCP fingerprint the classifier did not recognise, along
+    /// with the device that sent it.
+    pub async fn record_unknown_fingerprint(
+        &self,
+        fingerprint: &str,
+        vendor_class: Option<&str>,
+        device: &Device,
+        timestamp: DateTime<Utc>,
+    ) -> Result<()> {
+        let seen = timestamp.to_rfc3339();
+
+        sqlx::query(
+            r#"
+            INSERT INTO unknown_fingerprints (fingerprint, vendor_class, mac_address, hostname, vendor, first_seen, last_seen)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(fingerprint, vendor_class) DO UPDATE SET
+                mac_address = excluded.mac_address,
+                hostname = excluded.hostname,
+                vendor = excluded.vendor,
+                times_seen = times_seen + 1,
+                last_seen = excluded.last_seen
+            "#,
+        )
+        .bind(fingerprint)
+        .bind(vendor_class.unwrap_or(""))
+        .bind(&device.mac_address)
+        .bind(&device.hostname)
+        .bind(&device.vendor)
+        .bind(&seen)
+        .bind(&seen)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_unknown_fingerprints(&self) -> Result<Vec<UnknownFingerprint>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT fingerprint, vendor_class, mac_address, hostname, vendor, times_seen, first_seen, last_seen
+            FROM unknown_fingerprints
+            ORDER BY times_seen DESC, last_seen DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let vendor_class: String = row.get("vendor_class");
+                let first_seen_str: String = row.get("first_seen");
+                let last_seen_str: String = row.get("last_seen");
+
+                Ok(UnknownFingerprint {
+                    fingerprint: row.get("fingerprint"),
+                    vendor_class: (!vendor_class.is_empty()).then_some(vendor_class),
+                    mac_address: row.get("mac_address"),
+                    hostname: row.get("hostname"),
+                    vendor: row.get("vendor"),
+                    times_seen: row.get("times_seen"),
+                    first_seen: chrono::DateTime::parse_from_rfc3339(&first_seen_str)
+                        .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                        .with_timezone(&Utc),
+                    last_seen: chrono::DateTime::parse_from_rfc3339(&last_seen_str)
+                        .map_err(|e| DaemonError::Database(sqlx::Error::Decode(Box::new(e))))?
+                        .with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
     pub async fn create_rule(&self, rule: &Rule) -> Result<i64> {
         let trigger_type_str = rule.trigger_type.to_string();
         let channels_json = serde_json::to_string(&rule.notification_channels)?;
@@ -294,8 +1743,8 @@ impl Database {
 
         let result = sqlx::query(
             r#"
-            INSERT INTO rules (name, description, trigger_type, mac_filter, enabled, notification_channels, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO rules (name, description, trigger_type, mac_filter, segment_filter, vendor_filter, os_family_filter, device_type_filter, tcp_os_filter, enabled, notification_channels, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             RETURNING id
             "#,
         )
@@ -303,6 +1752,11 @@ impl Database {
         .bind(&rule.description)
         .bind(&trigger_type_str)
         .bind(&rule.mac_filter)
+        .bind(&rule.segment_filter)
+        .bind(&rule.vendor_filter)
+        .bind(&rule.os_family_filter)
+        .bind(&rule.device_type_filter)
+        .bind(&rule.tcp_os_filter)
         .bind(rule.enabled)
         .bind(&channels_json)
         .bind(&created_at)
@@ -316,7 +1770,7 @@ impl Database {
     pub async fn get_rule_by_id(&self, id: i64) -> Result<Option<Rule>> {
         let row = sqlx::query(
             r#"
-            SELECT id, name, description, trigger_type, mac_filter, enabled, notification_channels, created_at, updated_at
+            SELECT id, name, description, trigger_type, mac_filter, segment_filter, vendor_filter, os_family_filter, device_type_filter, tcp_os_filter, enabled, notification_channels, created_at, updated_at
             FROM rules
             WHERE id = ?
             "#,
@@ -334,7 +1788,7 @@ impl Database {
     pub async fn get_all_rules(&self) -> Result<Vec<Rule>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, name, description, trigger_type, mac_filter, enabled, notification_channels, created_at, updated_at
+            SELECT id, name, description, trigger_type, mac_filter, segment_filter, vendor_filter, os_family_filter, device_type_filter, tcp_os_filter, enabled, notification_channels, created_at, updated_at
             FROM rules
             ORDER BY created_at DESC
             "#,
@@ -348,7 +1802,7 @@ impl Database {
     pub async fn get_enabled_rules(&self) -> Result<Vec<Rule>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, name, description, trigger_type, mac_filter, enabled, notification_channels, created_at, updated_at
+            SELECT id, name, description, trigger_type, mac_filter, segment_filter, vendor_filter, os_family_filter, device_type_filter, tcp_os_filter, enabled, notification_channels, created_at, updated_at
             FROM rules
             WHERE enabled = 1
             ORDER BY created_at DESC
@@ -368,7 +1822,7 @@ impl Database {
         sqlx::query(
             r#"
             UPDATE rules
-            SET name = ?, description = ?, trigger_type = ?, mac_filter = ?, enabled = ?, notification_channels = ?, updated_at = ?
+            SET name = ?, description = ?, trigger_type = ?, mac_filter = ?, segment_filter = ?, vendor_filter = ?, os_family_filter = ?, device_type_filter = ?, tcp_os_filter = ?, enabled = ?, notification_channels = ?, updated_at = ?
             WHERE id = ?
             "#,
         )
@@ -376,6 +1830,11 @@ impl Database {
         .bind(&rule.description)
         .bind(&trigger_type_str)
         .bind(&rule.mac_filter)
+        .bind(&rule.segment_filter)
+        .bind(&rule.vendor_filter)
+        .bind(&rule.os_family_filter)
+        .bind(&rule.device_type_filter)
+        .bind(&rule.tcp_os_filter)
         .bind(rule.enabled)
         .bind(&channels_json)
         .bind(&updated_at)
@@ -402,6 +1861,11 @@ impl Database {
             "device_disconnected" => TriggerType::DeviceDisconnected,
             "new_device" => TriggerType::NewDevice,
             "device_status_change" => TriggerType::DeviceStatusChange,
+            "arp_spoofing" => TriggerType::ArpSpoofing,
+            "gratuitous_arp_flood" => TriggerType::GratuitousArpFlood,
+            "multiple_ip_claims" => TriggerType::MultipleIpClaims,
+            "ip_conflict" => TriggerType::IpConflict,
+            "rogue_dhcp_server" => TriggerType::RogueDhcpServer,
             _ => {
                 return Err(DaemonError::Database(sqlx::Error::Decode(Box::new(
                     std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid trigger type"),
@@ -421,6 +1885,11 @@ impl Database {
             description: row.get("description"),
             trigger_type,
             mac_filter: row.get("mac_filter"),
+            segment_filter: row.get("segment_filter"),
+            vendor_filter: row.get("vendor_filter"),
+            os_family_filter: row.get("os_family_filter"),
+            device_type_filter: row.get("device_type_filter"),
+            tcp_os_filter: row.get("tcp_os_filter"),
             enabled: row.get("enabled"),
             notification_channels,
             created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)
@@ -700,4 +2169,82 @@ impl Database {
 
         Ok(())
     }
+
+    /// Remembers a DHCP server trusted because it was the first one seen.
+    pub async fn save_learned_dhcp_server(&self, mac: &str, ip: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO dhcp_servers (mac_address, ip_address, learned_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT(mac_address) DO UPDATE SET ip_address = excluded.ip_address
+            "#,
+        )
+        .bind(mac)
+        .bind(ip)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns the learned DHCP servers as (MAC, IP) pairs.
+    pub async fn get_learned_dhcp_servers(&self) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query("SELECT mac_address, ip_address FROM dhcp_servers")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("mac_address"), row.get("ip_address")))
+            .collect())
+    }
+
+    /// Stores an encoded OUI registry, replacing any previous import.
+    pub async fn save_oui_registry(&self, data: &[u8], entries: i64) -> Result<()> {
+        let imported_at = Utc::now().to_rfc3339();
+
+        sqlx::query(
+            r#"
+            INSERT INTO oui_registry (id, data, entries, imported_at)
+            VALUES (1, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                data = excluded.data,
+                entries = excluded.entries,
+                imported_at = excluded.imported_at
+            "#,
+        )
+        .bind(data)
+        .bind(entries)
+        .bind(&imported_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_oui_registry(&self) -> Result<Option<Vec<u8>>> {
+        let row = sqlx::query("SELECT data FROM oui_registry WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| row.get("data")))
+    }
+}
+
+/// Stores a probe list as comma-separated names, e.g. `arp,tcp:62078`.
+fn encode_presence_probes(probes: &[PresenceProbe]) -> String {
+    probes
+        .iter()
+        .map(|probe| probe.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn decode_presence_probes(probes: &str) -> Vec<PresenceProbe> {
+    probes
+        .split(',')
+        .filter(|probe| !probe.is_empty())
+        .filter_map(|probe| probe.parse().ok())
+        .collect()
 }
@@ -33,6 +33,9 @@ pub enum DaemonError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -43,6 +46,7 @@ impl From<config::ConfigError> for DaemonError {
     }
 }
 
+#[cfg(feature = "libpcap")]
 impl From<pcap::Error> for DaemonError {
     fn from(err: pcap::Error) -> Self {
         DaemonError::PacketCapture(err.to_string())
@@ -67,6 +71,7 @@ impl IntoResponse for DaemonError {
             DaemonError::Io(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             DaemonError::Serialization(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             DaemonError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            DaemonError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone()),
             DaemonError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
         };
 
@@ -0,0 +1,242 @@
+use crate::signatures::SignatureDatabase;
+
+/// What a device's DHCP fingerprint says about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classification {
+    pub os_family: String,
+    pub device_type: String,
+    /// How closely the fingerprint matched, from 0 to 100.
+    pub confidence: u8,
+}
+
+#[derive(Debug, Clone)]
+struct Fingerprint {
+    /// Parameter request list (option 55), in the client's order.
+    options: Option<Vec<u8>>,
+    /// Lowercased prefix of the vendor class identifier (option 60).
+    vendor_class: Option<String>,
+    os_family: String,
+    device_type: String,
+}
+
+/// Known DHCP client fingerprints: the parameter request list, whose content
+/// and order differ between DHCP client implementations, and the vendor
+/// class identifier.
+#[derive(Debug, Default)]
+pub struct DhcpFingerprints {
+    fingerprints: Vec<Fingerprint>,
+}
+
+/// Fingerprints in the format of `data/dhcp_fingerprints.txt`. Ones read
+/// later win ties with those read earlier.
+impl SignatureDatabase for DhcpFingerprints {
+    const KIND: &'static str = "DHCP fingerprint";
+    const EMBEDDED: &'static str = include_str!("../data/dhcp_fingerprints.txt");
+
+    type State = ();
+
+    fn add_line(&mut self, line: &str, _: &mut ()) -> bool {
+        parse_line(line)
+            .map(|fingerprint| self.fingerprints.push(fingerprint))
+            .is_some()
+    }
+
+    fn len(&self) -> usize {
+        self.fingerprints.len()
+    }
+}
+
+impl DhcpFingerprints {
+    /// Classifies a client by its parameter request list and vendor class.
+    /// An identical request list is the strongest evidence; the same options
+    /// in another order point to a related client; a matching vendor class
+    /// adds to either and is enough on its own. A vendor class that differs
+    /// from the one a fingerprint expects rules that fingerprint out.
+    pub fn classify(
+        &self,
+        options: Option<&[u8]>,
+        vendor_class: Option<&str>,
+    ) -> Option<Classification> {
+        let vendor_class = vendor_class.map(|class| class.to_lowercase());
+
+        self.fingerprints
+            .iter()
+            .filter_map(|fingerprint| {
+                let options_match = match (&fingerprint.options, options) {
+                    (Some(expected), Some(options)) if expected == options => OptionsMatch::Exact,
+                    (Some(expected), Some(options)) if same_options(expected, options) => {
+                        OptionsMatch::Reordered
+                    }
+                    (Some(_), Some(_)) => return None,
+                    _ => OptionsMatch::Unknown,
+                };
+                let vendor_match = match (&fingerprint.vendor_class, &vendor_class) {
+                    (Some(prefix), Some(class)) if class.starts_with(prefix.as_str()) => true,
+                    (Some(_), Some(_)) => return None,
+                    _ => false,
+                };
+
+                let confidence = match (options_match, vendor_match) {
+                    (OptionsMatch::Exact, true) => 95,
+                    (OptionsMatch::Exact, false) => 85,
+                    (OptionsMatch::Reordered, true) => 75,
+                    (OptionsMatch::Unknown, true) => 70,
+                    (OptionsMatch::Reordered, false) => 50,
+                    (OptionsMatch::Unknown, false) => return None,
+                };
+                // The longest vendor class prefix is the most specific.
+                let specificity = fingerprint.vendor_class.as_ref().map_or(0, String::len);
+
+                Some(((confidence, specificity), fingerprint))
+            })
+            .max_by_key(|(rank, _)| *rank)
+            .map(|((confidence, _), fingerprint)| Classification {
+                os_family: fingerprint.os_family.clone(),
+                device_type: fingerprint.device_type.clone(),
+                confidence,
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum OptionsMatch {
+    Exact,
+    Reordered,
+    /// The fingerprint or the client has no parameter request list.
+    Unknown,
+}
+
+fn same_options(a: &[u8], b: &[u8]) -> bool {
+    let mut a = a.to_vec();
+    let mut b = b.to_vec();
+    a.sort_unstable();
+    b.sort_unstable();
+    a == b
+}
+
+fn parse_line(line: &str) -> Option<Fingerprint> {
+    let fields: Vec<&str> = line.split('|').collect();
+    let [options, vendor_class, os_family, device_type] = fields[..] else {
+        return None;
+    };
+
+    let options = match options.trim() {
+        "" => None,
+        options => Some(
+            options
+                .split(',')
+                .map(|code| code.trim().parse().ok())
+                .collect::<Option<Vec<u8>>>()?,
+        ),
+    };
+    // Prefixes may end in a space, as in "udhcp ".
+    let vendor_class = (!vendor_class.is_empty()).then(|| vendor_class.to_lowercase());
+    if options.is_none() && vendor_class.is_none() {
+        return None;
+    }
+
+    let os_family = os_family.trim();
+    let device_type = device_type.trim();
+    if os_family.is_empty() || device_type.is_empty() {
+        return None;
+    }
+
+    Some(Fingerprint {
+        options,
+        vendor_class,
+        os_family: os_family.to_string(),
+        device_type: device_type.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOWS_10: [u8; 14] = [1, 3, 6, 15, 31, 33, 43, 44, 46, 47, 119, 121, 249, 252];
+
+    fn classify(options: Option<&[u8]>, vendor_class: Option<&str>) -> Option<(String, u8)> {
+        DhcpFingerprints::embedded()
+            .classify(options, vendor_class)
+            .map(|c| (c.os_family, c.confidence))
+    }
+
+    #[test]
+    fn ranks_request_list_and_vendor_class_matches() {
+        assert_eq!(
+            classify(Some(&WINDOWS_10), Some("MSFT 5.0")),
+            Some(("Windows".into(), 95))
+        );
+        assert_eq!(
+            classify(Some(&[1, 121, 3, 6, 15, 119, 252]), None),
+            Some(("iOS".into(), 85))
+        );
+        assert_eq!(
+            classify(Some(&[1, 3, 6, 15, 119, 121, 252]), None),
+            Some(("iOS".into(), 50))
+        );
+        assert_eq!(
+            classify(None, Some("msft 5.0")),
+            Some(("Windows".into(), 70))
+        );
+        assert_eq!(classify(Some(&[1, 3, 200]), None), None);
+        assert_eq!(classify(None, None), None);
+    }
+
+    #[test]
+    fn prefers_the_longest_vendor_class() {
+        let fingerprints = DhcpFingerprints::embedded();
+        let xbox = fingerprints.classify(None, Some("MSFT 5.0 XBOX")).unwrap();
+
+        assert_eq!(xbox.os_family, "Xbox");
+        assert_eq!(xbox.device_type, "game_console");
+    }
+
+    #[test]
+    fn a_different_vendor_class_rules_a_fingerprint_out() {
+        let fingerprints = DhcpFingerprints::parse(
+            "1,3,6,12,15,28,42|udhcp |Linux|iot\n1,3,6,12,15,28,42||Linux|computer\n",
+        );
+
+        let busybox = fingerprints
+            .classify(Some(&[1, 3, 6, 12, 15, 28, 42]), Some("udhcp 1.36.1"))
+            .unwrap();
+        assert_eq!(
+            (busybox.device_type.as_str(), busybox.confidence),
+            ("iot", 95)
+        );
+
+        let other = fingerprints
+            .classify(Some(&[1, 3, 6, 12, 15, 28, 42]), Some("dhcpcd-9.4.1"))
+            .unwrap();
+        assert_eq!(
+            (other.device_type.as_str(), other.confidence),
+            ("computer", 85)
+        );
+    }
+
+    #[test]
+    fn later_fingerprints_win_ties() {
+        let mut fingerprints = DhcpFingerprints::embedded();
+        fingerprints.read("1,3,6,15,31,33,43,44,46,47,119,121,249,252|MSFT 5.0|Windows|server\n");
+
+        let classification = fingerprints
+            .classify(Some(&WINDOWS_10), Some("MSFT 5.0"))
+            .unwrap();
+        assert_eq!(classification.device_type, "server");
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let mut fingerprints = DhcpFingerprints::default();
+        for line in [
+            "1,3,6|Linux|computer",
+            "1,3,600||Linux|computer",
+            "||Linux|computer",
+            "1,3,6|||computer",
+        ] {
+            assert!(!fingerprints.add_line(line, &mut ()), "{}", line);
+        }
+        assert!(fingerprints.is_empty());
+    }
+}
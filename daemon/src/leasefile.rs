@@ -0,0 +1,494 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures::StreamExt;
+use inotify::{EventStream, Inotify, WatchDescriptor, WatchMask};
+use pnet::util::MacAddr;
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// Kea's `valid_lifetime` for leases that never expire.
+const INFINITE_LIFETIME: u32 = u32::MAX;
+/// Kea lease states other than this one (declined, expired-reclaimed) do
+/// not hold an address.
+const KEA_STATE_DEFAULT: &str = "0";
+
+/// One client lease read from a DHCP server's lease file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaseEntry {
+    pub mac: String,
+    pub ip: IpAddr,
+    pub hostname: Option<String>,
+    /// `None` for an infinite lease.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl LeaseEntry {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+/// The lease file formats foxd reads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LeaseFileFormat {
+    /// dnsmasq's `dnsmasq.leases`: `<expiry> <mac> <ip> <hostname> <client-id>`.
+    Dnsmasq,
+    /// ISC dhcpd's `dhcpd.leases`: `lease <ip> { ... }` blocks.
+    IscDhcpd,
+    /// Kea's memfile CSV, e.g. `kea-leases4.csv`.
+    Kea,
+}
+
+impl LeaseFileFormat {
+    /// Tells the format from the contents, or `None` for an empty file.
+    pub fn detect(contents: &str) -> Option<Self> {
+        let first = contents
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))?;
+
+        let fields: Vec<&str> = first.split_whitespace().collect();
+        if first.starts_with("address,") {
+            Some(Self::Kea)
+        } else if fields.first() == Some(&"duid")
+            || (fields.len() >= 4 && fields[0].parse::<i64>().is_ok())
+        {
+            // dnsmasq starts with its DUID when it serves DHCPv6 too.
+            Some(Self::Dnsmasq)
+        } else {
+            Some(Self::IscDhcpd)
+        }
+    }
+
+    /// Reads the current IPv4 leases from a lease file, one per MAC. Files
+    /// that are appended to (dhcpd, Kea) hold several records per address;
+    /// the last one wins.
+    pub fn parse(self, contents: &str) -> Vec<LeaseEntry> {
+        let by_ip = match self {
+            Self::Dnsmasq => parse_dnsmasq(contents),
+            Self::IscDhcpd => parse_isc_dhcpd(contents),
+            Self::Kea => parse_kea(contents),
+        };
+
+        // A client that moved to another address keeps the lease that runs
+        // longest.
+        let mut by_mac: HashMap<String, LeaseEntry> = HashMap::new();
+        for entry in by_ip.into_values().flatten() {
+            match by_mac.get(&entry.mac) {
+                Some(existing)
+                    if existing.expires_at.is_none()
+                        || entry.expires_at.is_some_and(|expires_at| {
+                            existing.expires_at.is_some_and(|e| e >= expires_at)
+                        }) => {}
+                _ => {
+                    by_mac.insert(entry.mac.clone(), entry);
+                }
+            }
+        }
+
+        let mut entries: Vec<LeaseEntry> = by_mac.into_values().collect();
+        entries.sort_by(|a, b| a.mac.cmp(&b.mac));
+        entries
+    }
+}
+
+impl std::fmt::Display for LeaseFileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LeaseFileFormat::Dnsmasq => write!(f, "dnsmasq"),
+            LeaseFileFormat::IscDhcpd => write!(f, "ISC dhcpd"),
+            LeaseFileFormat::Kea => write!(f, "Kea"),
+        }
+    }
+}
+
+/// Leases keyed by address; `None` marks an address whose last record
+/// released it.
+type LeasesByIp = HashMap<IpAddr, Option<LeaseEntry>>;
+
+fn parse_dnsmasq(contents: &str) -> LeasesByIp {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [expiry, mac, ip, hostname, ..] = fields[..] else {
+                return None;
+            };
+            // DHCPv6 leases carry an IAID instead of a MAC.
+            let mac = normalize_mac(mac)?;
+            let ip: IpAddr = ip.parse().ok()?;
+            let expiry: i64 = expiry.parse().ok()?;
+
+            Some((
+                ip,
+                Some(LeaseEntry {
+                    mac,
+                    ip,
+                    hostname: (hostname != "*").then(|| hostname.to_string()),
+                    expires_at: match expiry {
+                        0 => None,
+                        expiry => Some(DateTime::from_timestamp(expiry, 0)?),
+                    },
+                }),
+            ))
+        })
+        .collect()
+}
+
+fn parse_isc_dhcpd(contents: &str) -> LeasesByIp {
+    let mut leases = HashMap::new();
+    let mut current: Option<(IpAddr, IscLease)> = None;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("lease ") {
+            current = rest
+                .trim_end_matches('{')
+                .trim()
+                .parse()
+                .ok()
+                .map(|ip| (ip, IscLease::default()));
+            continue;
+        }
+        let Some((ip, lease)) = &mut current else {
+            continue;
+        };
+
+        if line == "}" {
+            let entry = lease.active.unwrap_or(true).then(|| {
+                Some(LeaseEntry {
+                    mac: lease.mac.clone()?,
+                    ip: *ip,
+                    hostname: lease.hostname.clone(),
+                    expires_at: lease.ends,
+                })
+            });
+            leases.insert(*ip, entry.flatten());
+            current = None;
+            continue;
+        }
+
+        // dhcpd follows some statements with a comment, e.g. the local time.
+        let statement = line.split('#').next().unwrap_or_default();
+        let statement = statement.trim().trim_end_matches(';');
+        if let Some(mac) = statement.strip_prefix("hardware ethernet ") {
+            lease.mac = normalize_mac(mac.trim());
+        } else if let Some(hostname) = statement.strip_prefix("client-hostname ") {
+            lease.hostname = unquote(hostname);
+        } else if let Some(state) = statement.strip_prefix("binding state ") {
+            lease.active = Some(state.trim() == "active");
+        } else if let Some(ends) = statement.strip_prefix("ends ") {
+            lease.ends = parse_isc_time(ends);
+        }
+    }
+
+    leases
+}
+
+#[derive(Default)]
+struct IscLease {
+    mac: Option<String>,
+    hostname: Option<String>,
+    /// Files from dhcpd 2 have no binding state; their leases are active.
+    active: Option<bool>,
+    ends: Option<DateTime<Utc>>,
+}
+
+/// Parses `4 2024/01/01 12:00:00` (UTC), `epoch 1704110400` or `never`.
+fn parse_isc_time(value: &str) -> Option<DateTime<Utc>> {
+    let mut fields = value.split_whitespace();
+    match fields.next()? {
+        "never" => None,
+        "epoch" => DateTime::from_timestamp(fields.next()?.parse().ok()?, 0),
+        _ => {
+            let date = fields.next()?;
+            let time = fields.next()?;
+            NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y/%m/%d %H:%M:%S")
+                .ok()
+                .map(|time| time.and_utc())
+        }
+    }
+}
+
+fn parse_kea(contents: &str) -> LeasesByIp {
+    let mut lines = contents.lines();
+    let Some(header) = lines.next() else {
+        return HashMap::new();
+    };
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+    let column = |name: &str| columns.iter().position(|column| *column == name);
+    let (Some(address), Some(hwaddr), Some(valid_lifetime), Some(expire)) = (
+        column("address"),
+        column("hwaddr"),
+        column("valid_lifetime"),
+        column("expire"),
+    ) else {
+        return HashMap::new();
+    };
+    let hostname = column("hostname");
+    let state = column("state");
+
+    lines
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').collect();
+            let ip: IpAddr = fields.get(address)?.parse().ok()?;
+
+            // Kea records a deleted lease with a zero lifetime.
+            let lifetime: u32 = fields.get(valid_lifetime)?.parse().ok()?;
+            let held = lifetime != 0
+                && state.is_none_or(|state| fields.get(state) == Some(&KEA_STATE_DEFAULT));
+            if !held {
+                return Some((ip, None));
+            }
+
+            let mac = normalize_mac(fields.get(hwaddr)?)?;
+            let expire: i64 = fields.get(expire)?.parse().ok()?;
+            let hostname = hostname
+                .and_then(|column| fields.get(column))
+                // Kea escapes commas in text fields.
+                .map(|name| name.replace("&#x2c", ","))
+                .map(|name| name.trim_end_matches('.').to_string())
+                .filter(|name| !name.is_empty());
+
+            Some((
+                ip,
+                Some(LeaseEntry {
+                    mac,
+                    ip,
+                    hostname,
+                    expires_at: if lifetime == INFINITE_LIFETIME {
+                        None
+                    } else {
+                        DateTime::from_timestamp(expire, 0)
+                    },
+                }),
+            ))
+        })
+        .collect()
+}
+
+/// Lowercase colon-separated form of an Ethernet MAC, or `None` for anything
+/// else.
+fn normalize_mac(mac: &str) -> Option<String> {
+    mac.parse::<MacAddr>().ok().map(|mac| mac.to_string())
+}
+
+fn unquote(value: &str) -> Option<String> {
+    let value = value.trim().trim_matches('"');
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Reports files created, rewritten or moved into watched directories,
+/// through inotify. Lease files are watched by directory because DHCP
+/// servers replace them by renaming a new file over the old one.
+pub struct DirectoryWatcher {
+    events: EventStream<[u8; 4096]>,
+    directories: HashMap<WatchDescriptor, PathBuf>,
+}
+
+impl DirectoryWatcher {
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            events: Inotify::init()?.into_event_stream([0; 4096])?,
+            directories: HashMap::new(),
+        })
+    }
+
+    pub fn watch(&mut self, directory: &Path) -> io::Result<()> {
+        let mask =
+            WatchMask::CLOSE_WRITE | WatchMask::MODIFY | WatchMask::MOVED_TO | WatchMask::CREATE;
+        let wd = self.events.watches().add(directory, mask)?;
+
+        self.directories.insert(wd, directory.to_path_buf());
+        Ok(())
+    }
+
+    /// Waits for the next change and returns the path of the file that
+    /// changed.
+    pub async fn changed(&mut self) -> io::Result<PathBuf> {
+        loop {
+            let event = self.events.next().await.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "inotify stream ended")
+            })??;
+
+            if let (Some(directory), Some(name)) = (self.directories.get(&event.wd), event.name) {
+                return Ok(directory.join(name));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(timestamp: i64) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(timestamp, 0)
+    }
+
+    #[test]
+    fn reads_dnsmasq_leases() {
+        let contents = "\
+duid 00:01:00:01:2c:1f:3a:9b:52:54:00:12:34:56
+1704110400 8c:85:90:1a:2b:3c 192.168.1.23 iPhone 01:8c:85:90:1a:2b:3c
+0 B8:27:EB:00:00:09 192.168.1.9 * *
+1704110400 1234567 2001:db8::23 laptop 00:01:00:01:2c:1f:3a:9b
+";
+        assert_eq!(
+            LeaseFileFormat::detect(contents),
+            Some(LeaseFileFormat::Dnsmasq)
+        );
+
+        assert_eq!(
+            LeaseFileFormat::Dnsmasq.parse(contents),
+            vec![
+                LeaseEntry {
+                    mac: "8c:85:90:1a:2b:3c".into(),
+                    ip: "192.168.1.23".parse().unwrap(),
+                    hostname: Some("iPhone".into()),
+                    expires_at: at(1_704_110_400),
+                },
+                LeaseEntry {
+                    mac: "b8:27:eb:00:00:09".into(),
+                    ip: "192.168.1.9".parse().unwrap(),
+                    hostname: None,
+                    expires_at: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_isc_dhcpd_leases() {
+        let contents = r#"# The format of this file is documented in the dhcpd.leases(5) manual page.
+# This lease file was written by isc-dhcp-4.4.3
+
+authoring-byte-order little-endian;
+
+lease 192.168.1.40 {
+  starts 1 2024/01/01 10:00:00;
+  ends 1 2024/01/01 12:00:00;
+  binding state active;
+  next binding state free;
+  hardware ethernet 00:1b:21:3c:4d:5e;
+  client-hostname "build-box";
+}
+lease 192.168.1.41 {
+  starts 1 2024/01/01 10:00:00;
+  ends epoch 1704110400; # Mon Jan 01 12:00:00 2024
+  binding state active;
+  hardware ethernet 00:1b:21:3c:4d:5f;
+}
+lease 192.168.1.41 {
+  starts 1 2024/01/01 11:00:00;
+  ends 1 2024/01/01 11:00:00;
+  binding state free;
+  hardware ethernet 00:1b:21:3c:4d:5f;
+}
+lease 192.168.1.42 {
+  ends never;
+  hardware ethernet 00:1b:21:3c:4d:60;
+}
+"#;
+        assert_eq!(
+            LeaseFileFormat::detect(contents),
+            Some(LeaseFileFormat::IscDhcpd)
+        );
+
+        assert_eq!(
+            LeaseFileFormat::IscDhcpd.parse(contents),
+            vec![
+                LeaseEntry {
+                    mac: "00:1b:21:3c:4d:5e".into(),
+                    ip: "192.168.1.40".parse().unwrap(),
+                    hostname: Some("build-box".into()),
+                    expires_at: at(1_704_110_400),
+                },
+                LeaseEntry {
+                    mac: "00:1b:21:3c:4d:60".into(),
+                    ip: "192.168.1.42".parse().unwrap(),
+                    hostname: None,
+                    expires_at: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_kea_leases() {
+        let contents = "\
+address,hwaddr,client_id,valid_lifetime,expire,subnet_id,fqdn_fwd,fqdn_rev,hostname,state,user_context
+192.168.1.50,8c:85:90:1a:2b:3c,01:8c:85:90:1a:2b:3c,3600,1704110400,1,0,0,iphone.example.com.,0,
+192.168.1.51,8c:85:90:1a:2b:3d,,3600,1704110400,1,0,0,,0,
+192.168.1.51,8c:85:90:1a:2b:3d,,0,1704107000,1,0,0,,0,
+192.168.1.52,8c:85:90:1a:2b:3e,,3600,1704110400,1,0,0,,1,
+192.168.1.53,8c:85:90:1a:2b:3f,,4294967295,1704110400,1,0,0,a&#x2cb,0,
+";
+        assert_eq!(
+            LeaseFileFormat::detect(contents),
+            Some(LeaseFileFormat::Kea)
+        );
+
+        assert_eq!(
+            LeaseFileFormat::Kea.parse(contents),
+            vec![
+                LeaseEntry {
+                    mac: "8c:85:90:1a:2b:3c".into(),
+                    ip: "192.168.1.50".parse().unwrap(),
+                    hostname: Some("iphone.example.com".into()),
+                    expires_at: at(1_704_110_400),
+                },
+                LeaseEntry {
+                    mac: "8c:85:90:1a:2b:3f".into(),
+                    ip: "192.168.1.53".parse().unwrap(),
+                    hostname: Some("a,b".into()),
+                    expires_at: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_the_longest_lease_of_a_client() {
+        let contents = "\
+1704110400 8c:85:90:1a:2b:3c 192.168.1.23 iPhone *
+1704114000 8c:85:90:1a:2b:3c 192.168.1.24 iPhone *
+";
+        let leases = LeaseFileFormat::Dnsmasq.parse(contents);
+
+        assert_eq!(leases.len(), 1);
+        assert_eq!(leases[0].ip, "192.168.1.24".parse::<IpAddr>().unwrap());
+        assert!(leases[0].is_active(at(1_704_110_400).unwrap()));
+        assert!(!leases[0].is_active(at(1_704_114_000).unwrap()));
+    }
+
+    #[test]
+    fn detects_nothing_in_an_empty_file() {
+        assert_eq!(LeaseFileFormat::detect(""), None);
+        assert_eq!(LeaseFileFormat::detect("# nothing yet\n\n"), None);
+    }
+
+    #[tokio::test]
+    async fn reports_files_renamed_into_a_watched_directory() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut watcher = DirectoryWatcher::new().unwrap();
+        watcher.watch(directory.path()).unwrap();
+
+        let lease_file = directory.path().join("dnsmasq.leases");
+        let temporary = tempfile::NamedTempFile::new_in(directory.path()).unwrap();
+        std::fs::write(temporary.path(), "0 b8:27:eb:00:00:09 192.168.1.9 pi *\n").unwrap();
+
+        // The temporary file itself is reported first.
+        let mut changed = Vec::new();
+        temporary.persist(&lease_file).unwrap();
+        while changed.last() != Some(&lease_file) {
+            let path = tokio::time::timeout(Duration::from_secs(5), watcher.changed())
+                .await
+                .expect("no change reported")
+                .unwrap();
+            changed.push(path);
+        }
+    }
+}
@@ -1,6 +1,17 @@
 pub mod api;
+pub mod arpwatch;
 pub mod daemon;
 pub mod db;
 pub mod errors;
+pub mod fingerprint;
+pub mod leasefile;
 pub mod models;
+pub mod neighbors;
 pub mod notifier;
+pub mod oui;
+pub mod probe;
+pub mod protocols;
+pub mod scanner;
+pub mod signatures;
+pub mod source;
+pub mod tcpfingerprint;
@@ -1,21 +1,17 @@
-mod api;
-mod daemon;
-mod db;
-mod errors;
-mod models;
-mod notifier;
-
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tracing::{error, info};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::api::{create_router, AppState};
-use crate::daemon::Daemon;
-use crate::db::Database;
-use crate::errors::Result;
-use crate::models::Config;
-use crate::notifier::Notifier;
+use foxd_daemon::api::{AppState, create_router};
+use foxd_daemon::daemon::Daemon;
+use foxd_daemon::db::Database;
+use foxd_daemon::errors::{self, Result};
+use foxd_daemon::models::{self, Config};
+use foxd_daemon::notifier::Notifier;
+use foxd_daemon::oui::OuiRegistry;
+use foxd_daemon::source::SourceKind;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -29,15 +25,55 @@ async fn main() -> Result<()> {
 
     info!("foxd starting...");
 
-    let config = load_config()?;
+    let command = parse_args()?;
+
+    if let Command::OuiRefresh(refresh) = &command
+        && let Some(output) = &refresh.output
+    {
+        let registry = refresh.registry()?;
+        std::fs::write(output, registry.encode())?;
+        info!(
+            "Wrote {} OUI assignments to {}",
+            registry.len(),
+            output.display()
+        );
+        return Ok(());
+    }
+
+    let mut config = load_config()?;
     info!("Configuration loaded from config.toml");
 
-    let db_url = if config.database.path.starts_with("sqlite://") {
-        config.database.path.clone()
-    } else {
-        format!("sqlite://{}", config.database.path)
-    };
-    let db = Database::new(&db_url).await?;
+    if let Some(source) = &config.daemon.source {
+        Daemon::validate_source(source)?;
+    }
+    if let Some(filter) = &config.daemon.capture_filter {
+        Daemon::validate_capture_filter(filter, config.daemon.source.as_deref())?;
+    }
+
+    // A replay must not touch the live database or send real notifications,
+    // so it gets a database of its own and no notification channels.
+    if let Command::Replay(replay) = command {
+        let db = match &replay.db {
+            Some(path) => Database::new(&database_url(&path.to_string_lossy())).await?,
+            None => Database::in_memory().await?,
+        };
+        let oui = OuiRegistry::load(&db).await?;
+
+        config.daemon.replay_packet_time |= replay.packet_time;
+        let mut daemon = Daemon::new(db.clone(), Notifier::new(Vec::new()), &config.daemon);
+        daemon.set_oui_registry(oui);
+        let mut source = SourceKind::File(replay.pcap.clone()).open("")?;
+        let count = daemon.replay(source.as_mut()).await?;
+        info!(
+            "Replayed {} packets from {}: {} devices",
+            count,
+            replay.pcap.display(),
+            db.get_total_device_count().await?
+        );
+        return Ok(());
+    }
+
+    let db = Database::new(&database_url(&config.database.path)).await?;
     info!("Database initialized");
 
     let notification_channels = db.get_all_notification_channels_raw().await?;
@@ -47,15 +83,23 @@ async fn main() -> Result<()> {
         notification_channels.len()
     );
 
-    let daemon = Arc::new(Daemon::new(
-        db.clone(),
-        notifier,
-        config.daemon.interface.clone(),
-        config.daemon.device_timeout_secs,
-        config.daemon.neighbor_check_interval_secs,
-        config.daemon.log_cleanup_enabled,
-        config.daemon.log_retention_days,
-    ));
+    if let Command::OuiRefresh(refresh) = &command {
+        let registry = refresh.registry()?;
+        db.save_oui_registry(&registry.encode(), registry.len() as i64)
+            .await?;
+        info!(
+            "Imported {} OUI assignments; restart foxd to use them",
+            registry.len()
+        );
+        return Ok(());
+    }
+
+    let oui = OuiRegistry::load(&db).await?;
+    info!("OUI registry loaded with {} assignments", oui.len());
+
+    let mut daemon = Daemon::new(db.clone(), notifier, &config.daemon);
+    daemon.set_oui_registry(oui);
+    let daemon = Arc::new(daemon);
 
     let api_state = AppState::new(db, config.clone(), Some(Arc::clone(&daemon)));
 
@@ -123,6 +167,120 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+enum Command {
+    Run,
+    Replay(ReplayArgs),
+    OuiRefresh(OuiRefreshArgs),
+}
+
+/// Arguments of `foxd replay --pcap <file> [--packet-time] [--db <file>]`.
+struct ReplayArgs {
+    pcap: PathBuf,
+    packet_time: bool,
+    /// Database to record the replay in; an in-memory one if unset.
+    db: Option<PathBuf>,
+}
+
+/// Arguments of `foxd oui-refresh --csv <file>... [--output <file>]`.
+struct OuiRefreshArgs {
+    csv: Vec<PathBuf>,
+    /// Write the encoded registry to this file instead of the database.
+    output: Option<PathBuf>,
+}
+
+impl OuiRefreshArgs {
+    fn registry(&self) -> Result<OuiRegistry> {
+        let registry = OuiRegistry::from_csv_files(&self.csv)?;
+        if registry.is_empty() {
+            return Err(errors::DaemonError::Config(
+                "No OUI assignments found in the given CSV files".to_string(),
+            ));
+        }
+        Ok(registry)
+    }
+}
+
+const USAGE: &str = "usage: foxd [replay --pcap <file> [--packet-time] [--db <file>]] \
+                     [oui-refresh --csv <file>... [--output <file>]]";
+
+fn parse_args() -> Result<Command> {
+    let mut args = std::env::args().skip(1);
+
+    match args.next().as_deref() {
+        None => Ok(Command::Run),
+        Some("replay") => parse_replay_args(args),
+        Some("oui-refresh") => parse_oui_refresh_args(args),
+        Some(other) => Err(errors::DaemonError::Config(format!(
+            "Unknown command: {} ({})",
+            other, USAGE
+        ))),
+    }
+}
+
+fn parse_replay_args(mut args: impl Iterator<Item = String>) -> Result<Command> {
+    let mut pcap = None;
+    let mut packet_time = false;
+    let mut db = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pcap" => pcap = args.next().map(PathBuf::from),
+            "--packet-time" => packet_time = true,
+            "--db" => db = args.next().map(PathBuf::from),
+            _ => {
+                return Err(errors::DaemonError::Config(format!(
+                    "Unknown replay argument: {}",
+                    arg
+                )));
+            }
+        }
+    }
+
+    let pcap = pcap.ok_or_else(|| {
+        errors::DaemonError::Config(
+            "Usage: foxd replay --pcap <file> [--packet-time] [--db <file>]".to_string(),
+        )
+    })?;
+
+    Ok(Command::Replay(ReplayArgs {
+        pcap,
+        packet_time,
+        db,
+    }))
+}
+
+fn database_url(path: &str) -> String {
+    if path.starts_with("sqlite://") {
+        path.to_string()
+    } else {
+        format!("sqlite://{}", path)
+    }
+}
+
+fn parse_oui_refresh_args(mut args: impl Iterator<Item = String>) -> Result<Command> {
+    let mut csv = Vec::new();
+    let mut output = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--csv" => csv.extend(args.next().map(PathBuf::from)),
+            "--output" => output = args.next().map(PathBuf::from),
+            _ => {
+                return Err(errors::DaemonError::Config(format!(
+                    "Unknown oui-refresh argument: {}",
+                    arg
+                )));
+            }
+        }
+    }
+
+    if csv.is_empty() {
+        return Err(errors::DaemonError::Config(
+            "Usage: foxd oui-refresh --csv <file>... [--output <file>]".to_string(),
+        ));
+    }
+
+    Ok(Command::OuiRefresh(OuiRefreshArgs { csv, output }))
+}
+
 fn load_config() -> Result<Config> {
     let config_path = std::env::var("FOXD_CONFIG").unwrap_or_else(|_| "config.toml".to_string());
 
@@ -141,11 +299,29 @@ fn default_config() -> Config {
     Config {
         daemon: models::DaemonConfig {
             interface: std::env::var("FOXD_INTERFACE").unwrap_or_else(|_| "wlan0".to_string()),
+            interfaces: Vec::new(),
             capture_filter: None,
             neighbor_check_interval_secs: 30,
             device_timeout_secs: 60,
             log_cleanup_enabled: true,
             log_retention_days: 30,
+            trusted_ipv6_routers: Vec::new(),
+            trusted_dhcp_servers: Vec::new(),
+            trusted_dhcp_relays: Vec::new(),
+            source: None,
+            replay_packet_time: false,
+            ssdp_fetch_descriptions: false,
+            netbios_query_unnamed: false,
+            arp_scan_interval_secs: 0,
+            arp_scan_rate: 100,
+            presence_probes: models::default_presence_probes(),
+            arp_watch_ips: Vec::new(),
+            gratuitous_arp_threshold: 10,
+            arp_max_ips_per_mac: 8,
+            lease_files: Vec::new(),
+            tcp_fingerprinting: false,
+            dhcp_fingerprints_file: None,
+            tcp_signatures_file: None,
         },
         database: models::DatabaseConfig {
             path: std::env::var("FOXD_DB_PATH").unwrap_or_else(|_| "./foxd.db".to_string()),
@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, sqlx::Type)]
 #[sqlx(type_name = "TEXT")]
@@ -21,10 +22,370 @@ impl std::fmt::Display for DeviceStatus {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    Ipv4,
+    Ipv6,
+}
+
+impl From<&IpAddr> for AddressFamily {
+    fn from(ip: &IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(_) => AddressFamily::Ipv4,
+            IpAddr::V6(_) => AddressFamily::Ipv6,
+        }
+    }
+}
+
+impl std::fmt::Display for AddressFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressFamily::Ipv4 => write!(f, "ipv4"),
+            AddressFamily::Ipv6 => write!(f, "ipv6"),
+        }
+    }
+}
+
+/// A way of checking that a quiet device is still on the network before it
+/// is marked offline.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum PresenceProbe {
+    /// Unicast ARP request to the device's MAC.
+    Arp,
+    /// ICMP echo request to the device's IPv4 address.
+    Icmp,
+    /// Unicast IPv6 Neighbor Solicitation for the device's IPv6 addresses.
+    Ndp,
+    /// TCP connection attempt; a refused connection still shows the device
+    /// is up.
+    Tcp(u16),
+}
+
+impl std::fmt::Display for PresenceProbe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PresenceProbe::Arp => write!(f, "arp"),
+            PresenceProbe::Icmp => write!(f, "icmp"),
+            PresenceProbe::Ndp => write!(f, "ndp"),
+            PresenceProbe::Tcp(port) => write!(f, "tcp:{}", port),
+        }
+    }
+}
+
+impl std::str::FromStr for PresenceProbe {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "arp" => Ok(PresenceProbe::Arp),
+            "icmp" => Ok(PresenceProbe::Icmp),
+            "ndp" => Ok(PresenceProbe::Ndp),
+            other => other
+                .strip_prefix("tcp:")
+                .and_then(|port| port.parse().ok())
+                .filter(|port| *port != 0)
+                .map(PresenceProbe::Tcp)
+                .ok_or_else(|| format!("Unknown presence probe: {}", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for PresenceProbe {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<PresenceProbe> for String {
+    fn from(probe: PresenceProbe) -> Self {
+        probe.to_string()
+    }
+}
+
+/// Where foxd learned that a device holds an address.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressSource {
+    Arp,
+    Dhcp,
+    Ndp,
+    Netlink,
+    Mdns,
+    Ssdp,
+    Nbns,
+    Llmnr,
+    #[serde(rename = "lease_file")]
+    LeaseFile,
+    Lldp,
+    Cdp,
+    Tcp,
+}
+
+impl std::fmt::Display for AddressSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressSource::Arp => write!(f, "arp"),
+            AddressSource::Dhcp => write!(f, "dhcp"),
+            AddressSource::Ndp => write!(f, "ndp"),
+            AddressSource::Netlink => write!(f, "netlink"),
+            AddressSource::Mdns => write!(f, "mdns"),
+            AddressSource::Ssdp => write!(f, "ssdp"),
+            AddressSource::Nbns => write!(f, "nbns"),
+            AddressSource::Llmnr => write!(f, "llmnr"),
+            AddressSource::LeaseFile => write!(f, "lease_file"),
+            AddressSource::Lldp => write!(f, "lldp"),
+            AddressSource::Cdp => write!(f, "cdp"),
+            AddressSource::Tcp => write!(f, "tcp"),
+        }
+    }
+}
+
+/// Where a device's `device_type` came from.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceTypeSource {
+    /// The DHCP fingerprint.
+    Fingerprint,
+    /// The device's own LLDP or CDP announcement.
+    Announced,
+}
+
+impl std::fmt::Display for DeviceTypeSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceTypeSource::Fingerprint => write!(f, "fingerprint"),
+            DeviceTypeSource::Announced => write!(f, "announced"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceAddress {
+    pub family: AddressFamily,
+    pub address: String,
+    pub source: AddressSource,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// A network segment (monitored interface) a device has been seen on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceSegment {
+    pub segment: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// A DNS-SD service instance announced over mDNS.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AdvertisedService {
+    /// Service type such as `_airplay._tcp`.
+    pub service_type: String,
+    /// Instance name, e.g. `Living Room`.
+    pub instance: String,
+    pub port: Option<u16>,
+    pub txt: Vec<String>,
+}
+
+/// A service a device has been seen advertising.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceService {
+    #[serde(flatten)]
+    pub service: AdvertisedService,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Link-layer discovery protocol a device announced itself with.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscoveryProtocol {
+    Lldp,
+    Cdp,
+}
+
+impl std::fmt::Display for DiscoveryProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiscoveryProtocol::Lldp => write!(f, "lldp"),
+            DiscoveryProtocol::Cdp => write!(f, "cdp"),
+        }
+    }
+}
+
+/// What a switch, access point, phone or other network gear announced
+/// about itself over LLDP or CDP. CDP fields are mapped onto their LLDP
+/// counterparts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LinkAnnouncement {
+    pub protocol: DiscoveryProtocol,
+    /// LLDP chassis ID, e.g. the base MAC of a switch; unset for CDP.
+    pub chassis_id: Option<String>,
+    /// LLDP system name, or the CDP device ID.
+    pub system_name: Option<String>,
+    /// LLDP system description, or the CDP software version.
+    pub system_description: Option<String>,
+    /// CDP platform, e.g. `cisco WS-C2960X-48FPD-L`.
+    pub platform: Option<String>,
+    /// Enabled capabilities in LLDP terms, e.g. `bridge`, `router`,
+    /// `wlan_access_point` or `telephone`.
+    pub capabilities: Vec<String>,
+    pub management_address: Option<String>,
+    /// Port the announcement was sent from: for a switch, the port the
+    /// monitoring host is plugged into.
+    pub port_id: Option<String>,
+    pub port_description: Option<String>,
+}
+
+impl LinkAnnouncement {
+    /// Kind of device its capabilities make it. A phone or access point also
+    /// bridges, so those are checked first.
+    pub fn device_type(&self) -> Option<&'static str> {
+        let has = |capability: &str| self.capabilities.iter().any(|c| c == capability);
+
+        if has("telephone") {
+            Some("voip_phone")
+        } else if has("wlan_access_point") {
+            Some("access_point")
+        } else if has("bridge") {
+            Some("switch")
+        } else if has("router") {
+            Some("router")
+        } else {
+            None
+        }
+    }
+}
+
+/// Device types given to network infrastructure announcing itself over LLDP
+/// or CDP.
+pub const INFRASTRUCTURE_DEVICE_TYPES: [&str; 3] = ["switch", "router", "access_point"];
+
+/// The latest LLDP or CDP announcement from a device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkDiscovery {
+    #[serde(flatten)]
+    pub announcement: LinkAnnouncement,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// What a device has announced about itself over SSDP/UPnP.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpnpInfo {
+    pub uuid: Option<String>,
+    /// `SERVER` header, e.g. `Linux/4.9 UPnP/1.0 Samsung/1.0`.
+    pub server: Option<String>,
+    /// Device type URN, e.g. `urn:schemas-upnp-org:device:MediaRenderer:1`.
+    pub device_type: Option<String>,
+    /// URL of the device description document.
+    pub location: Option<String>,
+    /// Filled in from the description document when
+    /// `ssdp_fetch_descriptions` is enabled.
+    pub friendly_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
+    pub description_fetched_at: Option<DateTime<Utc>>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// A MAC address a device has used, e.g. before a randomized MAC rotated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceMac {
+    pub mac_address: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Two devices claiming the same IPv4 address. `mac_address` held the
+/// address first; `conflicting_mac_address` claimed or probed for it while
+/// it was still in use.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpConflict {
+    pub id: Option<i64>,
+    pub ip_address: String,
+    pub mac_address: String,
+    pub conflicting_mac_address: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// The DHCP lease a device was last granted, from the server's DHCPACK.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DhcpLease {
+    pub mac_address: String,
+    pub ip_address: String,
+    /// Server identifier (option 54) of the server that granted the lease.
+    pub server_id: Option<String>,
+    pub server_mac: String,
+    /// Lease time (option 51); `None` for an infinite lease.
+    pub lease_time_secs: Option<u32>,
+    /// Renewal (T1, option 58) and rebinding (T2, option 59) times, or the
+    /// RFC 2131 defaults of half and seven eighths of the lease time.
+    pub renewal_time_secs: Option<u32>,
+    pub rebinding_time_secs: Option<u32>,
+    /// When the address was first granted to the device.
+    pub acquired_at: DateTime<Utc>,
+    /// When the lease was last granted or renewed.
+    pub renewed_at: DateTime<Utc>,
+    pub renews_at: Option<DateTime<Utc>>,
+    pub rebinds_at: Option<DateTime<Utc>>,
+    /// When the lease ends, or ended if the device released it early.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// How many times the device renewed the lease before it expired.
+    pub renewals: i64,
+}
+
+impl DhcpLease {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+/// A DHCP fingerprint that matched nothing in the fingerprint database, with
+/// what is known about the last device that sent it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnknownFingerprint {
+    /// Parameter request list (option 55).
+    pub fingerprint: String,
+    /// Vendor class identifier (option 60).
+    pub vendor_class: Option<String>,
+    pub mac_address: String,
+    pub hostname: Option<String>,
+    pub vendor: Option<String>,
+    pub times_seen: i64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// What a device has said about itself, used to recognise it when it comes
+/// back under a new randomized MAC.
+#[derive(Debug, Clone, Default)]
+pub struct IdentityHints {
+    pub hostname: Option<String>,
+    pub client_id: Option<String>,
+    /// DHCP parameter request list (option 55), e.g. `1,3,6,15,119,252`.
+    pub dhcp_fingerprint: Option<String>,
+    /// DHCP vendor class identifier (option 60), e.g. `MSFT 5.0`.
+    pub vendor_class: Option<String>,
+    /// Device type announced over LLDP or CDP, which outranks the DHCP
+    /// fingerprint.
+    pub announced_device_type: Option<String>,
+    /// p0f-style signature of a TCP SYN the device sent.
+    pub tcp_signature: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Device {
     pub id: Option<i64>,
     pub mac_address: String,
+    /// Most recently seen IPv4 address, kept for clients that predate
+    /// `addresses`.
     pub ip_address: Option<String>,
     pub hostname: Option<String>,
     pub nickname: Option<String>,
@@ -32,6 +393,92 @@ pub struct Device {
     pub first_seen: DateTime<Utc>,
     pub last_seen: DateTime<Utc>,
     pub status: DeviceStatus,
+    /// Segment the device was most recently seen on.
+    #[serde(default)]
+    pub segment: Option<String>,
+    /// VLAN ID of the tagged frames the device was most recently seen in.
+    #[serde(default)]
+    pub vlan_id: Option<u16>,
+    /// The MAC has the locally administered bit set (usually randomized), so
+    /// no vendor lookup was attempted.
+    #[serde(default)]
+    pub locally_administered: bool,
+    /// DHCP client identifier (option 61) last sent by the device.
+    #[serde(default)]
+    pub dhcp_client_id: Option<String>,
+    /// DHCP parameter request list (option 55) last sent by the device.
+    #[serde(default)]
+    pub dhcp_fingerprint: Option<String>,
+    /// Operating system family from the DHCP fingerprint, e.g. `Android`.
+    #[serde(default)]
+    pub os_family: Option<String>,
+    /// Kind of device, e.g. `phone` or `printer` from the DHCP fingerprint, or
+    /// `switch`, `router`, `access_point` or `voip_phone` from LLDP or CDP.
+    #[serde(default)]
+    pub device_type: Option<String>,
+    /// Where `device_type` came from. An announced type stands until the
+    /// device announces another.
+    #[serde(default)]
+    pub device_type_source: Option<DeviceTypeSource>,
+    /// Whether `device_type` is one of [`INFRASTRUCTURE_DEVICE_TYPES`].
+    #[serde(default)]
+    pub infrastructure: bool,
+    /// Confidence in `os_family` and `device_type`, from 0 to 100; 100 when
+    /// the device announced its type over LLDP or CDP.
+    #[serde(default)]
+    pub classification_confidence: Option<u8>,
+    /// p0f-style signature of the last TCP SYN the device sent, when
+    /// `tcp_fingerprinting` is enabled.
+    #[serde(default)]
+    pub tcp_signature: Option<String>,
+    /// Operating system the TCP SYN signature matched, e.g. `Linux`.
+    #[serde(default)]
+    pub tcp_os: Option<String>,
+    /// Version or variant of `tcp_os`, e.g. `3.11 and newer`.
+    #[serde(default)]
+    pub tcp_os_flavor: Option<String>,
+    /// Every address seen for the device. Only filled in by the API.
+    #[serde(default)]
+    pub addresses: Vec<DeviceAddress>,
+    /// Every segment the device has been seen on. Only filled in by the API.
+    #[serde(default)]
+    pub segments: Vec<DeviceSegment>,
+    /// Every MAC the device has used. Only filled in by the API.
+    #[serde(default)]
+    pub mac_history: Vec<DeviceMac>,
+    /// Services the device advertises. Only filled in by the API.
+    #[serde(default)]
+    pub services: Vec<DeviceService>,
+    /// UPnP details from SSDP announcements. Only filled in by the API.
+    #[serde(default)]
+    pub upnp: Option<UpnpInfo>,
+    /// Latest DHCP lease granted to the device. Only filled in by the API.
+    #[serde(default)]
+    pub lease: Option<DhcpLease>,
+    /// Latest LLDP or CDP announcement from the device. Only filled in by
+    /// the API.
+    #[serde(default)]
+    pub link_discovery: Option<LinkDiscovery>,
+    /// Probes tried before the device is marked offline. `None` uses the
+    /// configured `presence_probes`; an empty list marks it offline as soon
+    /// as it times out.
+    #[serde(default)]
+    pub presence_probes: Option<Vec<PresenceProbe>>,
+    /// Any other MAC answering for one of the device's IPv4 addresses is
+    /// reported as ARP spoofing.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl Device {
+    /// Vendor for display in notifications.
+    pub fn vendor_label(&self) -> &str {
+        match &self.vendor {
+            Some(vendor) => vendor,
+            None if self.locally_administered => "Randomized MAC",
+            None => "Unknown",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, sqlx::Type)]
@@ -42,6 +489,33 @@ pub enum TriggerType {
     DeviceDisconnected,
     NewDevice,
     DeviceStatusChange,
+    /// A watched IPv4 address (gateway, DNS server, pinned device) is
+    /// claimed by a different MAC.
+    ArpSpoofing,
+    /// A MAC sends more gratuitous ARP packets than
+    /// `gratuitous_arp_threshold` per minute.
+    GratuitousArpFlood,
+    /// A MAC claims more IPv4 addresses than `arp_max_ips_per_mac`.
+    MultipleIpClaims,
+    /// Two devices claim the same IPv4 address.
+    IpConflict,
+    /// A DHCP server other than the trusted ones answers clients.
+    RogueDhcpServer,
+}
+
+impl TriggerType {
+    /// Alerts about suspicious or broken network state, as opposed to device
+    /// lifecycle events.
+    pub fn is_alert(&self) -> bool {
+        matches!(
+            self,
+            TriggerType::ArpSpoofing
+                | TriggerType::GratuitousArpFlood
+                | TriggerType::MultipleIpClaims
+                | TriggerType::IpConflict
+                | TriggerType::RogueDhcpServer
+        )
+    }
 }
 
 impl std::fmt::Display for TriggerType {
@@ -51,6 +525,11 @@ impl std::fmt::Display for TriggerType {
             TriggerType::DeviceDisconnected => write!(f, "device_disconnected"),
             TriggerType::NewDevice => write!(f, "new_device"),
             TriggerType::DeviceStatusChange => write!(f, "device_status_change"),
+            TriggerType::ArpSpoofing => write!(f, "arp_spoofing"),
+            TriggerType::GratuitousArpFlood => write!(f, "gratuitous_arp_flood"),
+            TriggerType::MultipleIpClaims => write!(f, "multiple_ip_claims"),
+            TriggerType::IpConflict => write!(f, "ip_conflict"),
+            TriggerType::RogueDhcpServer => write!(f, "rogue_dhcp_server"),
         }
     }
 }
@@ -62,6 +541,17 @@ pub struct Rule {
     pub description: Option<String>,
     pub trigger_type: TriggerType,
     pub mac_filter: Option<String>,
+    /// Only match devices on this segment (interface name).
+    pub segment_filter: Option<String>,
+    /// Only match devices whose vendor contains this text (case-insensitive).
+    pub vendor_filter: Option<String>,
+    /// Only match devices classified as this OS family (case-insensitive).
+    pub os_family_filter: Option<String>,
+    /// Only match devices classified as this device type (case-insensitive).
+    pub device_type_filter: Option<String>,
+    /// Only match devices whose TCP SYN signature matched this OS
+    /// (case-insensitive).
+    pub tcp_os_filter: Option<String>,
     pub enabled: bool,
     pub notification_channels: Vec<String>,
     pub created_at: DateTime<Utc>,
@@ -74,6 +564,16 @@ pub struct RuleRequest {
     pub description: Option<String>,
     pub trigger_type: TriggerType,
     pub mac_filter: Option<String>,
+    #[serde(default)]
+    pub segment_filter: Option<String>,
+    #[serde(default)]
+    pub vendor_filter: Option<String>,
+    #[serde(default)]
+    pub os_family_filter: Option<String>,
+    #[serde(default)]
+    pub device_type_filter: Option<String>,
+    #[serde(default)]
+    pub tcp_os_filter: Option<String>,
     pub enabled: bool,
     pub notification_channels: Vec<String>,
 }
@@ -102,7 +602,10 @@ impl NotificationChannel {
             NotificationChannel::Telegram { chat_id, .. } => format!("telegram_{}", chat_id),
             NotificationChannel::Ntfy { topic, .. } => format!("ntfy_{}", topic),
             NotificationChannel::Webhook { url, .. } => {
-                format!("webhook_{}", url.split('/').last().unwrap_or("unknown"))
+                format!(
+                    "webhook_{}",
+                    url.split('/').next_back().unwrap_or("unknown")
+                )
             }
         }
     }
@@ -124,11 +627,104 @@ pub struct Config {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DaemonConfig {
     pub interface: String,
+    /// Interfaces to monitor, each as its own segment. Overrides `interface`
+    /// when not empty.
+    #[serde(default)]
+    pub interfaces: Vec<String>,
     pub capture_filter: Option<String>,
     pub neighbor_check_interval_secs: u64,
     pub device_timeout_secs: u64,
     pub log_cleanup_enabled: bool,
     pub log_retention_days: u64,
+    #[serde(default)]
+    pub trusted_ipv6_routers: Vec<String>,
+    /// MAC or IPv4 addresses of the DHCP servers allowed to answer clients.
+    /// Empty trusts the first server seen.
+    #[serde(default)]
+    pub trusted_dhcp_servers: Vec<String>,
+    /// MAC addresses of relay agents allowed to forward DHCP replies.
+    #[serde(default)]
+    pub trusted_dhcp_relays: Vec<String>,
+    /// Where packets come from. Unset captures live on `interface`;
+    /// `file:<path>` replays a saved pcap capture instead.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// When replaying, drive the daemon clock (last_seen, timeouts) from the
+    /// packet timestamps instead of wall-clock time.
+    #[serde(default)]
+    pub replay_packet_time: bool,
+    /// Fetch the UPnP description document announced in SSDP `LOCATION`
+    /// headers for friendly name, manufacturer and model.
+    #[serde(default)]
+    pub ssdp_fetch_descriptions: bool,
+    /// Send NetBIOS node status queries to online devices that have no
+    /// hostname yet.
+    #[serde(default)]
+    pub netbios_query_unnamed: bool,
+    /// Seconds between active ARP sweeps of the monitored subnets; 0 only
+    /// sweeps when requested through the API.
+    #[serde(default)]
+    pub arp_scan_interval_secs: u64,
+    /// ARP requests sent per second during a sweep.
+    #[serde(default = "default_arp_scan_rate")]
+    pub arp_scan_rate: u32,
+    /// Probes tried, in order, before a timed-out device is marked offline,
+    /// unless the device has its own list.
+    #[serde(default = "default_presence_probes")]
+    pub presence_probes: Vec<PresenceProbe>,
+    /// IPv4 addresses whose MAC binding is watched for ARP spoofing, on top
+    /// of the default gateway and the DNS servers from /etc/resolv.conf.
+    #[serde(default)]
+    pub arp_watch_ips: Vec<IpAddr>,
+    /// Gratuitous ARP packets per minute from one MAC that count as a flood;
+    /// 0 disables the check.
+    #[serde(default = "default_gratuitous_arp_threshold")]
+    pub gratuitous_arp_threshold: u32,
+    /// IPv4 addresses one MAC may claim within ten minutes before it is
+    /// reported; 0 disables the check.
+    #[serde(default = "default_arp_max_ips_per_mac")]
+    pub arp_max_ips_per_mac: u32,
+    /// DHCP server lease files (dnsmasq, ISC dhcpd or Kea memfile) to read
+    /// hostnames and addresses from.
+    #[serde(default)]
+    pub lease_files: Vec<PathBuf>,
+    /// Capture TCP SYN packets too and guess each device's operating system
+    /// from them, p0f-style.
+    #[serde(default)]
+    pub tcp_fingerprinting: bool,
+    /// Extra DHCP fingerprints, in the format of `data/dhcp_fingerprints.txt`.
+    #[serde(default)]
+    pub dhcp_fingerprints_file: Option<PathBuf>,
+    /// Extra TCP SYN signatures, in the format of p0f's `p0f.fp`.
+    #[serde(default)]
+    pub tcp_signatures_file: Option<PathBuf>,
+}
+
+fn default_arp_scan_rate() -> u32 {
+    100
+}
+
+fn default_gratuitous_arp_threshold() -> u32 {
+    10
+}
+
+fn default_arp_max_ips_per_mac() -> u32 {
+    8
+}
+
+pub fn default_presence_probes() -> Vec<PresenceProbe> {
+    vec![PresenceProbe::Arp, PresenceProbe::Icmp, PresenceProbe::Ndp]
+}
+
+impl DaemonConfig {
+    /// The interfaces to capture on: `interfaces`, or `interface` alone.
+    pub fn monitored_interfaces(&self) -> Vec<String> {
+        if self.interfaces.is_empty() {
+            vec![self.interface.clone()]
+        } else {
+            self.interfaces.clone()
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -158,6 +754,16 @@ pub struct DeviceNicknameRequest {
     pub nickname: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevicePresenceProbesRequest {
+    pub presence_probes: Option<Vec<PresenceProbe>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevicePinnedRequest {
+    pub pinned: bool,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct NotificationEvent {
     pub timestamp: DateTime<Utc>,
@@ -166,24 +772,200 @@ pub struct NotificationEvent {
     pub message: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DhcpMessageType {
+    Discover,
+    Offer,
+    Request,
+    Decline,
+    Ack,
+    Nak,
+    Release,
+    Inform,
+}
+
+impl std::fmt::Display for DhcpMessageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DhcpMessageType::Discover => write!(f, "DISCOVER"),
+            DhcpMessageType::Offer => write!(f, "OFFER"),
+            DhcpMessageType::Request => write!(f, "REQUEST"),
+            DhcpMessageType::Decline => write!(f, "DECLINE"),
+            DhcpMessageType::Ack => write!(f, "ACK"),
+            DhcpMessageType::Nak => write!(f, "NAK"),
+            DhcpMessageType::Release => write!(f, "RELEASE"),
+            DhcpMessageType::Inform => write!(f, "INFORM"),
+        }
+    }
+}
+
+/// Kernel neighbour cache (NUD) state reported over netlink.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NeighborState {
+    Reachable,
+    Stale,
+    Delay,
+    Probe,
+    Failed,
+    Permanent,
+    Other,
+}
+
+/// A monitored interface foxd cannot capture packets on. Presence there is
+/// tracked from the kernel neighbour table alone.
+#[derive(Debug, Clone, Serialize)]
+pub struct DegradedInterface {
+    pub interface: String,
+    /// Why packet capture is unavailable.
+    pub reason: String,
+    pub since: DateTime<Utc>,
+}
+
+/// Where a network event was observed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventOrigin {
+    /// Monitored interface, extended with the VLAN IDs of tagged frames the
+    /// way Linux names VLAN interfaces (`eth0.10`, `eth0.100.10`).
+    pub segment: String,
+    /// Innermost VLAN ID of a tagged frame.
+    pub vlan_id: Option<u16>,
+}
+
+impl EventOrigin {
+    pub fn new(interface: &str, vlan_ids: &[u16]) -> Self {
+        let mut segment = interface.to_string();
+        for vlan_id in vlan_ids {
+            segment.push_str(&format!(".{}", vlan_id));
+        }
+
+        Self {
+            segment,
+            vlan_id: vlan_ids.last().copied(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum NetworkEvent {
     ArpRequest {
         source_mac: String,
         source_ip: IpAddr,
+        target_ip: IpAddr,
     },
     ArpReply {
         source_mac: String,
         source_ip: IpAddr,
+        target_ip: IpAddr,
     },
     DhcpRequest {
         client_mac: String,
+        message_type: DhcpMessageType,
         requested_ip: Option<IpAddr>,
+        hostname: Option<String>,
+        client_id: Option<String>,
+        vendor_class: Option<String>,
+        parameter_request_list: Option<Vec<u8>>,
+        /// Relay agent (`giaddr`) the message came through; the client is
+        /// then on the relay's network, not on the capture segment.
+        relay_agent: Option<IpAddr>,
+    },
+    /// A DHCP OFFER or ACK from a server (or relay agent) to a client.
+    DhcpReply {
+        server_mac: String,
+        server_ip: IpAddr,
+        /// Server identifier (option 54); differs from `server_ip` when the
+        /// reply comes through a relay agent.
+        server_id: Option<IpAddr>,
+        client_mac: String,
+        message_type: DhcpMessageType,
+        offered_ip: Option<IpAddr>,
+        /// Lease (option 51), renewal (option 58) and rebinding (option 59)
+        /// times in seconds.
+        lease_time: Option<u32>,
+        renewal_time: Option<u32>,
+        rebinding_time: Option<u32>,
+        /// Relay agent (`giaddr`) the reply goes through, as in
+        /// `DhcpRequest`.
+        relay_agent: Option<IpAddr>,
+    },
+    /// A lease read from a DHCP server's lease file. `changed` is set when
+    /// the server wrote it while foxd was watching, rather than it being in
+    /// the file already when foxd started.
+    LeaseFileEntry {
+        client_mac: String,
+        ip: IpAddr,
+        hostname: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
+        changed: bool,
+    },
+    MdnsResponse {
+        source_mac: String,
+        source_ip: IpAddr,
+        hostname: Option<String>,
+        services: Vec<AdvertisedService>,
+    },
+    /// An LLDP or CDP announcement. `device_mac` is the chassis MAC when
+    /// LLDP gives one, as switches send from a different MAC on every port.
+    LinkAnnouncement {
+        device_mac: String,
+        announcement: LinkAnnouncement,
+    },
+    /// A TCP SYN sent straight from a host on the segment, with its p0f-style
+    /// signature.
+    TcpSyn {
+        source_mac: String,
+        source_ip: IpAddr,
+        signature: String,
+    },
+    SsdpNotify {
+        source_mac: String,
+        source_ip: IpAddr,
+        uuid: Option<String>,
+        server: Option<String>,
+        device_type: Option<String>,
+        location: Option<String>,
+    },
+    NetbiosName {
+        source_mac: String,
+        source_ip: IpAddr,
+        name: String,
+    },
+    /// The sender refused a registration of `name`, which it already owns.
+    NetbiosConflict {
+        source_mac: String,
+        source_ip: IpAddr,
+        name: String,
+    },
+    LlmnrResponse {
+        source_mac: String,
+        source_ip: IpAddr,
+        name: String,
+    },
+    NeighborSolicitation {
+        source_mac: String,
+        source_ip: Option<IpAddr>,
+        target_ip: IpAddr,
+    },
+    NeighborAdvertisement {
+        source_mac: String,
+        target_ip: IpAddr,
+        is_router: bool,
+    },
+    RouterSolicitation {
+        source_mac: String,
+        source_ip: Option<IpAddr>,
+    },
+    RouterAdvertisement {
+        source_mac: String,
+        source_ip: IpAddr,
+        router_lifetime_secs: u16,
+        prefixes: Vec<String>,
     },
     NeighborAdded {
         mac: String,
         ip: IpAddr,
         interface_index: u32,
+        state: NeighborState,
     },
     NeighborRemoved {
         mac: String,
@@ -194,6 +976,7 @@ pub enum NetworkEvent {
         mac: String,
         ip: IpAddr,
         interface_index: u32,
+        state: NeighborState,
     },
 }
 
@@ -267,3 +1050,21 @@ pub struct LogsResponse {
     pub logs: Vec<LogEntry>,
     pub count: usize,
 }
+
+#[derive(Debug, Serialize)]
+pub struct LeasesResponse {
+    pub leases: Vec<DhcpLease>,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnknownFingerprintsResponse {
+    pub fingerprints: Vec<UnknownFingerprint>,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IpConflictsResponse {
+    pub conflicts: Vec<IpConflict>,
+    pub count: usize,
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::process::Command;
+use tracing::info;
+
+use crate::models::NeighborState;
+
+/// ATF_COM in /proc/net/arp: the entry has a link-layer address.
+const ATF_COM: u32 = 0x2;
+/// ATF_PERM in /proc/net/arp: the entry was added statically.
+const ATF_PERM: u32 = 0x4;
+
+/// One row of the kernel neighbour table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeighborEntry {
+    pub ip: IpAddr,
+    /// Missing while the kernel is still resolving the address, or after it
+    /// gave up.
+    pub mac: Option<String>,
+    pub state: NeighborState,
+}
+
+/// A neighbour worth reporting, shaped like the netlink events.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeighborChange {
+    pub mac: String,
+    pub ip: IpAddr,
+    pub state: NeighborState,
+    /// The entry was not in the previous read.
+    pub added: bool,
+}
+
+/// Reads the neighbour table of one interface without any privileges, for
+/// when neither packet capture nor the netlink multicast group is available.
+/// `ip neigh` gives the real NUD state and IPv6 neighbours; without the `ip`
+/// tool, /proc/net/arp only says whether an IPv4 entry is complete, so an
+/// entry counts as fresh evidence when it appears or changes.
+pub struct NeighborTable {
+    interface: String,
+    ip_command: bool,
+    known: HashMap<IpAddr, (String, NeighborState)>,
+}
+
+impl NeighborTable {
+    pub fn new(interface: &str) -> Self {
+        Self {
+            interface: interface.to_string(),
+            ip_command: true,
+            known: HashMap::new(),
+        }
+    }
+
+    /// Reads the table and returns what to report. With `ip neigh` every
+    /// entry is reported on each read, as the netlink monitor does when it
+    /// resyncs; with /proc/net/arp only entries that became complete, were
+    /// learned again or failed are, plus static entries.
+    pub fn poll(&mut self) -> io::Result<Vec<NeighborChange>> {
+        let entries = match self.ip_command.then(|| read_ip_neigh(&self.interface)) {
+            Some(Ok(entries)) => Some(entries),
+            Some(Err(e)) => {
+                info!(
+                    "Cannot run `ip neigh` ({}), reading /proc/net/arp for {} instead",
+                    e, self.interface
+                );
+                self.ip_command = false;
+                None
+            }
+            None => None,
+        };
+        let confirmed = entries.is_some();
+        let entries = match entries {
+            Some(entries) => entries,
+            None => parse_proc_net_arp(&std::fs::read_to_string("/proc/net/arp")?, &self.interface),
+        };
+
+        let mut previous = std::mem::take(&mut self.known);
+        let mut changes = Vec::new();
+
+        for entry in entries {
+            let earlier = previous.remove(&entry.ip);
+            // Failed entries lose their link-layer address.
+            let Some(mac) = entry
+                .mac
+                .or_else(|| earlier.as_ref().map(|(mac, _)| mac.clone()))
+            else {
+                continue;
+            };
+            self.known.insert(entry.ip, (mac.clone(), entry.state));
+
+            let state = if confirmed {
+                entry.state
+            } else {
+                match (entry.state, &earlier) {
+                    (NeighborState::Permanent, _) => NeighborState::Permanent,
+                    (NeighborState::Stale, Some((known_mac, known_state)))
+                        if *known_mac == mac && *known_state != NeighborState::Failed =>
+                    {
+                        continue;
+                    }
+                    (NeighborState::Stale, _) => NeighborState::Reachable,
+                    (NeighborState::Failed, Some((_, NeighborState::Failed)) | None) => {
+                        continue;
+                    }
+                    (state, _) => state,
+                }
+            };
+
+            changes.push(NeighborChange {
+                mac,
+                ip: entry.ip,
+                state,
+                added: earlier.is_none(),
+            });
+        }
+
+        Ok(changes)
+    }
+}
+
+/// Index of `interface`, as reported with netlink neighbour events.
+pub fn interface_index(interface: &str) -> u32 {
+    std::fs::read_to_string(format!("/sys/class/net/{}/ifindex", interface))
+        .ok()
+        .and_then(|index| index.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Runs `ip neigh show dev <interface>`. Blocks until the command exits.
+pub fn read_ip_neigh(interface: &str) -> io::Result<Vec<NeighborEntry>> {
+    let output = Command::new("ip")
+        .args(["neigh", "show", "dev", interface])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(parse_ip_neigh(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses `ip neigh` output, e.g.
+/// `192.168.1.1 lladdr aa:bb:cc:dd:ee:ff router REACHABLE`. The state comes
+/// last; entries being resolved or that failed have no `lladdr`.
+pub fn parse_ip_neigh(output: &str) -> Vec<NeighborEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let ip = fields.first()?.parse().ok()?;
+            let mac = fields
+                .iter()
+                .position(|field| *field == "lladdr")
+                .and_then(|index| fields.get(index + 1))
+                .map(|mac| mac.to_lowercase());
+            let state = match *fields.last()? {
+                "REACHABLE" => NeighborState::Reachable,
+                "STALE" => NeighborState::Stale,
+                "DELAY" => NeighborState::Delay,
+                "PROBE" => NeighborState::Probe,
+                "FAILED" | "INCOMPLETE" => NeighborState::Failed,
+                "PERMANENT" | "NOARP" => NeighborState::Permanent,
+                _ => NeighborState::Other,
+            };
+            Some(NeighborEntry { ip, mac, state })
+        })
+        .collect()
+}
+
+/// Parses /proc/net/arp, keeping the rows of `interface`. Complete entries
+/// are reported as stale since the file carries no reachability state.
+pub fn parse_proc_net_arp(contents: &str, interface: &str) -> Vec<NeighborEntry> {
+    contents
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [ip, _, flags, mac, _, device] = fields[..] else {
+                return None;
+            };
+            if device != interface {
+                return None;
+            }
+            let ip = ip.parse().ok()?;
+            let flags = u32::from_str_radix(flags.trim_start_matches("0x"), 16).ok()?;
+
+            let (mac, state) = if flags & ATF_COM == 0 {
+                (None, NeighborState::Failed)
+            } else if flags & ATF_PERM != 0 {
+                (Some(mac.to_lowercase()), NeighborState::Permanent)
+            } else {
+                (Some(mac.to_lowercase()), NeighborState::Stale)
+            };
+            Some(NeighborEntry { ip, mac, state })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(ip: &str, mac: Option<&str>, state: NeighborState) -> NeighborEntry {
+        NeighborEntry {
+            ip: ip.parse().unwrap(),
+            mac: mac.map(String::from),
+            state,
+        }
+    }
+
+    #[test]
+    fn parses_ip_neigh() {
+        let output = "\
+192.168.1.1 lladdr AA:BB:CC:DD:EE:01 router REACHABLE
+192.168.1.23 lladdr 8c:85:90:1a:2b:3c STALE
+192.168.1.40  FAILED
+192.168.1.41 lladdr 00:1b:21:3c:4d:5e PERMANENT
+fe80::1 lladdr aa:bb:cc:dd:ee:01 router DELAY
+not-an-address lladdr 00:00:00:00:00:00 STALE
+";
+
+        assert_eq!(
+            parse_ip_neigh(output),
+            vec![
+                entry(
+                    "192.168.1.1",
+                    Some("aa:bb:cc:dd:ee:01"),
+                    NeighborState::Reachable
+                ),
+                entry(
+                    "192.168.1.23",
+                    Some("8c:85:90:1a:2b:3c"),
+                    NeighborState::Stale
+                ),
+                entry("192.168.1.40", None, NeighborState::Failed),
+                entry(
+                    "192.168.1.41",
+                    Some("00:1b:21:3c:4d:5e"),
+                    NeighborState::Permanent
+                ),
+                entry("fe80::1", Some("aa:bb:cc:dd:ee:01"), NeighborState::Delay),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_proc_net_arp() {
+        let contents = "\
+IP address       HW type     Flags       HW address            Mask     Device
+192.168.1.1      0x1         0x2         aa:bb:cc:dd:ee:01     *        eth0
+192.168.1.40     0x1         0x0         00:00:00:00:00:00     *        eth0
+192.168.1.41     0x1         0x6         00:1B:21:3C:4D:5E     *        eth0
+10.0.0.1         0x1         0x2         aa:bb:cc:dd:ee:02     *        wlan0
+";
+
+        assert_eq!(
+            parse_proc_net_arp(contents, "eth0"),
+            vec![
+                entry(
+                    "192.168.1.1",
+                    Some("aa:bb:cc:dd:ee:01"),
+                    NeighborState::Stale
+                ),
+                entry("192.168.1.40", None, NeighborState::Failed),
+                entry(
+                    "192.168.1.41",
+                    Some("00:1b:21:3c:4d:5e"),
+                    NeighborState::Permanent
+                ),
+            ]
+        );
+    }
+}
@@ -89,6 +89,7 @@ impl Notifier {
              <b>Device:</b> {}\n\
              <b>IP:</b> {}\n\
              <b>MAC:</b> {}\n\
+             <b>Vendor:</b> {}\n\
              <b>Status:</b> {}\n\
              <b>Time:</b> {}\n\n\
              {}",
@@ -97,10 +98,16 @@ impl Notifier {
                 crate::models::TriggerType::DeviceDisconnected => "Device Disconnected",
                 crate::models::TriggerType::NewDevice => "New Device Discovered",
                 crate::models::TriggerType::DeviceStatusChange => "Device Status Changed",
+                crate::models::TriggerType::ArpSpoofing => "Possible ARP Spoofing",
+                crate::models::TriggerType::GratuitousArpFlood => "Gratuitous ARP Flood",
+                crate::models::TriggerType::MultipleIpClaims => "MAC Claiming Many IPs",
+                crate::models::TriggerType::IpConflict => "IP Address Conflict",
+                crate::models::TriggerType::RogueDhcpServer => "Rogue DHCP Server",
             },
             event.device.hostname.as_deref().unwrap_or("Unknown"),
             event.device.ip_address.as_deref().unwrap_or("Unknown"),
             event.device.mac_address,
+            event.device.vendor_label(),
             event.device.status,
             event.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
             event.message
@@ -139,22 +146,37 @@ impl Notifier {
             crate::models::TriggerType::DeviceDisconnected => "Device Disconnected",
             crate::models::TriggerType::NewDevice => "New Device",
             crate::models::TriggerType::DeviceStatusChange => "Status Changed",
+            crate::models::TriggerType::ArpSpoofing => "ARP Spoofing",
+            crate::models::TriggerType::GratuitousArpFlood => "Gratuitous ARP Flood",
+            crate::models::TriggerType::MultipleIpClaims => "Many IPs Claimed",
+            crate::models::TriggerType::IpConflict => "IP Conflict",
+            crate::models::TriggerType::RogueDhcpServer => "Rogue DHCP Server",
         };
 
-        let message = format!(
-            "{}\nMAC: {}\nIP: {}\nStatus: {}",
+        let mut message = format!(
+            "{}\nMAC: {}\nVendor: {}\nIP: {}\nStatus: {}",
             event.device.hostname.as_deref().unwrap_or("Unknown"),
             event.device.mac_address,
+            event.device.vendor_label(),
             event.device.ip_address.as_deref().unwrap_or("Unknown"),
             event.device.status
         );
+        let (priority, tags) = match event.event_type {
+            // Takes every client on the network offline.
+            crate::models::TriggerType::RogueDhcpServer => ("urgent", "fox,network,rotating_light"),
+            ref event_type if event_type.is_alert() => ("high", "fox,network,warning"),
+            _ => ("default", "fox,network"),
+        };
+        if event.event_type.is_alert() {
+            message = format!("{}\n{}", event.message, message);
+        }
 
         let mut request = self
             .client
             .post(&url)
             .header("Title", title)
-            .header("Priority", "default")
-            .header("Tags", "fox,network");
+            .header("Priority", priority)
+            .header("Tags", tags);
 
         if let Some(token) = token {
             request = request.header("Authorization", format!("Bearer {}", token));
@@ -186,6 +208,8 @@ impl Notifier {
                 "mac_address": event.device.mac_address,
                 "ip_address": event.device.ip_address,
                 "hostname": event.device.hostname,
+                "vendor": event.device.vendor,
+                "locally_administered": event.device.locally_administered,
                 "status": event.device.status,
                 "last_seen": event.device.last_seen
             },
@@ -194,12 +218,10 @@ impl Notifier {
 
         let mut request = self.client.post(url).json(&payload);
 
-        if let Some(headers_obj) = headers {
-            if let Some(headers_map) = headers_obj.as_object() {
-                for (key, value) in headers_map {
-                    if let Some(value_str) = value.as_str() {
-                        request = request.header(key, value_str);
-                    }
+        if let Some(headers_map) = headers.and_then(|h| h.as_object()) {
+            for (key, value) in headers_map {
+                if let Some(value_str) = value.as_str() {
+                    request = request.header(key, value_str);
                 }
             }
         }
@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use tracing::warn;
+
+use crate::db::Database;
+use crate::errors::{DaemonError, Result};
+
+/// Registry shipped with foxd, in the format written by [`OuiRegistry::encode`].
+/// Regenerate it from the IEEE downloads with `scripts/update-oui.sh`.
+static EMBEDDED: &[u8] = include_bytes!("../data/oui.bin");
+
+const MAGIC: &[u8] = b"FOXOUI1";
+
+/// Assignment sizes in bits, longest first: MA-S, MA-M, MA-L.
+const PREFIX_BITS: [u8; 3] = [36, 28, 24];
+
+/// Result of looking up the vendor of a MAC address.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VendorLookup {
+    Vendor(String),
+    /// The locally administered bit is set, so the address was not assigned
+    /// by the IEEE; usually a randomized MAC.
+    LocallyAdministered,
+    Unknown,
+}
+
+/// IEEE MA-L, MA-M and MA-S assignments.
+#[derive(Debug, Default)]
+pub struct OuiRegistry {
+    vendors: Vec<String>,
+    /// Vendor index keyed by prefix length and prefix value.
+    prefixes: HashMap<(u8, u64), usize>,
+}
+
+impl OuiRegistry {
+    pub fn embedded() -> Self {
+        Self::decode(EMBEDDED).unwrap_or_else(|e| {
+            warn!("Embedded OUI registry is unreadable: {}", e);
+            Self::default()
+        })
+    }
+
+    /// Returns the registry imported with `foxd oui-refresh`, or the embedded
+    /// one if none has been imported.
+    pub async fn load(db: &Database) -> Result<Self> {
+        match db.get_oui_registry().await? {
+            Some(data) => Self::decode(&data),
+            None => Ok(Self::embedded()),
+        }
+    }
+
+    /// Builds a registry from IEEE CSV exports (`oui.csv`, `mam.csv`,
+    /// `oui36.csv`).
+    pub fn from_csv_files(paths: &[impl AsRef<Path>]) -> Result<Self> {
+        let mut registry = Self::default();
+        for path in paths {
+            registry.import_csv(BufReader::new(File::open(path)?))?;
+        }
+        Ok(registry)
+    }
+
+    pub fn len(&self) -> usize {
+        self.prefixes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+
+    pub fn lookup(&self, mac: &str) -> VendorLookup {
+        let Some(address) = parse_mac(mac) else {
+            return VendorLookup::Unknown;
+        };
+
+        if (address >> 40) & 0x02 != 0 {
+            return VendorLookup::LocallyAdministered;
+        }
+
+        PREFIX_BITS
+            .iter()
+            .find_map(|&bits| self.prefixes.get(&(bits, address >> (48 - bits))))
+            .map(|&index| VendorLookup::Vendor(self.vendors[index].clone()))
+            .unwrap_or(VendorLookup::Unknown)
+    }
+
+    /// Adds the assignments in an IEEE registry CSV
+    /// (`Registry,Assignment,Organization Name,Organization Address`).
+    /// Returns the number of assignments read.
+    pub fn import_csv(&mut self, reader: impl BufRead) -> Result<usize> {
+        let mut vendor_index: HashMap<String, usize> = self
+            .vendors
+            .iter()
+            .enumerate()
+            .map(|(index, vendor)| (vendor.clone(), index))
+            .collect();
+        let mut count = 0;
+
+        for line in reader.lines() {
+            let fields = split_csv_line(&line?);
+            let (Some(assignment), Some(name)) = (fields.get(1), fields.get(2)) else {
+                continue;
+            };
+
+            let bits = match assignment.len() {
+                6 => 24,
+                7 => 28,
+                9 => 36,
+                _ => continue,
+            };
+            let Ok(prefix) = u64::from_str_radix(assignment, 16) else {
+                continue;
+            };
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+
+            let index = *vendor_index.entry(name.to_string()).or_insert_with(|| {
+                self.vendors.push(name.to_string());
+                self.vendors.len() - 1
+            });
+            self.prefixes.insert((bits, prefix), index);
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Serializes the registry compactly: a deduplicated vendor name table
+    /// followed by, for each prefix length, the sorted prefixes as varint
+    /// deltas with the index of their vendor.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut entries: Vec<(u8, u64, &str)> = self
+            .prefixes
+            .iter()
+            .map(|(&(bits, prefix), &index)| (bits, prefix, self.vendors[index].as_str()))
+            .collect();
+        entries.sort();
+
+        let mut names = Vec::new();
+        let mut name_index: HashMap<&str, usize> = HashMap::new();
+        for (_, _, name) in &entries {
+            name_index.entry(name).or_insert_with(|| {
+                names.push(*name);
+                names.len() - 1
+            });
+        }
+
+        let mut out = MAGIC.to_vec();
+        write_varint(&mut out, names.len() as u64);
+        for name in &names {
+            write_varint(&mut out, name.len() as u64);
+            out.extend_from_slice(name.as_bytes());
+        }
+
+        for bits in PREFIX_BITS {
+            let group: Vec<_> = entries.iter().filter(|(b, _, _)| *b == bits).collect();
+            write_varint(&mut out, group.len() as u64);
+
+            let mut previous = 0;
+            for (_, prefix, name) in group {
+                write_varint(&mut out, prefix - previous);
+                write_varint(&mut out, name_index[name] as u64);
+                previous = *prefix;
+            }
+        }
+
+        out
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader { data, position: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(corrupt("bad header"));
+        }
+
+        let vendor_count = reader.varint()? as usize;
+        let mut vendors = Vec::with_capacity(vendor_count);
+        for _ in 0..vendor_count {
+            let len = reader.varint()? as usize;
+            let name = std::str::from_utf8(reader.take(len)?)
+                .map_err(|_| corrupt("vendor name is not UTF-8"))?;
+            vendors.push(name.to_string());
+        }
+
+        let mut prefixes = HashMap::new();
+        for bits in PREFIX_BITS {
+            let mut prefix = 0;
+            for _ in 0..reader.varint()? {
+                prefix += reader.varint()?;
+                let index = reader.varint()? as usize;
+                if index >= vendors.len() {
+                    return Err(corrupt("vendor index out of range"));
+                }
+                prefixes.insert((bits, prefix), index);
+            }
+        }
+
+        Ok(Self { vendors, prefixes })
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| corrupt("truncated"))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(corrupt("varint too long"))
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn corrupt(reason: &str) -> DaemonError {
+    DaemonError::Internal(format!("Corrupt OUI registry: {}", reason))
+}
+
+fn parse_mac(mac: &str) -> Option<u64> {
+    let octets: Vec<&str> = mac.split([':', '-']).collect();
+    if octets.len() != 6 {
+        return None;
+    }
+
+    octets.iter().try_fold(0u64, |address, octet| {
+        Some((address << 8) | u64::from(u8::from_str_radix(octet, 16).ok()?))
+    })
+}
+
+/// Splits one CSV record, honouring double-quoted fields.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vendor(name: &str) -> VendorLookup {
+        VendorLookup::Vendor(name.to_string())
+    }
+
+    #[test]
+    fn embedded_registry_knows_common_vendors() {
+        let registry = OuiRegistry::embedded();
+
+        assert_eq!(
+            registry.lookup("b8:27:eb:12:34:56"),
+            vendor("Raspberry Pi Foundation")
+        );
+        assert_eq!(registry.lookup("00:50:56:C0:00:08"), vendor("VMware, Inc."));
+        assert_eq!(
+            registry.lookup("08-00-27-aa-bb-cc"),
+            vendor("PCS Systemtechnik GmbH")
+        );
+        assert_eq!(
+            registry.lookup("00:00:0c:07:ac:01"),
+            vendor("Cisco Systems, Inc")
+        );
+        assert_eq!(
+            registry.lookup("00:17:88:01:02:03"),
+            vendor("Philips Lighting BV")
+        );
+    }
+
+    /// Needs the full registry in `data/oui.bin`; see `scripts/update-oui.sh`.
+    #[test]
+    #[ignore = "data/oui.bin has to be regenerated from the IEEE registries first"]
+    fn embedded_registry_covers_every_assignment_size() {
+        let registry = OuiRegistry::embedded();
+        assert!(registry.len() > 40_000, "{} assignments", registry.len());
+
+        for (bits, minimum) in [(24, 30_000), (28, 3_000), (36, 3_000)] {
+            let assignments: Vec<_> = registry
+                .prefixes
+                .iter()
+                .filter(|((size, _), _)| *size == bits)
+                .collect();
+            assert!(
+                assignments.len() > minimum,
+                "{} assignments of {} bits",
+                assignments.len(),
+                bits
+            );
+
+            // The last address of the first assignment found.
+            let (&(_, prefix), &index) = assignments[0];
+            let address = (prefix << (48 - bits)) | ((1 << (48 - bits)) - 1);
+            let mac = address.to_be_bytes()[2..]
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(":");
+            assert_eq!(
+                registry.lookup(&mac),
+                VendorLookup::Vendor(registry.vendors[index].clone()),
+                "{}",
+                mac
+            );
+        }
+    }
+
+    #[test]
+    fn flags_locally_administered_addresses() {
+        let registry = OuiRegistry::embedded();
+
+        assert_eq!(
+            registry.lookup("da:a1:19:00:00:01"),
+            VendorLookup::LocallyAdministered
+        );
+        assert_eq!(registry.lookup("00:00:00:00:00:01"), VendorLookup::Unknown);
+        assert_eq!(registry.lookup("not a mac"), VendorLookup::Unknown);
+    }
+
+    #[test]
+    fn longest_assignment_wins() {
+        let csv = "Registry,Assignment,Organization Name,Organization Address\n\
+                   MA-L,70B3D5,IEEE Registration Authority,Piscataway\n\
+                   MA-M,70B3D51,\"Foo \"\"Bar\"\", Inc.\",Somewhere\n\
+                   MA-S,70B3D5123,Baz,Elsewhere\n";
+        let mut registry = OuiRegistry::default();
+        assert_eq!(registry.import_csv(csv.as_bytes()).unwrap(), 3);
+
+        let registry = OuiRegistry::decode(&registry.encode()).unwrap();
+        assert_eq!(registry.len(), 3);
+        assert_eq!(registry.lookup("70:b3:d5:12:34:56"), vendor("Baz"));
+        assert_eq!(
+            registry.lookup("70:b3:d5:19:34:56"),
+            vendor("Foo \"Bar\", Inc.")
+        );
+        assert_eq!(
+            registry.lookup("70:b3:d5:22:34:56"),
+            vendor("IEEE Registration Authority")
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_data() {
+        assert!(OuiRegistry::decode(b"FOXOUI1\x05").is_err());
+        assert!(OuiRegistry::decode(b"NOTOUI").is_err());
+    }
+}
@@ -0,0 +1,343 @@
+use ipnetwork::IpNetwork;
+use pnet::datalink::{self, Channel, DataLinkReceiver, DataLinkSender, NetworkInterface};
+use pnet::packet::{MutablePacket, Packet};
+use pnet::packet::arp::{ArpOperations, ArpPacket};
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
+use pnet::packet::icmp::echo_reply::EchoReplyPacket;
+use pnet::packet::icmp::echo_request::MutableEchoRequestPacket;
+use pnet::packet::icmp::{self, IcmpPacket, IcmpTypes};
+use pnet::packet::icmpv6::{self, Icmpv6Packet, Icmpv6Types, MutableIcmpv6Packet};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv6::{Ipv6Packet, MutableIpv6Packet};
+use pnet::transport::{self, TransportChannelType, TransportProtocol};
+use pnet::util::MacAddr;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tracing::debug;
+
+use crate::errors::{DaemonError, Result};
+use crate::models::PresenceProbe;
+use crate::scanner;
+
+/// How long each probe waits for an answer.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+const ETHERNET_HEADER_LEN: usize = 14;
+const IPV6_HEADER_LEN: usize = 40;
+/// ICMPv6 header, reserved field, target address and a source link-layer
+/// address option.
+const NEIGHBOR_SOLICIT_LEN: usize = 4 + 4 + 16 + 8;
+
+static ECHO_SEQUENCE: AtomicU16 = AtomicU16::new(0);
+
+/// The device a probe is aimed at.
+#[derive(Debug, Clone)]
+pub struct ProbeTarget {
+    pub mac: MacAddr,
+    pub ipv4: Option<Ipv4Addr>,
+    pub ipv6: Vec<Ipv6Addr>,
+}
+
+/// Tries `probes` in order on `interface` and returns the first one the
+/// device answered, or `None` if it answered none of them.
+pub async fn probe(
+    interface: &str,
+    target: &ProbeTarget,
+    probes: &[PresenceProbe],
+) -> Option<PresenceProbe> {
+    for &probe in probes {
+        if run(interface, target, probe).await {
+            return Some(probe);
+        }
+    }
+    None
+}
+
+async fn run(interface: &str, target: &ProbeTarget, probe: PresenceProbe) -> bool {
+    let blocking: fn(&str, &ProbeTarget) -> Result<bool> = match probe {
+        PresenceProbe::Arp => arp_probe,
+        PresenceProbe::Icmp => icmp_probe,
+        PresenceProbe::Ndp => ndp_probe,
+        PresenceProbe::Tcp(port) => return tcp_probe(target, port).await,
+    };
+
+    let interface = interface.to_string();
+    let target = target.clone();
+    let result = tokio::task::spawn_blocking(move || blocking(&interface, &target)).await;
+
+    match result {
+        Ok(Ok(answered)) => answered,
+        Ok(Err(e)) => {
+            debug!("{} probe failed: {}", probe, e);
+            false
+        }
+        Err(e) => {
+            debug!("{} probe panicked: {}", probe, e);
+            false
+        }
+    }
+}
+
+fn arp_probe(interface: &str, target: &ProbeTarget) -> Result<bool> {
+    let Some(target_ip) = target.ipv4 else {
+        return Ok(false);
+    };
+    let network_interface = find_interface(interface)?;
+    let (Some(source_mac), Some(source_ip)) = (
+        network_interface.mac,
+        source_ipv4(&network_interface, target_ip),
+    ) else {
+        return Ok(false);
+    };
+
+    let (mut tx, mut rx) = open_channel(&network_interface)?;
+    send(
+        tx.as_mut(),
+        &scanner::arp_request(source_mac, source_ip, target.mac, target_ip),
+    )?;
+
+    Ok(wait_for(rx.as_mut(), |ethernet| {
+        ethernet.get_ethertype() == EtherTypes::Arp
+            && ArpPacket::new(ethernet.payload()).is_some_and(|arp| {
+                arp.get_operation() == ArpOperations::Reply
+                    && arp.get_sender_hw_addr() == target.mac
+                    && arp.get_sender_proto_addr() == target_ip
+            })
+    }))
+}
+
+fn icmp_probe(_interface: &str, target: &ProbeTarget) -> Result<bool> {
+    let Some(target_ip) = target.ipv4 else {
+        return Ok(false);
+    };
+
+    let (mut tx, mut rx) = transport::transport_channel(
+        4096,
+        TransportChannelType::Layer4(TransportProtocol::Ipv4(IpNextHeaderProtocols::Icmp)),
+    )?;
+
+    let identifier = std::process::id() as u16;
+    let sequence = ECHO_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    let mut buffer = [0u8; 16];
+    let mut request =
+        MutableEchoRequestPacket::new(&mut buffer).expect("buffer fits an echo request");
+    request.set_icmp_type(IcmpTypes::EchoRequest);
+    request.set_identifier(identifier);
+    request.set_sequence_number(sequence);
+    let checksum =
+        icmp::checksum(&IcmpPacket::new(request.packet()).expect("echo request is ICMP"));
+    request.set_checksum(checksum);
+    tx.send_to(request, IpAddr::V4(target_ip))?;
+
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    let mut replies = transport::icmp_packet_iter(&mut rx);
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        let Some((packet, address)) = replies.next_with_timeout(remaining)? else {
+            break;
+        };
+        if address == IpAddr::V4(target_ip)
+            && packet.get_icmp_type() == IcmpTypes::EchoReply
+            && EchoReplyPacket::new(packet.packet()).is_some_and(|reply| {
+                reply.get_identifier() == identifier && reply.get_sequence_number() == sequence
+            })
+        {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn ndp_probe(interface: &str, target: &ProbeTarget) -> Result<bool> {
+    if target.ipv6.is_empty() {
+        return Ok(false);
+    }
+    let network_interface = find_interface(interface)?;
+    let (Some(source_mac), Some(source_ip)) =
+        (network_interface.mac, source_ipv6(&network_interface))
+    else {
+        return Ok(false);
+    };
+
+    let (mut tx, mut rx) = open_channel(&network_interface)?;
+    for &target_ip in &target.ipv6 {
+        send(
+            tx.as_mut(),
+            &neighbor_solicitation(source_mac, source_ip, target.mac, target_ip),
+        )?;
+    }
+
+    Ok(wait_for(rx.as_mut(), |ethernet| {
+        ethernet.get_ethertype() == EtherTypes::Ipv6
+            && ethernet.get_source() == target.mac
+            && Ipv6Packet::new(ethernet.payload()).is_some_and(|ipv6| {
+                ipv6.get_next_header() == IpNextHeaderProtocols::Icmpv6
+                    && Icmpv6Packet::new(ipv6.payload())
+                        .is_some_and(|icmp| icmp.get_icmpv6_type() == Icmpv6Types::NeighborAdvert)
+            })
+    }))
+}
+
+/// Connects to `port` on each of the device's addresses. A refused
+/// connection counts as an answer: the device had to be up to refuse it.
+async fn tcp_probe(target: &ProbeTarget, port: u16) -> bool {
+    let addresses = target
+        .ipv4
+        .map(IpAddr::V4)
+        .into_iter()
+        // Link-local addresses would need a scope ID.
+        .chain(
+            target
+                .ipv6
+                .iter()
+                .filter(|address| !address.is_unicast_link_local())
+                .map(|address| IpAddr::V6(*address)),
+        );
+
+    for address in addresses {
+        match tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect((address, port))).await {
+            Ok(Ok(_)) => return true,
+            Ok(Err(e)) if e.kind() == ErrorKind::ConnectionRefused => return true,
+            Ok(Err(e)) => debug!("TCP probe of {}:{} failed: {}", address, port, e),
+            Err(_) => debug!("TCP probe of {}:{} timed out", address, port),
+        }
+    }
+
+    false
+}
+
+fn find_interface(interface: &str) -> Result<NetworkInterface> {
+    datalink::interfaces()
+        .into_iter()
+        .find(|i| i.name == interface)
+        .ok_or_else(|| DaemonError::PacketCapture(format!("Interface {} not found", interface)))
+}
+
+/// The interface address on the same subnet as `target`, or any IPv4
+/// address of the interface.
+fn source_ipv4(interface: &NetworkInterface, target: Ipv4Addr) -> Option<Ipv4Addr> {
+    let addresses: Vec<_> = interface
+        .ips
+        .iter()
+        .filter_map(|ip| match ip {
+            IpNetwork::V4(network) => Some(network),
+            IpNetwork::V6(_) => None,
+        })
+        .collect();
+
+    addresses
+        .iter()
+        .find(|network| network.contains(target))
+        .or(addresses.first())
+        .map(|network| network.ip())
+}
+
+/// The interface's link-local address, which Neighbor Discovery prefers,
+/// or any IPv6 address of the interface.
+fn source_ipv6(interface: &NetworkInterface) -> Option<Ipv6Addr> {
+    let addresses: Vec<Ipv6Addr> = interface
+        .ips
+        .iter()
+        .filter_map(|ip| match ip {
+            IpNetwork::V6(network) => Some(network.ip()),
+            IpNetwork::V4(_) => None,
+        })
+        .collect();
+
+    addresses
+        .iter()
+        .find(|address| address.is_unicast_link_local())
+        .or(addresses.first())
+        .copied()
+}
+
+fn open_channel(
+    interface: &NetworkInterface,
+) -> Result<(Box<dyn DataLinkSender>, Box<dyn DataLinkReceiver>)> {
+    let config = datalink::Config {
+        read_timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+
+    match datalink::channel(interface, config)? {
+        Channel::Ethernet(tx, rx) => Ok((tx, rx)),
+        _ => Err(DaemonError::PacketCapture(format!(
+            "Unsupported AF_PACKET channel on {}",
+            interface.name
+        ))),
+    }
+}
+
+fn send(tx: &mut dyn DataLinkSender, frame: &[u8]) -> Result<()> {
+    tx.send_to(frame, None)
+        .unwrap_or_else(|| Err(std::io::Error::other("send buffer unavailable")))?;
+    Ok(())
+}
+
+/// Reads frames until one satisfies `answered` or the probe times out.
+fn wait_for(rx: &mut dyn DataLinkReceiver, answered: impl Fn(&EthernetPacket) -> bool) -> bool {
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+
+    while Instant::now() < deadline {
+        match rx.next() {
+            Ok(frame) => {
+                if EthernetPacket::new(frame).is_some_and(|ethernet| answered(&ethernet)) {
+                    return true;
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::TimedOut => {}
+            Err(e) => {
+                debug!("Reading probe answers failed: {}", e);
+                return false;
+            }
+        }
+    }
+
+    false
+}
+
+/// Builds a Neighbor Solicitation for `target_ip`, sent straight to the MAC
+/// it is expected at rather than to the solicited-node multicast group.
+fn neighbor_solicitation(
+    source_mac: MacAddr,
+    source_ip: Ipv6Addr,
+    destination: MacAddr,
+    target_ip: Ipv6Addr,
+) -> Vec<u8> {
+    let mut frame = vec![0u8; ETHERNET_HEADER_LEN + IPV6_HEADER_LEN + NEIGHBOR_SOLICIT_LEN];
+
+    let mut ethernet =
+        MutableEthernetPacket::new(&mut frame).expect("frame fits an Ethernet header");
+    ethernet.set_destination(destination);
+    ethernet.set_source(source_mac);
+    ethernet.set_ethertype(EtherTypes::Ipv6);
+
+    let mut ipv6 = MutableIpv6Packet::new(&mut frame[ETHERNET_HEADER_LEN..])
+        .expect("frame fits an IPv6 header");
+    ipv6.set_version(6);
+    ipv6.set_payload_length(NEIGHBOR_SOLICIT_LEN as u16);
+    ipv6.set_next_header(IpNextHeaderProtocols::Icmpv6);
+    ipv6.set_hop_limit(255);
+    ipv6.set_source(source_ip);
+    ipv6.set_destination(target_ip);
+
+    let mut solicit = MutableIcmpv6Packet::new(&mut frame[ETHERNET_HEADER_LEN + IPV6_HEADER_LEN..])
+        .expect("frame fits a Neighbor Solicitation");
+    solicit.set_icmpv6_type(Icmpv6Types::NeighborSolicit);
+
+    // Reserved field, target address, then the source link-layer address
+    // option (type 1, length in units of 8 bytes).
+    let body = solicit.payload_mut();
+    body[4..20].copy_from_slice(&target_ip.octets());
+    body[20] = 1;
+    body[21] = 1;
+    body[22..28].copy_from_slice(&source_mac.octets());
+
+    let checksum = icmpv6::checksum(&solicit.to_immutable(), &source_ip, &target_ip);
+    solicit.set_checksum(checksum);
+
+    frame
+}
@@ -0,0 +1,230 @@
+use pnet::util::MacAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::lldp::{capability_names, text};
+use crate::models::{DiscoveryProtocol, LinkAnnouncement};
+
+/// Destination MAC of CDP frames.
+pub const MULTICAST_MAC: MacAddr = MacAddr(0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcc);
+
+/// 802.2 LLC/SNAP header carrying Cisco's OUI and the CDP protocol ID.
+const SNAP_HEADER: [u8; 8] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x0c, 0x20, 0x00];
+/// Version, TTL and checksum.
+const CDP_HEADER_LEN: usize = 4;
+
+const TLV_DEVICE_ID: u16 = 0x0001;
+const TLV_ADDRESSES: u16 = 0x0002;
+const TLV_PORT_ID: u16 = 0x0003;
+const TLV_CAPABILITIES: u16 = 0x0004;
+const TLV_SOFTWARE_VERSION: u16 = 0x0005;
+const TLV_PLATFORM: u16 = 0x0006;
+const TLV_MANAGEMENT_ADDRESSES: u16 = 0x0016;
+
+/// CDP capability bits mapped onto LLDP capabilities; the bridge and switch
+/// bits are all `bridge`. Bits without an LLDP counterpart are skipped.
+const CAPABILITIES: [&str; 8] = [
+    "router",
+    "bridge",
+    "bridge",
+    "bridge",
+    "station_only",
+    "",
+    "repeater",
+    "telephone",
+];
+
+/// Parses the payload of an 802.3 frame sent to [`MULTICAST_MAC`], starting
+/// at the LLC header. Returns `None` for anything but CDP.
+pub fn parse(payload: &[u8]) -> Option<LinkAnnouncement> {
+    let cdp = payload.strip_prefix(&SNAP_HEADER[..])?;
+    // A TTL of zero withdraws the sender's information.
+    if cdp.len() < CDP_HEADER_LEN || cdp[1] == 0 {
+        return None;
+    }
+
+    let mut announcement = LinkAnnouncement {
+        protocol: DiscoveryProtocol::Cdp,
+        chassis_id: None,
+        system_name: None,
+        system_description: None,
+        platform: None,
+        capabilities: Vec::new(),
+        management_address: None,
+        port_id: None,
+        port_description: None,
+    };
+    let mut addresses = None;
+    let mut management_addresses = None;
+    let mut offset = CDP_HEADER_LEN;
+
+    while offset + 4 <= cdp.len() {
+        let tlv_type = u16::from_be_bytes([cdp[offset], cdp[offset + 1]]);
+        // The length includes the four header bytes.
+        let len = u16::from_be_bytes([cdp[offset + 2], cdp[offset + 3]]) as usize;
+        let value = cdp.get(offset + 4..offset + len.max(4))?;
+        offset += len.max(4);
+
+        match tlv_type {
+            TLV_DEVICE_ID => announcement.system_name = text(value),
+            TLV_PORT_ID => announcement.port_id = text(value),
+            TLV_SOFTWARE_VERSION => announcement.system_description = text(value),
+            TLV_PLATFORM => announcement.platform = text(value),
+            TLV_CAPABILITIES => {
+                let bits = u32::from_be_bytes(value.get(..4)?.try_into().ok()?);
+                announcement.capabilities.clear();
+                for name in capability_names(bits, &CAPABILITIES) {
+                    if !name.is_empty() && !announcement.capabilities.contains(&name) {
+                        announcement.capabilities.push(name);
+                    }
+                }
+            }
+            TLV_ADDRESSES => addresses = first_address(value),
+            TLV_MANAGEMENT_ADDRESSES => management_addresses = first_address(value),
+            _ => {}
+        }
+    }
+
+    // The device ID is the one mandatory TLV.
+    announcement.system_name.as_ref()?;
+    announcement.management_address = management_addresses
+        .or(addresses)
+        .map(|address| address.to_string());
+
+    Some(announcement)
+}
+
+/// The first IPv4 or IPv6 address of an address list TLV: a count, then for
+/// each address its protocol type and protocol, and the address itself.
+fn first_address(value: &[u8]) -> Option<IpAddr> {
+    let count = u32::from_be_bytes(value.get(..4)?.try_into().ok()?);
+    let mut offset = 4;
+
+    for _ in 0..count {
+        let protocol_len = *value.get(offset + 1)? as usize;
+        let protocol = value.get(offset + 2..offset + 2 + protocol_len)?;
+        offset += 2 + protocol_len;
+        let address_len = u16::from_be_bytes(value.get(offset..offset + 2)?.try_into().ok()?);
+        let address = value.get(offset + 2..offset + 2 + address_len as usize)?;
+        offset += 2 + address_len as usize;
+
+        match (protocol, address.len()) {
+            // NLPID for IP.
+            ([0xcc], 4) => {
+                return Some(IpAddr::V4(Ipv4Addr::from(
+                    <[u8; 4]>::try_from(address).ok()?,
+                )));
+            }
+            // 802.2 SNAP header with the IPv6 ethertype.
+            ([.., 0x86, 0xdd], 16) => {
+                return Some(IpAddr::V6(Ipv6Addr::from(
+                    <[u8; 16]>::try_from(address).ok()?,
+                )));
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::frames::cdp_tlv;
+
+    /// An address list TLV value holding one IPv4 address.
+    fn ipv4_addresses(address: [u8; 4]) -> Vec<u8> {
+        [&[0, 0, 0, 1, 1, 1, 0xcc, 0, 4][..], &address].concat()
+    }
+
+    /// A CDPv2 frame, from the LLC header on, with the given TTL and TLVs.
+    fn frame(ttl: u8, tlvs: &[Vec<u8>]) -> Vec<u8> {
+        [&SNAP_HEADER[..], &[2, ttl, 0x7e, 0x41], &tlvs.concat()].concat()
+    }
+
+    #[test]
+    fn reads_switch_announcement() {
+        let payload = frame(
+            180,
+            &[
+                cdp_tlv(TLV_DEVICE_ID, b"sw-access-02.example.com"),
+                cdp_tlv(TLV_ADDRESSES, &ipv4_addresses([10, 0, 0, 2])),
+                cdp_tlv(TLV_PORT_ID, b"GigabitEthernet1/0/7"),
+                // Switch and IGMP.
+                cdp_tlv(TLV_CAPABILITIES, &[0, 0, 0, 0x28]),
+                cdp_tlv(
+                    TLV_SOFTWARE_VERSION,
+                    b"Cisco IOS Software, C2960X Software (C2960X-UNIVERSALK9-M), Version 15.2(7)E4",
+                ),
+                cdp_tlv(TLV_PLATFORM, b"cisco WS-C2960X-48FPD-L"),
+                cdp_tlv(TLV_MANAGEMENT_ADDRESSES, &ipv4_addresses([10, 0, 99, 2])),
+            ],
+        );
+
+        assert_eq!(
+            parse(&payload),
+            Some(LinkAnnouncement {
+                protocol: DiscoveryProtocol::Cdp,
+                chassis_id: None,
+                system_name: Some("sw-access-02.example.com".into()),
+                system_description: Some(
+                    "Cisco IOS Software, C2960X Software (C2960X-UNIVERSALK9-M), Version 15.2(7)E4"
+                        .into()
+                ),
+                platform: Some("cisco WS-C2960X-48FPD-L".into()),
+                capabilities: vec!["bridge".into()],
+                management_address: Some("10.0.99.2".into()),
+                port_id: Some("GigabitEthernet1/0/7".into()),
+                port_description: None,
+            })
+        );
+    }
+
+    #[test]
+    fn reads_phone_announcement() {
+        let ipv6_addresses = [
+            &[
+                0, 0, 0, 1, 2, 8, 0xaa, 0xaa, 0x03, 0, 0, 0, 0x86, 0xdd, 0, 16,
+            ][..],
+            &[
+                0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0x30, 0, 0, 0, 0, 0, 0, 0, 0x15,
+            ],
+        ]
+        .concat();
+        let payload = frame(
+            180,
+            &[
+                cdp_tlv(TLV_DEVICE_ID, b"SEP00562B3C4D5E"),
+                cdp_tlv(TLV_ADDRESSES, &ipv6_addresses),
+                cdp_tlv(TLV_PORT_ID, b"Port 1"),
+                // Host and phone.
+                cdp_tlv(TLV_CAPABILITIES, &[0, 0, 0, 0x90]),
+                cdp_tlv(TLV_PLATFORM, b"Cisco IP Phone 8845"),
+            ],
+        );
+
+        let announcement = parse(&payload).unwrap();
+        assert_eq!(
+            announcement.management_address.as_deref(),
+            Some("2001:db8:0:30::15")
+        );
+        assert_eq!(announcement.capabilities, vec!["station_only", "telephone"]);
+        assert_eq!(announcement.device_type(), Some("voip_phone"));
+    }
+
+    #[test]
+    fn ignores_withdrawals_and_other_protocols() {
+        let device_id = [cdp_tlv(TLV_DEVICE_ID, b"sw-access-02.example.com")];
+        assert!(parse(&frame(180, &device_id)).is_some());
+        assert_eq!(parse(&frame(0, &device_id)), None);
+        assert_eq!(
+            parse(&frame(180, &[cdp_tlv(TLV_PORT_ID, b"Gi1/0/7")])),
+            None
+        );
+
+        // VTP, also sent to the CDP multicast address.
+        let mut vtp = frame(180, &device_id);
+        vtp[6..8].copy_from_slice(&[0x20, 0x03]);
+        assert_eq!(parse(&vtp), None);
+    }
+}
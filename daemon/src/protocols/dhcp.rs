@@ -0,0 +1,281 @@
+use pnet::packet::Packet;
+use pnet::packet::dhcp::{DhcpOperations, DhcpPacket};
+use std::net::Ipv4Addr;
+
+use crate::models::DhcpMessageType;
+
+pub const SERVER_PORT: u16 = 67;
+pub const CLIENT_PORT: u16 = 68;
+
+pub(crate) const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+
+const OPT_PAD: u8 = 0;
+const OPT_HOSTNAME: u8 = 12;
+const OPT_REQUESTED_IP: u8 = 50;
+const OPT_LEASE_TIME: u8 = 51;
+const OPT_MESSAGE_TYPE: u8 = 53;
+const OPT_SERVER_ID: u8 = 54;
+const OPT_PARAMETER_REQUEST_LIST: u8 = 55;
+const OPT_RENEWAL_TIME: u8 = 58;
+const OPT_REBINDING_TIME: u8 = 59;
+const OPT_VENDOR_CLASS: u8 = 60;
+const OPT_CLIENT_ID: u8 = 61;
+const OPT_END: u8 = 255;
+
+/// A decoded DHCPv4 message with the options foxd cares about.
+#[derive(Debug, Clone)]
+pub struct DhcpMessage {
+    pub is_client_message: bool,
+    pub message_type: DhcpMessageType,
+    pub client_mac: String,
+    pub client_ip: Option<Ipv4Addr>,
+    pub requested_ip: Option<Ipv4Addr>,
+    /// Address the server offers or assigns (`yiaddr`).
+    pub your_ip: Option<Ipv4Addr>,
+    /// Server identifier (option 54), the server's own address.
+    pub server_id: Option<Ipv4Addr>,
+    /// Address of the relay agent that forwarded the message (`giaddr`).
+    pub relay_agent: Option<Ipv4Addr>,
+    /// Lease time in seconds (option 51); `u32::MAX` means infinite.
+    pub lease_time: Option<u32>,
+    /// Renewal (T1, option 58) and rebinding (T2, option 59) times in seconds.
+    pub renewal_time: Option<u32>,
+    pub rebinding_time: Option<u32>,
+    pub hostname: Option<String>,
+    pub client_id: Option<String>,
+    pub vendor_class: Option<String>,
+    /// Option codes the client asked for, in the order it listed them.
+    pub parameter_request_list: Option<Vec<u8>>,
+}
+
+/// Parses the UDP payload of a BOOTP/DHCP packet. Returns `None` for plain
+/// BOOTP traffic or anything that is not a well-formed DHCP message.
+pub fn parse(payload: &[u8]) -> Option<DhcpMessage> {
+    let packet = DhcpPacket::new(payload)?;
+
+    let options = packet.payload();
+    if options.len() < MAGIC_COOKIE.len() || options[..4] != MAGIC_COOKIE {
+        return None;
+    }
+
+    let mut message_type = None;
+    let mut requested_ip = None;
+    let mut server_id = None;
+    let mut lease_time = None;
+    let mut renewal_time = None;
+    let mut rebinding_time = None;
+    let mut hostname = None;
+    let mut client_id = None;
+    let mut vendor_class = None;
+    let mut parameter_request_list = None;
+
+    for (code, value) in iter_options(&options[4..]) {
+        match code {
+            OPT_MESSAGE_TYPE if value.len() == 1 => {
+                message_type = message_type_from_code(value[0]);
+            }
+            OPT_REQUESTED_IP if value.len() == 4 => {
+                requested_ip = Some(Ipv4Addr::new(value[0], value[1], value[2], value[3]));
+            }
+            OPT_SERVER_ID if value.len() == 4 => {
+                server_id = Some(Ipv4Addr::new(value[0], value[1], value[2], value[3]));
+            }
+            OPT_LEASE_TIME => lease_time = option_u32(value),
+            OPT_RENEWAL_TIME => renewal_time = option_u32(value),
+            OPT_REBINDING_TIME => rebinding_time = option_u32(value),
+            OPT_HOSTNAME => hostname = option_string(value),
+            OPT_VENDOR_CLASS => vendor_class = option_string(value),
+            OPT_CLIENT_ID if !value.is_empty() => client_id = Some(hex_string(value)),
+            OPT_PARAMETER_REQUEST_LIST if !value.is_empty() => {
+                parameter_request_list = Some(value.to_vec());
+            }
+            _ => {}
+        }
+    }
+
+    let ciaddr = packet.get_ciaddr();
+    let yiaddr = packet.get_yiaddr();
+    let giaddr = packet.get_giaddr();
+
+    Some(DhcpMessage {
+        is_client_message: packet.get_op() == DhcpOperations::Request,
+        message_type: message_type?,
+        client_mac: packet.get_chaddr().to_string(),
+        client_ip: (!ciaddr.is_unspecified()).then_some(ciaddr),
+        requested_ip,
+        your_ip: (!yiaddr.is_unspecified()).then_some(yiaddr),
+        server_id,
+        relay_agent: (!giaddr.is_unspecified()).then_some(giaddr),
+        lease_time,
+        renewal_time,
+        rebinding_time,
+        hostname,
+        client_id,
+        vendor_class,
+        parameter_request_list,
+    })
+}
+
+/// Walks the TLV-encoded options area, stopping at the END option or at the
+/// first truncated option.
+fn iter_options(mut data: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    std::iter::from_fn(move || {
+        loop {
+            let (&code, rest) = data.split_first()?;
+            match code {
+                OPT_PAD => data = rest,
+                OPT_END => return None,
+                _ => {
+                    let (&len, rest) = rest.split_first()?;
+                    let len = len as usize;
+                    if rest.len() < len {
+                        return None;
+                    }
+                    let (value, rest) = rest.split_at(len);
+                    data = rest;
+                    return Some((code, value));
+                }
+            }
+        }
+    })
+}
+
+fn message_type_from_code(code: u8) -> Option<DhcpMessageType> {
+    match code {
+        1 => Some(DhcpMessageType::Discover),
+        2 => Some(DhcpMessageType::Offer),
+        3 => Some(DhcpMessageType::Request),
+        4 => Some(DhcpMessageType::Decline),
+        5 => Some(DhcpMessageType::Ack),
+        6 => Some(DhcpMessageType::Nak),
+        7 => Some(DhcpMessageType::Release),
+        8 => Some(DhcpMessageType::Inform),
+        _ => None,
+    }
+}
+
+fn option_u32(value: &[u8]) -> Option<u32> {
+    Some(u32::from_be_bytes(value.try_into().ok()?))
+}
+
+fn option_string(value: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(value);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn hex_string(value: &[u8]) -> String {
+    value
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::frames::bootp;
+    use pnet::util::MacAddr;
+
+    const IPHONE: MacAddr = MacAddr(0x8c, 0x85, 0x90, 0x1a, 0x2b, 0x3c);
+    const UNSPECIFIED: Ipv4Addr = Ipv4Addr::UNSPECIFIED;
+
+    #[test]
+    fn parses_request_options() {
+        let options = [
+            53, 1, 3, // DHCPREQUEST
+            55, 9, 1, 121, 3, 6, 15, 108, 114, 119, 252, // parameter request list
+            57, 2, 0x05, 0xdc, // maximum message size
+            61, 7, 1, 0x8c, 0x85, 0x90, 0x1a, 0x2b, 0x3c, // client identifier
+            50, 4, 192, 168, 1, 23, // requested address
+            51, 4, 0, 0x76, 0xa7, 0x00, // lease time
+            12, 6, b'i', b'P', b'h', b'o', b'n', b'e', // hostname
+            0, 0, // padding
+            255,
+        ];
+        let packet = bootp(1, IPHONE, [UNSPECIFIED; 4], &options);
+
+        let message = parse(&packet).unwrap();
+        assert!(message.is_client_message);
+        assert_eq!(message.message_type, DhcpMessageType::Request);
+        assert_eq!(message.client_mac, "8c:85:90:1a:2b:3c");
+        assert_eq!(message.requested_ip, Some(Ipv4Addr::new(192, 168, 1, 23)));
+        assert_eq!(message.client_ip, None);
+        assert_eq!(message.relay_agent, None);
+        assert_eq!(message.lease_time, Some(7_776_000));
+        assert_eq!(message.hostname.as_deref(), Some("iPhone"));
+        assert_eq!(message.client_id.as_deref(), Some("01:8c:85:90:1a:2b:3c"));
+        assert_eq!(
+            message.parameter_request_list,
+            Some(vec![1, 121, 3, 6, 15, 108, 114, 119, 252])
+        );
+        assert_eq!(message.vendor_class, None);
+    }
+
+    #[test]
+    fn parses_server_ack() {
+        let options = [
+            53, 1, 5, // DHCPACK
+            54, 4, 192, 168, 1, 1, // server identifier
+            51, 4, 0, 0, 0x0e, 0x10, // lease time
+            58, 4, 0, 0, 0x07, 0x08, // renewal time
+            59, 4, 0, 0, 0x0c, 0x4e, // rebinding time
+            1, 4, 255, 255, 255, 0, // subnet mask
+            255,
+        ];
+        let addresses = [
+            UNSPECIFIED,
+            Ipv4Addr::new(192, 168, 1, 23),
+            UNSPECIFIED,
+            UNSPECIFIED,
+        ];
+        let packet = bootp(2, IPHONE, addresses, &options);
+
+        let message = parse(&packet).unwrap();
+        assert!(!message.is_client_message);
+        assert_eq!(message.message_type, DhcpMessageType::Ack);
+        assert_eq!(message.your_ip, Some(Ipv4Addr::new(192, 168, 1, 23)));
+        assert_eq!(message.server_id, Some(Ipv4Addr::new(192, 168, 1, 1)));
+        assert_eq!(message.lease_time, Some(3600));
+        assert_eq!(message.renewal_time, Some(1800));
+        assert_eq!(message.rebinding_time, Some(3150));
+    }
+
+    #[test]
+    fn records_relay_agent_and_vendor_class() {
+        let options = [
+            53, 1, 1, // DHCPDISCOVER
+            60, 8, b'M', b'S', b'F', b'T', b' ', b'5', b'.', b'0', // vendor class
+            255,
+        ];
+        let addresses = [
+            UNSPECIFIED,
+            UNSPECIFIED,
+            UNSPECIFIED,
+            Ipv4Addr::new(10, 0, 20, 1),
+        ];
+        let packet = bootp(1, IPHONE, addresses, &options);
+
+        let message = parse(&packet).unwrap();
+        assert_eq!(message.message_type, DhcpMessageType::Discover);
+        assert_eq!(message.relay_agent, Some(Ipv4Addr::new(10, 0, 20, 1)));
+        assert_eq!(message.vendor_class.as_deref(), Some("MSFT 5.0"));
+        assert_eq!(message.parameter_request_list, None);
+    }
+
+    #[test]
+    fn rejects_bootp_and_truncated_messages() {
+        let mut bootp_only = bootp(1, IPHONE, [UNSPECIFIED; 4], &[255]);
+        assert!(parse(&bootp_only).is_none(), "no message type");
+
+        bootp_only.truncate(236);
+        assert!(parse(&bootp_only).is_none(), "no magic cookie");
+
+        // The message type option runs past the end of the packet.
+        let truncated = bootp(1, IPHONE, [UNSPECIFIED; 4], &[53, 4, 1]);
+        assert!(parse(&truncated).is_none());
+
+        assert!(parse(&[1, 1, 6, 0]).is_none());
+    }
+}
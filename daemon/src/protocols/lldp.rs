@@ -0,0 +1,248 @@
+use pnet::util::MacAddr;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::models::{DiscoveryProtocol, LinkAnnouncement};
+
+const TLV_END: u8 = 0;
+const TLV_CHASSIS_ID: u8 = 1;
+const TLV_PORT_ID: u8 = 2;
+const TLV_TTL: u8 = 3;
+const TLV_PORT_DESCRIPTION: u8 = 4;
+const TLV_SYSTEM_NAME: u8 = 5;
+const TLV_SYSTEM_DESCRIPTION: u8 = 6;
+const TLV_SYSTEM_CAPABILITIES: u8 = 7;
+const TLV_MANAGEMENT_ADDRESS: u8 = 8;
+
+/// Chassis ID subtype for a MAC address.
+const CHASSIS_MAC: u8 = 4;
+/// Chassis ID subtype for a management address.
+const CHASSIS_NETWORK_ADDRESS: u8 = 5;
+/// Port ID subtype for a MAC address.
+const PORT_MAC: u8 = 3;
+/// Port ID subtype for a management address.
+const PORT_NETWORK_ADDRESS: u8 = 4;
+
+/// IANA address family numbers used in chassis IDs, port IDs and management
+/// addresses.
+const FAMILY_IPV4: u8 = 1;
+const FAMILY_IPV6: u8 = 2;
+
+/// System capability bits (IEEE 802.1AB §8.5.8), in LLDP terms.
+pub const CAPABILITIES: [&str; 11] = [
+    "other",
+    "repeater",
+    "bridge",
+    "wlan_access_point",
+    "router",
+    "telephone",
+    "docsis_cable_device",
+    "station_only",
+    "c_vlan",
+    "s_vlan",
+    "tpmr",
+];
+
+/// A decoded LLDPDU.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lldpdu {
+    pub announcement: LinkAnnouncement,
+    /// The chassis ID, when it is a MAC address.
+    pub chassis_mac: Option<MacAddr>,
+}
+
+/// Parses the payload of an LLDP frame (ethertype 0x88cc). A shutdown
+/// LLDPDU, with a TTL of zero, withdraws the sender's information and yields
+/// `None`, as do frames missing the mandatory TLVs.
+pub fn parse(payload: &[u8]) -> Option<Lldpdu> {
+    let mut announcement = LinkAnnouncement {
+        protocol: DiscoveryProtocol::Lldp,
+        chassis_id: None,
+        system_name: None,
+        system_description: None,
+        platform: None,
+        capabilities: Vec::new(),
+        management_address: None,
+        port_id: None,
+        port_description: None,
+    };
+    let mut chassis_mac = None;
+    let mut ttl = None;
+    let mut offset = 0;
+
+    while offset + 2 <= payload.len() {
+        let header = u16::from_be_bytes([payload[offset], payload[offset + 1]]);
+        let tlv_type = (header >> 9) as u8;
+        let len = (header & 0x01ff) as usize;
+        let value = payload.get(offset + 2..offset + 2 + len)?;
+        offset += 2 + len;
+
+        match tlv_type {
+            TLV_END => break,
+            TLV_CHASSIS_ID => {
+                let (&subtype, id) = value.split_first()?;
+                if subtype == CHASSIS_MAC {
+                    chassis_mac = mac_address(id);
+                }
+                announcement.chassis_id = match subtype {
+                    CHASSIS_MAC => chassis_mac.map(|mac| mac.to_string()),
+                    CHASSIS_NETWORK_ADDRESS => network_address(id),
+                    _ => text(id),
+                };
+            }
+            TLV_PORT_ID => {
+                let (&subtype, id) = value.split_first()?;
+                announcement.port_id = match subtype {
+                    PORT_MAC => mac_address(id).map(|mac| mac.to_string()),
+                    PORT_NETWORK_ADDRESS => network_address(id),
+                    _ => text(id),
+                };
+            }
+            TLV_TTL => ttl = Some(u16::from_be_bytes(value.get(..2)?.try_into().ok()?)),
+            TLV_PORT_DESCRIPTION => announcement.port_description = text(value),
+            TLV_SYSTEM_NAME => announcement.system_name = text(value),
+            TLV_SYSTEM_DESCRIPTION => announcement.system_description = text(value),
+            TLV_SYSTEM_CAPABILITIES if value.len() >= 4 => {
+                let system = u16::from_be_bytes([value[0], value[1]]);
+                let enabled = u16::from_be_bytes([value[2], value[3]]);
+                // Some devices leave the enabled set empty.
+                let bits = if enabled != 0 { enabled } else { system };
+                announcement.capabilities = capability_names(bits as u32, &CAPABILITIES);
+            }
+            TLV_MANAGEMENT_ADDRESS if announcement.management_address.is_none() => {
+                // Address string length (subtype included), then the address.
+                let len = *value.first()? as usize;
+                announcement.management_address = network_address(value.get(1..1 + len)?);
+            }
+            _ => {}
+        }
+    }
+
+    if ttl? == 0 || announcement.chassis_id.is_none() {
+        return None;
+    }
+
+    Some(Lldpdu {
+        announcement,
+        chassis_mac,
+    })
+}
+
+/// Names of the bits set in `bits`, bit 0 first.
+pub fn capability_names(bits: u32, names: &[&str]) -> Vec<String> {
+    names
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| bits & (1 << bit) != 0)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+fn mac_address(bytes: &[u8]) -> Option<MacAddr> {
+    let [a, b, c, d, e, f] = bytes.try_into().ok()?;
+    Some(MacAddr::new(a, b, c, d, e, f))
+}
+
+/// An address prefixed with its IANA address family.
+fn network_address(bytes: &[u8]) -> Option<String> {
+    let (&family, address) = bytes.split_first()?;
+    match family {
+        FAMILY_IPV4 => Some(Ipv4Addr::from(<[u8; 4]>::try_from(address).ok()?).to_string()),
+        FAMILY_IPV6 => Some(Ipv6Addr::from(<[u8; 16]>::try_from(address).ok()?).to_string()),
+        _ => None,
+    }
+}
+
+/// A string TLV, trimmed; devices pad them with spaces or NULs.
+pub fn text(bytes: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(bytes);
+    let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::frames::lldp_tlv;
+
+    /// An LLDPDU as sent by an access switch port.
+    fn switch_lldpdu(ttl: u16) -> Vec<u8> {
+        [
+            lldp_tlv(TLV_CHASSIS_ID, &[4, 0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x00]),
+            lldp_tlv(TLV_PORT_ID, b"\x05gi1/0/12"),
+            lldp_tlv(TLV_TTL, &ttl.to_be_bytes()),
+            lldp_tlv(TLV_PORT_DESCRIPTION, b"Office uplink\0"),
+            lldp_tlv(TLV_SYSTEM_NAME, b"sw-core-01"),
+            lldp_tlv(
+                TLV_SYSTEM_DESCRIPTION,
+                b"ProCurve J9773A Switch 2530-24G-PoEP",
+            ),
+            // Bridge and router available, bridge enabled.
+            lldp_tlv(TLV_SYSTEM_CAPABILITIES, &[0x00, 0x14, 0x00, 0x04]),
+            lldp_tlv(
+                TLV_MANAGEMENT_ADDRESS,
+                &[5, 1, 192, 168, 1, 2, 2, 0, 0, 0, 1, 0],
+            ),
+            lldp_tlv(TLV_END, &[]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn reads_switch_announcement() {
+        let lldpdu = parse(&switch_lldpdu(120)).unwrap();
+
+        assert_eq!(
+            lldpdu.chassis_mac,
+            Some(MacAddr::new(0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x00))
+        );
+        assert_eq!(
+            lldpdu.announcement,
+            LinkAnnouncement {
+                protocol: DiscoveryProtocol::Lldp,
+                chassis_id: Some("00:1b:21:3c:4d:00".into()),
+                system_name: Some("sw-core-01".into()),
+                system_description: Some("ProCurve J9773A Switch 2530-24G-PoEP".into()),
+                platform: None,
+                capabilities: vec!["bridge".into()],
+                management_address: Some("192.168.1.2".into()),
+                port_id: Some("gi1/0/12".into()),
+                port_description: Some("Office uplink".into()),
+            }
+        );
+        assert_eq!(lldpdu.announcement.device_type(), Some("switch"));
+    }
+
+    #[test]
+    fn reads_phone_with_empty_enabled_capabilities() {
+        let lldpdu = [
+            lldp_tlv(TLV_CHASSIS_ID, &[5, 1, 10, 0, 30, 15]),
+            lldp_tlv(TLV_PORT_ID, &[3, 0x80, 0x5e, 0xc0, 0x12, 0x34, 0x56]),
+            lldp_tlv(TLV_TTL, &[0, 120]),
+            // Bridge and telephone available, none reported enabled.
+            lldp_tlv(TLV_SYSTEM_CAPABILITIES, &[0x00, 0x24, 0x00, 0x00]),
+        ]
+        .concat();
+
+        let announcement = parse(&lldpdu).unwrap().announcement;
+        assert_eq!(announcement.chassis_id.as_deref(), Some("10.0.30.15"));
+        assert_eq!(announcement.port_id.as_deref(), Some("80:5e:c0:12:34:56"));
+        assert_eq!(announcement.capabilities, vec!["bridge", "telephone"]);
+        assert_eq!(announcement.device_type(), Some("voip_phone"));
+    }
+
+    #[test]
+    fn ignores_shutdown_and_incomplete_lldpdus() {
+        assert_eq!(parse(&switch_lldpdu(0)), None);
+
+        let without_chassis = [
+            lldp_tlv(TLV_PORT_ID, b"\x05gi1/0/12"),
+            lldp_tlv(TLV_TTL, &[0, 120]),
+        ]
+        .concat();
+        assert_eq!(parse(&without_chassis), None);
+
+        let mut truncated = switch_lldpdu(120);
+        truncated.truncate(20);
+        assert_eq!(parse(&truncated), None);
+    }
+}
@@ -0,0 +1,78 @@
+use std::net::IpAddr;
+
+use super::mdns::{self, Record};
+
+pub const PORT: u16 = 5355;
+
+/// Returns the name a host answered for in an LLMNR response. LLMNR uses
+/// the DNS message format, so the records are read with the mDNS parser;
+/// a host only answers for its own name, so any address record will do,
+/// though the one for `source_ip` is preferred.
+pub fn responder_name(payload: &[u8], source_ip: IpAddr) -> Option<String> {
+    let records = mdns::parse(payload)?;
+
+    let mut fallback = None;
+    for record in &records {
+        let (name, address) = match record {
+            Record::A { name, address } => (name, IpAddr::V4(*address)),
+            Record::Aaaa { name, address } => (name, IpAddr::V6(*address)),
+            _ => continue,
+        };
+        if address == source_ip {
+            return clean_name(name);
+        }
+        fallback = fallback.or(Some(name));
+    }
+
+    fallback.and_then(|name| clean_name(name))
+}
+
+fn clean_name(name: &str) -> Option<String> {
+    let name = name.trim_end_matches('.');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::frames;
+
+    /// A Windows host answering an LLMNR query for its own name.
+    fn response(address: &[u8]) -> Vec<u8> {
+        let record_type: u16 = if address.len() == 4 { 1 } else { 28 };
+        [
+            frames::dns_header(0x9c41, 0x8000, [1, 1, 0, 0]),
+            frames::dns_name("DESKTOP-4F2K"),
+            [record_type.to_be_bytes(), 1u16.to_be_bytes()].concat(),
+            frames::dns_record(b"\xc0\x0c", record_type, 1, 30, address),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn reads_responder_name() {
+        let ipv4 = response(&[192, 168, 1, 42]);
+        assert_eq!(
+            responder_name(&ipv4, "192.168.1.42".parse().unwrap()).as_deref(),
+            Some("DESKTOP-4F2K")
+        );
+
+        let ipv6 = response(&[
+            0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0x1c, 0x2b, 0x3a, 0xff, 0xfe, 0x49, 0x58, 0x67,
+        ]);
+        assert_eq!(
+            responder_name(&ipv6, "fe80::1c2b:3aff:fe49:5867".parse().unwrap()).as_deref(),
+            Some("DESKTOP-4F2K")
+        );
+    }
+
+    #[test]
+    fn ignores_queries() {
+        let mut query = response(&[192, 168, 1, 42]);
+        query[2] = 0x00;
+        assert_eq!(
+            responder_name(&query, "192.168.1.42".parse().unwrap()),
+            None
+        );
+    }
+}
@@ -0,0 +1,431 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::models::AdvertisedService;
+
+pub const PORT: u16 = 5353;
+
+const HEADER_LEN: usize = 12;
+const FLAG_RESPONSE: u16 = 0x8000;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+
+/// Compression pointers followed before a name is considered malformed.
+const MAX_POINTERS: usize = 16;
+
+/// A resource record from an mDNS response, limited to the types foxd uses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    A {
+        name: String,
+        address: Ipv4Addr,
+    },
+    Aaaa {
+        name: String,
+        address: Ipv6Addr,
+    },
+    Ptr {
+        name: String,
+        target: String,
+    },
+    Srv {
+        name: String,
+        target: String,
+        port: u16,
+    },
+    Txt {
+        name: String,
+        entries: Vec<String>,
+    },
+}
+
+/// What a device announced about itself in one mDNS response.
+#[derive(Debug, Clone, Default)]
+pub struct Announcement {
+    pub hostname: Option<String>,
+    pub services: Vec<AdvertisedService>,
+}
+
+/// Parses the UDP payload of an mDNS response and returns the records in its
+/// answer, authority and additional sections. Queries and malformed messages
+/// yield `None`; records being withdrawn (TTL 0) are left out.
+pub fn parse(payload: &[u8]) -> Option<Vec<Record>> {
+    if payload.len() < HEADER_LEN || read_u16(payload, 2)? & FLAG_RESPONSE == 0 {
+        return None;
+    }
+
+    let questions = read_u16(payload, 4)?;
+    let records = [6, 8, 10]
+        .iter()
+        .map(|&offset| read_u16(payload, offset).map(usize::from))
+        .sum::<Option<usize>>()?;
+
+    let mut position = HEADER_LEN;
+    for _ in 0..questions {
+        position = read_name(payload, position)?.1 + 4;
+    }
+
+    let mut parsed = Vec::new();
+    for _ in 0..records {
+        let (name, next) = read_name(payload, position)?;
+        let record_type = read_u16(payload, next)?;
+        let ttl = read_u32(payload, next + 4)?;
+        let data_len = usize::from(read_u16(payload, next + 8)?);
+        let data_start = next + 10;
+        let data = payload.get(data_start..data_start + data_len)?;
+        position = data_start + data_len;
+
+        if ttl == 0 {
+            continue;
+        }
+
+        let record = match record_type {
+            TYPE_A => <[u8; 4]>::try_from(data).ok().map(|octets| Record::A {
+                name,
+                address: Ipv4Addr::from(octets),
+            }),
+            TYPE_AAAA => <[u8; 16]>::try_from(data).ok().map(|octets| Record::Aaaa {
+                name,
+                address: Ipv6Addr::from(octets),
+            }),
+            TYPE_PTR => {
+                read_name(payload, data_start).map(|(target, _)| Record::Ptr { name, target })
+            }
+            TYPE_SRV if data.len() >= 6 => {
+                read_name(payload, data_start + 6).map(|(target, _)| Record::Srv {
+                    name,
+                    target,
+                    port: u16::from_be_bytes([data[4], data[5]]),
+                })
+            }
+            TYPE_TXT => Some(Record::Txt {
+                name,
+                entries: txt_entries(data),
+            }),
+            _ => None,
+        };
+        parsed.extend(record);
+    }
+
+    Some(parsed)
+}
+
+/// Works out the sender's hostname and the DNS-SD service instances it
+/// advertises. The hostname comes from the address record for `source_ip`,
+/// falling back to any address record or SRV target.
+///
+/// A Bonjour sleep proxy answers for the hosts sleeping behind it, from its
+/// own addresses but with the hosts' address records. A host whose only
+/// address records in the sender's address family hold other addresses is
+/// taken to be such a host: neither its name nor the services on it are
+/// credited to the sender.
+pub fn announcement(records: &[Record], source_ip: IpAddr) -> Announcement {
+    let proxied = proxied_hosts(records, source_ip);
+    let mut hostname = None;
+    let mut fallback_hostname = None;
+    let mut services: BTreeMap<String, AdvertisedService> = BTreeMap::new();
+    let mut service_hosts: HashMap<&str, &str> = HashMap::new();
+
+    for record in records {
+        match record {
+            Record::A { name, address } if IpAddr::V4(*address) == source_ip => {
+                hostname = Some(name);
+            }
+            Record::Aaaa { name, address } if IpAddr::V6(*address) == source_ip => {
+                hostname = Some(name);
+            }
+            Record::A { name, .. } | Record::Aaaa { name, .. } => {
+                if !proxied.contains(name.as_str()) {
+                    fallback_hostname = fallback_hostname.or(Some(name));
+                }
+            }
+            Record::Ptr { target, .. } => {
+                service_entry(&mut services, target);
+            }
+            Record::Srv { name, target, port } => {
+                if let Some(service) = service_entry(&mut services, name) {
+                    service.port = Some(*port);
+                }
+                service_hosts.insert(name, target);
+                if !proxied.contains(target.as_str()) {
+                    fallback_hostname = fallback_hostname.or(Some(target));
+                }
+            }
+            Record::Txt { name, entries } => {
+                if let Some(service) = service_entry(&mut services, name) {
+                    service.txt = entries.clone();
+                }
+            }
+        }
+    }
+
+    Announcement {
+        hostname: hostname
+            .or(fallback_hostname)
+            .map(|name| strip_local(name))
+            .filter(|name| !name.is_empty() && !name.ends_with(".arpa")),
+        services: services
+            .into_iter()
+            .filter(|(name, _)| {
+                service_hosts
+                    .get(name.as_str())
+                    .is_none_or(|host| !proxied.contains(host))
+            })
+            .map(|(_, service)| service)
+            .collect(),
+    }
+}
+
+/// Names of the hosts whose address records of the kind of `source_ip`
+/// all hold addresses other than `source_ip`. Hosts often send from their
+/// link-local IPv6 address while only announcing global ones, so those are
+/// compared separately.
+fn proxied_hosts(records: &[Record], source_ip: IpAddr) -> HashSet<&str> {
+    let mut hosts: HashMap<&str, bool> = HashMap::new();
+    for record in records {
+        let (name, address) = match record {
+            Record::A { name, address } => (name, IpAddr::V4(*address)),
+            Record::Aaaa { name, address } => (name, IpAddr::V6(*address)),
+            _ => continue,
+        };
+        if scope(address) != scope(source_ip) {
+            continue;
+        }
+        *hosts.entry(name).or_insert(true) &= address != source_ip;
+    }
+
+    hosts
+        .into_iter()
+        .filter(|(_, proxied)| *proxied)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// IPv4, link-local IPv6 or other IPv6.
+fn scope(address: IpAddr) -> u8 {
+    match address {
+        IpAddr::V4(_) => 4,
+        IpAddr::V6(address) if address.is_unicast_link_local() => 6,
+        IpAddr::V6(_) => 0,
+    }
+}
+
+/// Returns the service for a DNS-SD instance name such as
+/// `Living Room._airplay._tcp.local`, adding it if needed. Names that are
+/// not service instances (e.g. `_services._dns-sd._udp.local` targets) are
+/// ignored.
+fn service_entry<'a>(
+    services: &'a mut BTreeMap<String, AdvertisedService>,
+    name: &str,
+) -> Option<&'a mut AdvertisedService> {
+    let labels: Vec<&str> = name.split('.').collect();
+    let protocol = labels
+        .iter()
+        .position(|label| *label == "_tcp" || *label == "_udp")?;
+    if protocol < 2 || !labels[protocol - 1].starts_with('_') {
+        return None;
+    }
+
+    let instance = labels[..protocol - 1].join(".");
+    let service_type = format!("{}.{}", labels[protocol - 1], labels[protocol]);
+
+    Some(
+        services
+            .entry(name.to_string())
+            .or_insert_with(|| AdvertisedService {
+                service_type,
+                instance,
+                port: None,
+                txt: Vec::new(),
+            }),
+    )
+}
+
+fn strip_local(name: &str) -> String {
+    let name = name.trim_end_matches('.');
+    name.strip_suffix(".local").unwrap_or(name).to_string()
+}
+
+/// Reads a possibly compressed domain name at `position`, returning it along
+/// with the position just past it in the original byte stream.
+pub(super) fn read_name(message: &[u8], mut position: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    let mut pointers = 0;
+
+    loop {
+        let len = *message.get(position)?;
+        match len {
+            0 => {
+                end.get_or_insert(position + 1);
+                break;
+            }
+            len if len & 0xc0 == 0xc0 => {
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return None;
+                }
+                end.get_or_insert(position + 2);
+                position = usize::from(read_u16(message, position)? & 0x3fff);
+            }
+            len if len & 0xc0 == 0 => {
+                let start = position + 1;
+                let label = message.get(start..start + usize::from(len))?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                position = start + usize::from(len);
+            }
+            _ => return None,
+        }
+    }
+
+    Some((labels.join("."), end?))
+}
+
+fn txt_entries(mut data: &[u8]) -> Vec<String> {
+    let mut entries = Vec::new();
+    while let Some((&len, rest)) = data.split_first() {
+        let len = usize::from(len).min(rest.len());
+        let (entry, rest) = rest.split_at(len);
+        if !entry.is_empty() {
+            entries.push(String::from_utf8_lossy(entry).into_owned());
+        }
+        data = rest;
+    }
+    entries
+}
+
+pub(super) fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+pub(super) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::frames;
+
+    /// An Apple TV answering a query for `_airplay._tcp.local`, with its
+    /// names compressed.
+    fn apple_tv_response() -> Vec<u8> {
+        let instance = b"\xc0\x2b"; // Living Room._airplay._tcp.local
+        [
+            frames::dns_header(0, 0x8400, [0, 1, 0, 3]),
+            frames::dns_record(
+                &frames::dns_name("_airplay._tcp.local"),
+                12,
+                1,
+                4500,
+                b"\x0bLiving Room\xc0\x0c",
+            ),
+            frames::dns_record(
+                instance,
+                33,
+                0x8001,
+                120,
+                b"\0\0\0\0\x1b\x58\x0bLiving-Room\xc0\x1a",
+            ),
+            frames::dns_record(
+                instance,
+                16,
+                0x8001,
+                4500,
+                b"\x10model=AppleTV6,2\x0bsrcvers=1.0",
+            ),
+            // Living-Room.local
+            frames::dns_record(b"\xc0\x4b", 1, 0x8001, 120, &[192, 168, 1, 60]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn parses_compressed_records() {
+        let records = parse(&apple_tv_response()).unwrap();
+
+        assert_eq!(
+            records,
+            vec![
+                Record::Ptr {
+                    name: "_airplay._tcp.local".into(),
+                    target: "Living Room._airplay._tcp.local".into(),
+                },
+                Record::Srv {
+                    name: "Living Room._airplay._tcp.local".into(),
+                    target: "Living-Room.local".into(),
+                    port: 7000,
+                },
+                Record::Txt {
+                    name: "Living Room._airplay._tcp.local".into(),
+                    entries: vec!["model=AppleTV6,2".into(), "srcvers=1.0".into()],
+                },
+                Record::A {
+                    name: "Living-Room.local".into(),
+                    address: Ipv4Addr::new(192, 168, 1, 60),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn credits_the_sender() {
+        let records = parse(&apple_tv_response()).unwrap();
+        let sender = announcement(&records, "192.168.1.60".parse().unwrap());
+
+        assert_eq!(sender.hostname.as_deref(), Some("Living-Room"));
+        assert_eq!(
+            sender.services,
+            vec![AdvertisedService {
+                service_type: "_airplay._tcp".into(),
+                instance: "Living Room".into(),
+                port: Some(7000),
+                txt: vec!["model=AppleTV6,2".into(), "srcvers=1.0".into()],
+            }]
+        );
+
+        // Sent from the link-local IPv6 address, which no record lists.
+        let sender = announcement(&records, "fe80::1c2b:3aff:fe49:5867".parse().unwrap());
+        assert_eq!(sender.hostname.as_deref(), Some("Living-Room"));
+        assert_eq!(sender.services.len(), 1);
+    }
+
+    #[test]
+    fn skips_records_answered_by_a_sleep_proxy() {
+        let records = parse(&apple_tv_response()).unwrap();
+        let sender = announcement(&records, "192.168.1.2".parse().unwrap());
+
+        assert_eq!(sender.hostname, None);
+        assert!(sender.services.is_empty());
+    }
+
+    #[test]
+    fn ignores_queries_goodbyes_and_pointer_loops() {
+        let mut query = apple_tv_response();
+        query[2] = 0;
+        assert!(parse(&query).is_none());
+
+        // The same answer with a TTL of 0 withdraws the record.
+        let mut goodbye = apple_tv_response();
+        goodbye.truncate(57);
+        goodbye[11] = 0;
+        goodbye[37..41].copy_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(parse(&goodbye), Some(Vec::new()));
+
+        // A name pointing at itself.
+        let looping = [
+            frames::dns_header(0, 0x8400, [0, 1, 0, 0]),
+            frames::dns_record(b"\xc0\x0c", 1, 1, 120, &[10, 0, 0, 1]),
+        ]
+        .concat();
+        assert!(parse(&looping).is_none());
+    }
+}
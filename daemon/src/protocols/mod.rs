@@ -0,0 +1,10 @@
+pub mod cdp;
+pub mod dhcp;
+pub mod lldp;
+pub mod llmnr;
+pub mod mdns;
+pub mod nbns;
+pub mod ndp;
+pub mod ssdp;
+pub mod tcp;
+pub mod vlan;
@@ -0,0 +1,339 @@
+use std::net::Ipv4Addr;
+
+use super::mdns::{read_name, read_u16};
+
+pub const PORT: u16 = 137;
+
+const HEADER_LEN: usize = 12;
+const FLAG_RESPONSE: u16 = 0x8000;
+
+const OPCODE_QUERY: u16 = 0;
+const OPCODE_REGISTRATION: u16 = 5;
+const OPCODE_REFRESH: u16 = 8;
+const OPCODE_REFRESH_ALT: u16 = 9;
+const OPCODE_MULTIHOMED_REGISTRATION: u16 = 15;
+
+/// The requested name is already owned by another node.
+const RCODE_ACTIVE: u16 = 6;
+const RCODE_CONFLICT: u16 = 7;
+
+const TYPE_NB: u16 = 0x20;
+const TYPE_NBSTAT: u16 = 0x21;
+const CLASS_IN: u16 = 1;
+
+/// Set in NB_FLAGS and NAME_FLAGS for group (e.g. workgroup) names.
+const FLAG_GROUP: u16 = 0x8000;
+
+/// Name suffixes that identify a host: workstation and file server.
+const SUFFIX_WORKSTATION: u8 = 0x00;
+const SUFFIX_SERVER: u8 = 0x20;
+
+const NBSTAT_ENTRY_LEN: usize = 18;
+
+/// What a NetBIOS Name Service packet says about its sender.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// The sender claims `name` for `address`, in a registration, refresh
+    /// or positive query response.
+    Claim { name: String, address: Ipv4Addr },
+    /// A node status response with the sender's workstation name.
+    NodeStatus { name: String },
+    /// The sender rejected another node's registration of a name it owns.
+    Conflict { name: String },
+}
+
+/// Parses the UDP payload of an NBNS packet. Only messages that name the
+/// sender's own host are returned; group names, plain queries and other
+/// operations yield `None`.
+pub fn parse(payload: &[u8]) -> Option<Message> {
+    if payload.len() < HEADER_LEN {
+        return None;
+    }
+
+    let flags = read_u16(payload, 2)?;
+    let opcode = (flags >> 11) & 0x0f;
+    let rcode = flags & 0x0f;
+    let questions = read_u16(payload, 4)?;
+    let answers = read_u16(payload, 6)?;
+    let additional = read_u16(payload, 10)?;
+
+    if flags & FLAG_RESPONSE == 0 {
+        let registration = matches!(
+            opcode,
+            OPCODE_REGISTRATION
+                | OPCODE_REFRESH
+                | OPCODE_REFRESH_ALT
+                | OPCODE_MULTIHOMED_REGISTRATION
+        );
+        if !registration || questions != 1 || additional == 0 {
+            return None;
+        }
+
+        // The question repeats the name, the additional record carries it
+        // with the address being registered.
+        let (_, next) = read_name(payload, HEADER_LEN)?;
+        let record = read_record(payload, next + 4)?;
+        return claim(&record);
+    }
+
+    if answers == 0 {
+        return None;
+    }
+    let record = read_record(payload, HEADER_LEN)?;
+
+    match opcode {
+        OPCODE_QUERY if rcode == 0 && record.record_type == TYPE_NBSTAT => {
+            node_status_name(record.data).map(|name| Message::NodeStatus { name })
+        }
+        OPCODE_QUERY if rcode == 0 => claim(&record),
+        OPCODE_REGISTRATION if rcode == RCODE_ACTIVE || rcode == RCODE_CONFLICT => {
+            let (name, suffix) = record.name?;
+            host_suffix(suffix).then_some(Message::Conflict { name })
+        }
+        _ => None,
+    }
+}
+
+/// Builds a node status (NBSTAT) request for the wildcard name `*`, which
+/// every NetBIOS host answers with the names it has registered.
+pub fn node_status_request(transaction_id: u16) -> Vec<u8> {
+    let mut request = Vec::with_capacity(50);
+    request.extend_from_slice(&transaction_id.to_be_bytes());
+    request.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+    let mut wildcard = [0u8; 16];
+    wildcard[0] = b'*';
+    request.push(32);
+    for byte in wildcard {
+        request.push(b'A' + (byte >> 4));
+        request.push(b'A' + (byte & 0x0f));
+    }
+    request.push(0);
+
+    request.extend_from_slice(&TYPE_NBSTAT.to_be_bytes());
+    request.extend_from_slice(&CLASS_IN.to_be_bytes());
+    request
+}
+
+struct ResourceRecord<'a> {
+    /// Decoded NetBIOS name and its suffix byte.
+    name: Option<(String, u8)>,
+    record_type: u16,
+    data: &'a [u8],
+}
+
+fn read_record(payload: &[u8], position: usize) -> Option<ResourceRecord<'_>> {
+    let (encoded, next) = read_name(payload, position)?;
+    let record_type = read_u16(payload, next)?;
+    let data_len = usize::from(read_u16(payload, next + 8)?);
+    let data_start = next + 10;
+
+    Some(ResourceRecord {
+        name: decode_name(&encoded),
+        record_type,
+        data: payload.get(data_start..data_start + data_len)?,
+    })
+}
+
+fn claim(record: &ResourceRecord) -> Option<Message> {
+    if record.record_type != TYPE_NB || record.data.len() < 6 {
+        return None;
+    }
+
+    let (name, suffix) = record.name.clone()?;
+    let nb_flags = u16::from_be_bytes([record.data[0], record.data[1]]);
+    if nb_flags & FLAG_GROUP != 0 || !host_suffix(suffix) {
+        return None;
+    }
+
+    let address = Ipv4Addr::new(
+        record.data[2],
+        record.data[3],
+        record.data[4],
+        record.data[5],
+    );
+    Some(Message::Claim { name, address })
+}
+
+/// Picks the unique workstation name out of a node status name table.
+fn node_status_name(data: &[u8]) -> Option<String> {
+    let (&count, entries) = data.split_first()?;
+
+    entries
+        .chunks_exact(NBSTAT_ENTRY_LEN)
+        .take(usize::from(count))
+        .find_map(|entry| {
+            let name_flags = u16::from_be_bytes([entry[16], entry[17]]);
+            if entry[15] != SUFFIX_WORKSTATION || name_flags & FLAG_GROUP != 0 {
+                return None;
+            }
+            clean_name(&entry[..15])
+        })
+}
+
+fn host_suffix(suffix: u8) -> bool {
+    suffix == SUFFIX_WORKSTATION || suffix == SUFFIX_SERVER
+}
+
+/// Reverses the first-level encoding of RFC 1001, where each byte of the
+/// 16-byte name becomes two letters `A`-`P`. Any scope after the first
+/// label is dropped.
+fn decode_name(encoded: &str) -> Option<(String, u8)> {
+    let label = encoded.split('.').next()?.as_bytes();
+    if label.len() != 32 {
+        return None;
+    }
+
+    let mut raw = [0u8; 16];
+    for (byte, pair) in raw.iter_mut().zip(label.chunks_exact(2)) {
+        let high = pair[0].checked_sub(b'A').filter(|n| *n < 16)?;
+        let low = pair[1].checked_sub(b'A').filter(|n| *n < 16)?;
+        *byte = (high << 4) | low;
+    }
+
+    Some((clean_name(&raw[..15])?, raw[15]))
+}
+
+/// Trims the space padding of a 15-byte NetBIOS name. Names with control
+/// characters, such as `\x01\x02__MSBROWSE__\x02`, are not host names.
+fn clean_name(raw: &[u8]) -> Option<String> {
+    let name = std::str::from_utf8(raw).ok()?.trim_end_matches([' ', '\0']);
+    if name.is_empty() || name.chars().any(|c| c.is_control()) {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::frames;
+
+    /// A name registration as Windows broadcasts it when joining the network.
+    fn registration(name: &str, suffix: u8, nb_flags: u16) -> Vec<u8> {
+        let address = [&nb_flags.to_be_bytes()[..], &[192, 168, 1, 42]].concat();
+        [
+            frames::dns_header(0x8001, 0x2910, [1, 0, 0, 1]),
+            frames::netbios_name(name, suffix),
+            vec![0, 0x20, 0, 1],
+            frames::dns_record(b"\xc0\x0c", 0x20, 1, 300_000, &address),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn reads_name_registration() {
+        assert_eq!(
+            parse(&registration("DESKTOP-4F2K", 0x00, 0x0000)),
+            Some(Message::Claim {
+                name: "DESKTOP-4F2K".into(),
+                address: Ipv4Addr::new(192, 168, 1, 42),
+            })
+        );
+        assert_eq!(
+            parse(&registration("NAS", 0x20, 0x6000)),
+            Some(Message::Claim {
+                name: "NAS".into(),
+                address: Ipv4Addr::new(192, 168, 1, 42),
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_group_and_service_names() {
+        assert_eq!(parse(&registration("WORKGROUP", 0x00, 0x8000)), None);
+        // Messenger service.
+        assert_eq!(parse(&registration("DESKTOP-4F2K", 0x03, 0x0000)), None);
+
+        // The same name in a plain query.
+        let mut query = registration("DESKTOP-4F2K", 0x00, 0x0000);
+        query[2] = 0x01;
+        query[3] = 0x10;
+        assert_eq!(parse(&query), None);
+    }
+
+    #[test]
+    fn reads_positive_query_response() {
+        let response = [
+            frames::dns_header(0x1234, 0x8500, [0, 1, 0, 0]),
+            frames::dns_record(
+                &frames::netbios_name("PRINTSRV", 0x20),
+                0x20,
+                1,
+                300_000,
+                &[0, 0, 10, 0, 0, 5],
+            ),
+        ]
+        .concat();
+
+        assert_eq!(
+            parse(&response),
+            Some(Message::Claim {
+                name: "PRINTSRV".into(),
+                address: Ipv4Addr::new(10, 0, 0, 5),
+            })
+        );
+    }
+
+    #[test]
+    fn reads_node_status_response() {
+        let mut table = vec![3];
+        for (name, suffix, flags) in [
+            (b"WORKGROUP      ", 0x00, [0x84, 0x00]),
+            (b"DESKTOP-4F2K   ", 0x00, [0x04, 0x00]),
+            (b"DESKTOP-4F2K   ", 0x20, [0x04, 0x00]),
+        ] {
+            table.extend_from_slice(name);
+            table.push(suffix);
+            table.extend_from_slice(&flags);
+        }
+        table.extend_from_slice(&[0x00, 0x15, 0x5d, 0x01, 0x02, 0x03]);
+        table.extend_from_slice(&[0; 40]);
+
+        let response = [
+            frames::dns_header(0xbeef, 0x8400, [0, 1, 0, 0]),
+            frames::dns_record(&frames::netbios_name("*", 0x00), 0x21, 1, 0, &table),
+        ]
+        .concat();
+
+        assert_eq!(
+            parse(&response),
+            Some(Message::NodeStatus {
+                name: "DESKTOP-4F2K".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn reads_registration_conflict() {
+        let response = [
+            frames::dns_header(0x8001, 0xad06, [0, 1, 0, 0]),
+            frames::dns_record(
+                &frames::netbios_name("DESKTOP-4F2K", 0x00),
+                0x20,
+                1,
+                0,
+                &[0, 0, 192, 168, 1, 42],
+            ),
+        ]
+        .concat();
+
+        assert_eq!(
+            parse(&response),
+            Some(Message::Conflict {
+                name: "DESKTOP-4F2K".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn builds_node_status_request() {
+        let request = node_status_request(0xbeef);
+
+        assert_eq!(request.len(), 50);
+        assert_eq!(&request[..12], &[0xbe, 0xef, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&request[12..15], b"\x20CK");
+        assert!(request[15..45].iter().all(|&byte| byte == b'A'));
+        assert_eq!(&request[45..], &[0, 0, 0x21, 0, 1]);
+    }
+}
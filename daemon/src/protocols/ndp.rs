@@ -0,0 +1,87 @@
+use pnet::packet::icmpv6::ndp::{NdpOption, NdpOptionType, NdpOptionTypes};
+use pnet::util::MacAddr;
+use std::net::Ipv6Addr;
+
+/// NDP messages must be sent with a hop limit of 255 (RFC 4861 §6.1), which
+/// guarantees they originated on the local link.
+pub const NDP_HOP_LIMIT: u8 = 255;
+
+/// Returns the address carried in the first Source/Target Link-Layer Address
+/// option of the given type.
+pub fn link_layer_address(options: &[NdpOption], option_type: NdpOptionType) -> Option<MacAddr> {
+    options
+        .iter()
+        .find(|o| o.option_type == option_type && o.data.len() >= 6)
+        .map(|o| {
+            MacAddr::new(
+                o.data[0], o.data[1], o.data[2], o.data[3], o.data[4], o.data[5],
+            )
+        })
+}
+
+/// Extracts the on-link prefixes announced in Prefix Information options,
+/// formatted as `prefix/len`.
+pub fn prefixes(options: &[NdpOption]) -> Vec<String> {
+    options
+        .iter()
+        .filter(|o| o.option_type == NdpOptionTypes::PrefixInformation && o.data.len() >= 30)
+        .filter_map(|o| {
+            let prefix_len = o.data[0];
+            let octets: [u8; 16] = o.data[14..30].try_into().ok()?;
+            Some(format!("{}/{}", Ipv6Addr::from(octets), prefix_len))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet::packet::icmpv6::ndp::{NeighborSolicitPacket, RouterAdvertPacket};
+
+    #[test]
+    fn reads_router_advertisement_options() {
+        let advertisement = [
+            134, 0, 0x4c, 0x3e, // type, code, checksum
+            64, 0x00, 0x07, 0x08, // hop limit, flags, router lifetime 1800 s
+            0, 0, 0, 0, 0, 0, 0, 0, // reachable time, retransmit timer
+            1, 1, 0x00, 0x0d, 0xb9, 0x4a, 0x12, 0x30, // source link-layer address
+            5, 1, 0, 0, 0, 0, 0x05, 0xdc, // MTU 1500
+            3, 4, 64, 0xc0, // prefix information, /64, on-link + autonomous
+            0x00, 0x27, 0x8d, 0x00, 0x00, 0x09, 0x3a, 0x80, 0, 0, 0, 0, // lifetimes
+            0x20, 0x01, 0x0d, 0xb8, 0x00, 0x01, 0x00, 0x02, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let ra = RouterAdvertPacket::new(&advertisement).unwrap();
+        let options = ra.get_options();
+
+        assert_eq!(ra.get_lifetime(), 1800);
+        assert_eq!(
+            link_layer_address(&options, NdpOptionTypes::SourceLLAddr),
+            Some(MacAddr::new(0x00, 0x0d, 0xb9, 0x4a, 0x12, 0x30))
+        );
+        assert_eq!(
+            link_layer_address(&options, NdpOptionTypes::TargetLLAddr),
+            None
+        );
+        assert_eq!(prefixes(&options), vec!["2001:db8:1:2::/64".to_string()]);
+    }
+
+    #[test]
+    fn reads_neighbor_solicitation_source() {
+        let solicitation = [
+            135, 0, 0x9a, 0x7b, 0, 0, 0, 0, // type, code, checksum, reserved
+            0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0x1c, 0x2b, 0x3a, 0xff, 0xfe, 0x49, 0x58, 0x67, 1, 1,
+            0x1e, 0x2b, 0x3a, 0x49, 0x58, 0x67, // source link-layer address
+        ];
+        let ns = NeighborSolicitPacket::new(&solicitation).unwrap();
+
+        assert_eq!(
+            ns.get_target_addr(),
+            "fe80::1c2b:3aff:fe49:5867".parse::<Ipv6Addr>().unwrap()
+        );
+        assert_eq!(
+            link_layer_address(&ns.get_options(), NdpOptionTypes::SourceLLAddr),
+            Some(MacAddr::new(0x1e, 0x2b, 0x3a, 0x49, 0x58, 0x67))
+        );
+        assert!(prefixes(&ns.get_options()).is_empty());
+    }
+}
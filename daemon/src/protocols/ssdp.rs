@@ -0,0 +1,222 @@
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+pub const PORT: u16 = 1900;
+
+/// An `ssdp:alive` NOTIFY, or a unicast M-SEARCH response, announcing a
+/// UPnP device or service.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notify {
+    pub server: Option<String>,
+    pub usn: Option<String>,
+    /// `NT` of a NOTIFY, or `ST` of a search response.
+    pub notification_type: Option<String>,
+    pub location: Option<String>,
+}
+
+impl Notify {
+    /// Device UUID from the USN, e.g. `uuid:abcd::upnp:rootdevice` gives `abcd`.
+    pub fn uuid(&self) -> Option<String> {
+        let usn = self.usn.as_deref()?;
+        let uuid = usn.strip_prefix("uuid:")?;
+        let uuid = uuid.split("::").next().unwrap_or(uuid);
+        (!uuid.is_empty()).then(|| uuid.to_string())
+    }
+
+    /// Device type URN, if this announcement is for a device rather than a
+    /// service or the root device.
+    pub fn device_type(&self) -> Option<String> {
+        self.notification_type
+            .as_ref()
+            .filter(|nt| nt.starts_with("urn:") && nt.contains(":device:"))
+            .cloned()
+    }
+}
+
+/// Fields of a UPnP device description document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceDescription {
+    pub friendly_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
+}
+
+/// Parses the UDP payload of an SSDP message. Only announcements are
+/// returned; searches and `ssdp:byebye` notifications yield `None`.
+pub fn parse(payload: &[u8]) -> Option<Notify> {
+    let text = std::str::from_utf8(payload).ok()?;
+    let mut lines = text.split("\r\n").flat_map(|line| line.split('\n'));
+
+    let start_line = lines.next()?.trim();
+    let is_notify = start_line.starts_with("NOTIFY ");
+    if !is_notify && !start_line.starts_with("HTTP/1.1 200") {
+        return None;
+    }
+
+    let mut notify = Notify {
+        server: None,
+        usn: None,
+        notification_type: None,
+        location: None,
+    };
+    let mut alive = !is_notify;
+
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+
+        match name.trim().to_ascii_uppercase().as_str() {
+            "SERVER" => notify.server = Some(value.to_string()),
+            "USN" => notify.usn = Some(value.to_string()),
+            "NT" | "ST" => notify.notification_type = Some(value.to_string()),
+            "LOCATION" => notify.location = Some(value.to_string()),
+            "NTS" => alive = value.eq_ignore_ascii_case("ssdp:alive"),
+            _ => {}
+        }
+    }
+
+    alive.then_some(notify)
+}
+
+/// Extracts the root device's details from a description document. The
+/// root device comes before any embedded devices, so the first occurrence of
+/// each element is used.
+pub fn parse_description(xml: &str) -> DeviceDescription {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut description = DeviceDescription::default();
+    let mut current = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) => {
+                current = match element.local_name().as_ref() {
+                    b"friendlyName" => Some(&mut description.friendly_name),
+                    b"manufacturer" => Some(&mut description.manufacturer),
+                    b"modelName" => Some(&mut description.model_name),
+                    _ => None,
+                };
+            }
+            Ok(Event::Text(text)) => {
+                if let Some(field) = current.take()
+                    && field.is_none()
+                    && let Ok(value) = text.unescape()
+                {
+                    *field = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+                }
+            }
+            Ok(Event::End(_)) => current = None,
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_alive_notify() {
+        let payload = b"NOTIFY * HTTP/1.1\r\n\
+            HOST: 239.255.255.250:1900\r\n\
+            CACHE-CONTROL: max-age=3600\r\n\
+            LOCATION: http://192.168.1.70:8060/\r\n\
+            NT: urn:roku-com:device:player:1-0\r\n\
+            NTS: ssdp:alive\r\n\
+            SERVER: Roku/12.5.0 UPnP/1.0 Roku/12.5.0\r\n\
+            USN: uuid:roku:ecp:YN00H5555555::urn:roku-com:device:player:1-0\r\n\
+            \r\n";
+
+        let notify = parse(payload).unwrap();
+        assert_eq!(
+            notify.location.as_deref(),
+            Some("http://192.168.1.70:8060/")
+        );
+        assert_eq!(
+            notify.server.as_deref(),
+            Some("Roku/12.5.0 UPnP/1.0 Roku/12.5.0")
+        );
+        assert_eq!(notify.uuid().as_deref(), Some("roku:ecp:YN00H5555555"));
+        assert_eq!(
+            notify.device_type().as_deref(),
+            Some("urn:roku-com:device:player:1-0")
+        );
+    }
+
+    #[test]
+    fn reads_search_response() {
+        let payload = b"HTTP/1.1 200 OK\r\n\
+            CACHE-CONTROL: max-age=1800\r\n\
+            EXT:\r\n\
+            LOCATION: http://192.168.1.1:1900/rootDesc.xml\r\n\
+            SERVER: OpenWRT/OpenWrt UPnP/1.1 MiniUPnPd/2.3.3\r\n\
+            ST: upnp:rootdevice\r\n\
+            USN: uuid:6e2c8f5a-0ad8-4b5e-9a3a-1d0c2b7e9f10::upnp:rootdevice\r\n\
+            \r\n";
+
+        let notify = parse(payload).unwrap();
+        assert_eq!(
+            notify.uuid().as_deref(),
+            Some("6e2c8f5a-0ad8-4b5e-9a3a-1d0c2b7e9f10")
+        );
+        assert_eq!(notify.notification_type.as_deref(), Some("upnp:rootdevice"));
+        assert_eq!(notify.device_type(), None);
+    }
+
+    #[test]
+    fn ignores_searches_and_byebyes() {
+        let search = b"M-SEARCH * HTTP/1.1\r\n\
+            HOST: 239.255.255.250:1900\r\n\
+            MAN: \"ssdp:discover\"\r\n\
+            MX: 1\r\n\
+            ST: ssdp:all\r\n\
+            \r\n";
+        assert_eq!(parse(search), None);
+
+        let byebye = b"NOTIFY * HTTP/1.1\r\n\
+            HOST: 239.255.255.250:1900\r\n\
+            NT: upnp:rootdevice\r\n\
+            NTS: ssdp:byebye\r\n\
+            USN: uuid:6e2c8f5a-0ad8-4b5e-9a3a-1d0c2b7e9f10::upnp:rootdevice\r\n\
+            \r\n";
+        assert_eq!(parse(byebye), None);
+    }
+
+    #[test]
+    fn reads_root_device_description() {
+        let xml = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <specVersion><major>1</major><minor>0</minor></specVersion>
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:InternetGatewayDevice:1</deviceType>
+    <friendlyName>OpenWrt router</friendlyName>
+    <manufacturer>OpenWrt &amp; friends</manufacturer>
+    <modelName>MiniUPnPd</modelName>
+    <deviceList>
+      <device>
+        <friendlyName>WANDevice</friendlyName>
+        <modelName>WAN Device</modelName>
+      </device>
+    </deviceList>
+  </device>
+</root>"#;
+
+        assert_eq!(
+            parse_description(xml),
+            DeviceDescription {
+                friendly_name: Some("OpenWrt router".into()),
+                manufacturer: Some("OpenWrt & friends".into()),
+                model_name: Some("MiniUPnPd".into()),
+            }
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use pnet::packet::Packet;
+use pnet::packet::ipv4::{Ipv4Flags, Ipv4Packet};
+use pnet::packet::ipv6::Ipv6Packet;
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
+
+/// Initial TTLs stacks start from. A SYN arriving with any other TTL crossed
+/// a router, whose MAC it then carries.
+pub const INITIAL_TTLS: [u8; 4] = [32, 64, 128, 255];
+
+const OPTION_EOL: u8 = 0;
+const OPTION_NOP: u8 = 1;
+const OPTION_MSS: u8 = 2;
+const OPTION_WS: u8 = 3;
+const OPTION_SOK: u8 = 4;
+const OPTION_SACK: u8 = 5;
+const OPTION_TS: u8 = 8;
+
+/// Largest window scale RFC 7323 allows.
+const MAX_WINDOW_SCALE: u8 = 14;
+
+/// What the IP header contributes to a signature.
+struct IpFields {
+    version: u8,
+    ttl: u8,
+    options_len: usize,
+    quirks: Vec<&'static str>,
+}
+
+/// p0f-style signature of a SYN sent straight from a host on the link, or
+/// `None` for anything else. See `data/p0f.fp` for the format;
+/// the window size is given as the raw value.
+pub fn ipv4_syn_signature(ipv4: &Ipv4Packet) -> Option<String> {
+    let flags = ipv4.get_flags();
+    let df = flags & Ipv4Flags::DontFragment != 0;
+    let id = ipv4.get_identification();
+
+    let mut quirks = Vec::new();
+    if df {
+        quirks.push("df");
+        if id != 0 {
+            quirks.push("id+");
+        }
+    } else if id == 0 {
+        quirks.push("id-");
+    }
+    if ipv4.get_ecn() != 0 {
+        quirks.push("ecn");
+    }
+    // The reserved ("evil") bit.
+    if flags & 0b100 != 0 {
+        quirks.push("0+");
+    }
+
+    let ip = IpFields {
+        version: 4,
+        ttl: ipv4.get_ttl(),
+        options_len: (ipv4.get_header_length() as usize * 4).saturating_sub(20),
+        quirks,
+    };
+    syn_signature(ip, &TcpPacket::new(ipv4.payload())?)
+}
+
+/// Like [`ipv4_syn_signature`], for a SYN over IPv6 without extension
+/// headers.
+pub fn ipv6_syn_signature(ipv6: &Ipv6Packet) -> Option<String> {
+    let mut quirks = Vec::new();
+    if ipv6.get_traffic_class() & 0b11 != 0 {
+        quirks.push("ecn");
+    }
+    if ipv6.get_flow_label() != 0 {
+        quirks.push("flow");
+    }
+
+    let ip = IpFields {
+        version: 6,
+        ttl: ipv6.get_hop_limit(),
+        options_len: 0,
+        quirks,
+    };
+    syn_signature(ip, &TcpPacket::new(ipv6.payload())?)
+}
+
+fn syn_signature(ip: IpFields, tcp: &TcpPacket) -> Option<String> {
+    let flags = tcp.get_flags();
+    if flags & (TcpFlags::SYN | TcpFlags::ACK) != TcpFlags::SYN || !INITIAL_TTLS.contains(&ip.ttl) {
+        return None;
+    }
+
+    let mut quirks = ip.quirks;
+    if flags & (TcpFlags::ECE | TcpFlags::CWR) != 0 && !quirks.contains(&"ecn") {
+        quirks.push("ecn");
+    }
+    if tcp.get_sequence() == 0 {
+        quirks.push("seq-");
+    }
+    if tcp.get_acknowledgement() != 0 {
+        quirks.push("ack+");
+    }
+    if flags & TcpFlags::URG != 0 {
+        quirks.push("urgf+");
+    } else if tcp.get_urgent_ptr() != 0 {
+        quirks.push("uptr+");
+    }
+    if flags & TcpFlags::PSH != 0 {
+        quirks.push("pushf+");
+    }
+
+    let options = parse_options(tcp.get_options_raw());
+    if options.timestamps.is_some_and(|(ts1, _)| ts1 == 0) {
+        quirks.push("ts1-");
+    }
+    if options.timestamps.is_some_and(|(_, ts2)| ts2 != 0) {
+        quirks.push("ts2+");
+    }
+    if options.data_after_eol {
+        quirks.push("opt+");
+    }
+    if options
+        .window_scale
+        .is_some_and(|scale| scale > MAX_WINDOW_SCALE)
+    {
+        quirks.push("exws");
+    }
+    if options.malformed {
+        quirks.push("bad");
+    }
+
+    Some(format!(
+        "{}:{}:{}:{}:{},{}:{}:{}:{}",
+        ip.version,
+        ip.ttl,
+        ip.options_len,
+        options
+            .mss
+            .map_or_else(|| "*".to_string(), |mss| mss.to_string()),
+        tcp.get_window(),
+        options.window_scale.unwrap_or(0),
+        options.layout.join(","),
+        quirks.join(","),
+        if tcp.payload().is_empty() { "0" } else { "+" },
+    ))
+}
+
+#[derive(Default)]
+struct Options {
+    layout: Vec<String>,
+    mss: Option<u16>,
+    window_scale: Option<u8>,
+    timestamps: Option<(u32, u32)>,
+    data_after_eol: bool,
+    malformed: bool,
+}
+
+fn parse_options(raw: &[u8]) -> Options {
+    let mut options = Options::default();
+    let mut offset = 0;
+
+    while let Some(&kind) = raw.get(offset) {
+        match kind {
+            OPTION_EOL => {
+                let rest = &raw[offset + 1..];
+                options.layout.push(format!("eol+{}", rest.len()));
+                options.data_after_eol = rest.iter().any(|&byte| byte != 0);
+                break;
+            }
+            OPTION_NOP => {
+                options.layout.push("nop".to_string());
+                offset += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let Some(value) = raw
+            .get(offset + 1)
+            .map(|&len| len as usize)
+            .filter(|&len| len >= 2)
+            .and_then(|len| raw.get(offset + 2..offset + len))
+        else {
+            options.malformed = true;
+            break;
+        };
+        offset += 2 + value.len();
+
+        let name = match kind {
+            OPTION_MSS => {
+                options.mss = value.try_into().ok().map(u16::from_be_bytes);
+                "mss".to_string()
+            }
+            OPTION_WS => {
+                options.window_scale = value.first().copied();
+                "ws".to_string()
+            }
+            OPTION_SOK => "sok".to_string(),
+            OPTION_SACK => "sack".to_string(),
+            OPTION_TS => {
+                if let (Some(ts1), Some(ts2)) = (value.get(..4), value.get(4..8)) {
+                    options.timestamps = Some((
+                        u32::from_be_bytes(ts1.try_into().unwrap()),
+                        u32::from_be_bytes(ts2.try_into().unwrap()),
+                    ));
+                }
+                "ts".to_string()
+            }
+            kind => format!("?{}", kind),
+        };
+        options.layout.push(name);
+    }
+
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::frames;
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::ipv4::MutableIpv4Packet;
+    use pnet::packet::ipv6::MutableIpv6Packet;
+    use std::net::Ipv4Addr;
+
+    /// A SYN from a Linux client: MSS 1460, SACK permitted, timestamps and
+    /// window scale 7.
+    const LINUX_SYN: [u8; 40] = [
+        0xb6, 0x8e, 0x01, 0xbb, 0x5f, 0x2a, 0x4c, 0x01, 0, 0, 0, 0, //
+        0xa0, 0x02, 0xfa, 0xf0, 0x3e, 0x8d, 0x00, 0x00, //
+        0x02, 0x04, 0x05, 0xb4, 0x04, 0x02, 0x08, 0x0a, 0x9e, 0x21, 0x37, 0x0c, 0, 0, 0, 0, 0x01,
+        0x03, 0x03, 0x07,
+    ];
+
+    /// A SYN from Windows 10: MSS 1460, window scale 8, SACK permitted.
+    const WINDOWS_SYN: [u8; 32] = [
+        0xc3, 0x51, 0x01, 0xbb, 0x8a, 0x3f, 0x11, 0x92, 0, 0, 0, 0, //
+        0x80, 0x02, 0xfa, 0xf0, 0x9d, 0x12, 0x00, 0x00, //
+        0x02, 0x04, 0x05, 0xb4, 0x01, 0x03, 0x03, 0x08, 0x01, 0x01, 0x04, 0x02,
+    ];
+
+    /// `segment` sent over IPv4 with an initial TTL of `ttl`.
+    fn ipv4(segment: &[u8], ttl: u8) -> Vec<u8> {
+        let mut packet = frames::ipv4(
+            Ipv4Addr::new(192, 168, 1, 23),
+            Ipv4Addr::new(93, 184, 216, 34),
+            IpNextHeaderProtocols::Tcp,
+            segment,
+        );
+        MutableIpv4Packet::new(&mut packet).unwrap().set_ttl(ttl);
+        packet
+    }
+
+    fn ipv4_signature(packet: &[u8]) -> Option<String> {
+        ipv4_syn_signature(&Ipv4Packet::new(packet).unwrap())
+    }
+
+    #[test]
+    fn signs_ipv4_syns() {
+        assert_eq!(
+            ipv4_signature(&ipv4(&LINUX_SYN, 64)).as_deref(),
+            Some("4:64:0:1460:64240,7:mss,sok,ts,nop,ws:df,id+:0")
+        );
+        assert_eq!(
+            ipv4_signature(&ipv4(&WINDOWS_SYN, 128)).as_deref(),
+            Some("4:128:0:1460:64240,8:mss,nop,ws,nop,nop,sok:df,id+:0")
+        );
+    }
+
+    #[test]
+    fn records_quirks() {
+        let mut syn = ipv4(&LINUX_SYN, 64);
+        // No DF with a zero IP ID, ECN setup, and a timestamp echo.
+        syn[4..7].copy_from_slice(&[0, 0, 0]);
+        syn[33] = 0xc2;
+        syn[52..56].copy_from_slice(&[0, 0, 0, 1]);
+
+        assert_eq!(
+            ipv4_signature(&syn).as_deref(),
+            Some("4:64:0:1460:64240,7:mss,sok,ts,nop,ws:id-,ecn,ts2+:0")
+        );
+    }
+
+    #[test]
+    fn ignores_routed_and_answering_segments() {
+        assert_eq!(ipv4_signature(&ipv4(&LINUX_SYN, 63)), None);
+
+        let mut syn_ack = ipv4(&LINUX_SYN, 64);
+        syn_ack[33] = 0x12;
+        assert_eq!(ipv4_signature(&syn_ack), None);
+    }
+
+    #[test]
+    fn signs_ipv6_syns() {
+        // The Linux SYN with an MSS of 1440, over IPv6 with a flow label.
+        let mut segment = LINUX_SYN;
+        segment[22..24].copy_from_slice(&[0x05, 0xa0]);
+        let mut packet = frames::ipv6(
+            "fe80::1c2b:3aff:fe49:5867".parse().unwrap(),
+            "2001:db8::1".parse().unwrap(),
+            IpNextHeaderProtocols::Tcp,
+            64,
+            &segment,
+        );
+        MutableIpv6Packet::new(&mut packet)
+            .unwrap()
+            .set_flow_label(0xa1b2c);
+
+        assert_eq!(
+            ipv6_syn_signature(&Ipv6Packet::new(&packet).unwrap()).as_deref(),
+            Some("6:64:0:1440:64240,7:mss,sok,ts,nop,ws:flow:0")
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use pnet::packet::ethernet::{EtherType, EtherTypes};
+use pnet::packet::vlan::VlanPacket;
+
+/// Stacked tags decoded before a frame is given up on: an 802.1ad service
+/// tag plus an 802.1Q customer tag.
+const MAX_TAGS: usize = 2;
+
+/// A frame payload with its 802.1Q/802.1ad tags removed.
+pub struct Untagged<'a> {
+    /// VLAN IDs from the outermost tag inwards; empty for untagged frames.
+    pub vlan_ids: Vec<u16>,
+    pub ethertype: EtherType,
+    pub payload: &'a [u8],
+}
+
+fn is_tag(ethertype: EtherType) -> bool {
+    matches!(
+        ethertype,
+        EtherTypes::Vlan | EtherTypes::PBridge | EtherTypes::QinQ
+    )
+}
+
+/// Strips any VLAN tags in front of the payload of an Ethernet frame.
+pub fn strip_tags(ethertype: EtherType, payload: &[u8]) -> Option<Untagged<'_>> {
+    let mut vlan_ids = Vec::new();
+    let mut ethertype = ethertype;
+    let mut payload = payload;
+
+    while is_tag(ethertype) {
+        if vlan_ids.len() == MAX_TAGS {
+            return None;
+        }
+
+        let tag = VlanPacket::new(payload)?;
+        vlan_ids.push(tag.get_vlan_identifier());
+        ethertype = tag.get_ethertype();
+        payload = &payload[VlanPacket::minimum_packet_size()..];
+    }
+
+    Some(Untagged {
+        vlan_ids,
+        ethertype,
+        payload,
+    })
+}
@@ -0,0 +1,130 @@
+use ipnetwork::{IpNetwork, Ipv4Network};
+use pnet::datalink::{self, Channel, NetworkInterface};
+use pnet::packet::arp::{ArpHardwareTypes, ArpOperations, MutableArpPacket};
+use pnet::packet::ethernet::{EtherTypes, MutableEthernetPacket};
+use pnet::util::MacAddr;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::errors::{DaemonError, Result};
+
+/// Subnets with a shorter prefix are not swept; a /16 already takes over ten
+/// minutes at 100 requests per second.
+pub const MIN_SWEEP_PREFIX: u8 = 16;
+
+const ETHERNET_HEADER_LEN: usize = 14;
+const ARP_PACKET_LEN: usize = 28;
+
+/// Sends an ARP request for every host address in the IPv4 subnets of
+/// `interface`, at most `rate` per second. Replies are not read here: they
+/// reach the packet capture like any other ARP traffic. Blocks until the
+/// sweep is done and returns the number of requests sent.
+pub fn sweep(interface: &str, rate: u32) -> Result<usize> {
+    let network_interface = datalink::interfaces()
+        .into_iter()
+        .find(|i| i.name == interface)
+        .ok_or_else(|| DaemonError::PacketCapture(format!("Interface {} not found", interface)))?;
+    let source_mac = network_interface.mac.ok_or_else(|| {
+        DaemonError::PacketCapture(format!("Interface {} has no MAC address", interface))
+    })?;
+
+    let networks = sweep_networks(&network_interface);
+    if networks.is_empty() {
+        debug!("No IPv4 subnet to sweep on {}", interface);
+        return Ok(0);
+    }
+
+    let mut tx = match datalink::channel(&network_interface, datalink::Config::default())? {
+        Channel::Ethernet(tx, _) => tx,
+        _ => {
+            return Err(DaemonError::PacketCapture(format!(
+                "Unsupported AF_PACKET channel on {}",
+                interface
+            )));
+        }
+    };
+
+    let pause = Duration::from_secs(1) / rate.max(1);
+    let mut sent = 0;
+
+    for network in networks {
+        debug!("Sweeping {} on {}", network, interface);
+        let source_ip = network.ip();
+
+        for target_ip in network.iter() {
+            if target_ip == source_ip
+                || target_ip == network.network()
+                || target_ip == network.broadcast()
+            {
+                continue;
+            }
+
+            let frame = arp_request(source_mac, source_ip, MacAddr::broadcast(), target_ip);
+            match tx.send_to(&frame, None) {
+                Some(Ok(())) => sent += 1,
+                Some(Err(e)) => debug!("Failed to send ARP request for {}: {}", target_ip, e),
+                None => {}
+            }
+            std::thread::sleep(pause);
+        }
+    }
+
+    Ok(sent)
+}
+
+/// The interface's IPv4 subnets that are small enough to sweep.
+fn sweep_networks(interface: &NetworkInterface) -> Vec<Ipv4Network> {
+    interface
+        .ips
+        .iter()
+        .filter_map(|ip| match ip {
+            IpNetwork::V4(network) => Some(*network),
+            IpNetwork::V6(_) => None,
+        })
+        // Point-to-point links have no neighbours to find.
+        .filter(|network| network.prefix() < 31)
+        .filter(|network| {
+            if network.prefix() < MIN_SWEEP_PREFIX {
+                warn!(
+                    "Not sweeping {} on {}: subnets wider than /{} are skipped",
+                    network, interface.name, MIN_SWEEP_PREFIX
+                );
+                return false;
+            }
+            true
+        })
+        .collect()
+}
+
+/// Builds an Ethernet frame carrying an ARP request for `target_ip`, sent to
+/// `destination`: broadcast for discovery, or the expected owner's MAC to
+/// check that it is still there.
+pub fn arp_request(
+    source_mac: MacAddr,
+    source_ip: Ipv4Addr,
+    destination: MacAddr,
+    target_ip: Ipv4Addr,
+) -> Vec<u8> {
+    let mut frame = vec![0u8; ETHERNET_HEADER_LEN + ARP_PACKET_LEN];
+
+    let mut ethernet =
+        MutableEthernetPacket::new(&mut frame).expect("frame fits an Ethernet header");
+    ethernet.set_destination(destination);
+    ethernet.set_source(source_mac);
+    ethernet.set_ethertype(EtherTypes::Arp);
+
+    let mut arp =
+        MutableArpPacket::new(&mut frame[ETHERNET_HEADER_LEN..]).expect("frame fits an ARP packet");
+    arp.set_hardware_type(ArpHardwareTypes::Ethernet);
+    arp.set_protocol_type(EtherTypes::Ipv4);
+    arp.set_hw_addr_len(6);
+    arp.set_proto_addr_len(4);
+    arp.set_operation(ArpOperations::Request);
+    arp.set_sender_hw_addr(source_mac);
+    arp.set_sender_proto_addr(source_ip);
+    arp.set_target_hw_addr(MacAddr::zero());
+    arp.set_target_proto_addr(target_ip);
+
+    frame
+}
@@ -0,0 +1,75 @@
+use std::path::Path;
+use tracing::{info, warn};
+
+/// A signature database read from text, one entry per line, such as the
+/// DHCP fingerprints or the TCP SYN signatures. Blank lines and lines
+/// starting with `#` or `;` are comments.
+pub trait SignatureDatabase: Default {
+    /// What an entry is called in log messages, e.g. `DHCP fingerprint`.
+    const KIND: &'static str;
+    /// The database built into foxd.
+    const EMBEDDED: &'static str;
+
+    /// Parser state carried from one line to the next within a file, such
+    /// as the section or label entries belong to.
+    type State: Default;
+
+    /// Adds the entry on `line`, or returns `false` if it is malformed.
+    fn add_line(&mut self, line: &str, state: &mut Self::State) -> bool;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn embedded() -> Self {
+        Self::parse(Self::EMBEDDED)
+    }
+
+    /// Reads a database, skipping (and logging) malformed lines.
+    fn parse(text: &str) -> Self {
+        let mut database = Self::default();
+        database.read(text);
+        database
+    }
+
+    /// Adds the entries in `text` after the ones already loaded.
+    fn read(&mut self, text: &str) {
+        let mut state = Self::State::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+                continue;
+            }
+            if !self.add_line(line.trim_end(), &mut state) {
+                warn!("Ignoring malformed {} on line {}", Self::KIND, number + 1);
+            }
+        }
+    }
+
+    /// The embedded database followed by the one in `path`, if any. A file
+    /// that cannot be read is logged and left out.
+    fn load(path: Option<&Path>) -> Self {
+        let mut database = Self::embedded();
+        let Some(path) = path else {
+            return database;
+        };
+
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                let before = database.len();
+                database.read(&text);
+                info!(
+                    "Loaded {} {}s from {}",
+                    database.len() - before,
+                    Self::KIND,
+                    path.display()
+                );
+            }
+            Err(e) => warn!("Cannot read {}s from {}: {}", Self::KIND, path.display(), e),
+        }
+        database
+    }
+}
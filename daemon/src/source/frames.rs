@@ -0,0 +1,236 @@
+use pnet::packet::arp::{ArpHardwareTypes, ArpOperation, MutableArpPacket};
+use pnet::packet::ethernet::{EtherType, EtherTypes, MutableEthernetPacket};
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv4::{self, Ipv4Flags, MutableIpv4Packet};
+use pnet::packet::ipv6::MutableIpv6Packet;
+use pnet::packet::udp::{self, MutableUdpPacket};
+use pnet::util::MacAddr;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4};
+
+use crate::protocols::dhcp::MAGIC_COOKIE;
+
+const ETHERNET_HEADER_LEN: usize = 14;
+const ARP_PACKET_LEN: usize = 28;
+const IPV4_HEADER_LEN: usize = 20;
+const IPV6_HEADER_LEN: usize = 40;
+const UDP_HEADER_LEN: usize = 8;
+
+/// Wraps `payload` in an Ethernet header.
+pub fn ethernet(
+    destination: MacAddr,
+    source: MacAddr,
+    ethertype: EtherType,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut frame = vec![0u8; ETHERNET_HEADER_LEN + payload.len()];
+
+    let mut ethernet =
+        MutableEthernetPacket::new(&mut frame).expect("frame fits an Ethernet header");
+    ethernet.set_destination(destination);
+    ethernet.set_source(source);
+    ethernet.set_ethertype(ethertype);
+    frame[ETHERNET_HEADER_LEN..].copy_from_slice(payload);
+
+    frame
+}
+
+/// Builds a broadcast ARP frame from `sender_mac` claiming `sender_ip`. A
+/// request or reply whose target is the sender's own address is gratuitous.
+pub fn arp(
+    operation: ArpOperation,
+    sender_mac: MacAddr,
+    sender_ip: Ipv4Addr,
+    target_ip: Ipv4Addr,
+) -> Vec<u8> {
+    let mut packet = vec![0u8; ARP_PACKET_LEN];
+
+    let mut arp = MutableArpPacket::new(&mut packet).expect("packet fits an ARP packet");
+    arp.set_hardware_type(ArpHardwareTypes::Ethernet);
+    arp.set_protocol_type(EtherTypes::Ipv4);
+    arp.set_hw_addr_len(6);
+    arp.set_proto_addr_len(4);
+    arp.set_operation(operation);
+    arp.set_sender_hw_addr(sender_mac);
+    arp.set_sender_proto_addr(sender_ip);
+    arp.set_target_hw_addr(MacAddr::zero());
+    arp.set_target_proto_addr(target_ip);
+
+    ethernet(MacAddr::broadcast(), sender_mac, EtherTypes::Arp, &packet)
+}
+
+/// Builds an IPv4 packet with a TTL of 64 and the don't-fragment bit set, as
+/// most stacks send.
+pub fn ipv4(
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    protocol: IpNextHeaderProtocol,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut packet = vec![0u8; IPV4_HEADER_LEN + payload.len()];
+
+    let mut ipv4 = MutableIpv4Packet::new(&mut packet).expect("packet fits an IPv4 header");
+    ipv4.set_version(4);
+    ipv4.set_header_length((IPV4_HEADER_LEN / 4) as u8);
+    ipv4.set_total_length((IPV4_HEADER_LEN + payload.len()) as u16);
+    ipv4.set_identification(0x1c46);
+    ipv4.set_flags(Ipv4Flags::DontFragment);
+    ipv4.set_ttl(64);
+    ipv4.set_next_level_protocol(protocol);
+    ipv4.set_source(source);
+    ipv4.set_destination(destination);
+    ipv4.set_payload(payload);
+    let checksum = ipv4::checksum(&ipv4.to_immutable());
+    ipv4.set_checksum(checksum);
+
+    packet
+}
+
+/// Builds an IPv6 packet without extension headers.
+pub fn ipv6(
+    source: Ipv6Addr,
+    destination: Ipv6Addr,
+    next_header: IpNextHeaderProtocol,
+    hop_limit: u8,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut packet = vec![0u8; IPV6_HEADER_LEN + payload.len()];
+
+    let mut ipv6 = MutableIpv6Packet::new(&mut packet).expect("packet fits an IPv6 header");
+    ipv6.set_version(6);
+    ipv6.set_payload_length(payload.len() as u16);
+    ipv6.set_next_header(next_header);
+    ipv6.set_hop_limit(hop_limit);
+    ipv6.set_source(source);
+    ipv6.set_destination(destination);
+    ipv6.set_payload(payload);
+
+    packet
+}
+
+/// Builds an Ethernet frame carrying a UDP datagram over IPv4. Datagrams to a
+/// multicast group go to its MAC; any other destination gets the broadcast
+/// MAC, as the parser only looks at the IP addresses.
+pub fn udp4(
+    source_mac: MacAddr,
+    source: SocketAddrV4,
+    destination: SocketAddrV4,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut datagram = vec![0u8; UDP_HEADER_LEN + payload.len()];
+
+    let mut udp = MutableUdpPacket::new(&mut datagram).expect("datagram fits a UDP header");
+    udp.set_source(source.port());
+    udp.set_destination(destination.port());
+    udp.set_length((UDP_HEADER_LEN + payload.len()) as u16);
+    udp.set_payload(payload);
+    let checksum = udp::ipv4_checksum(&udp.to_immutable(), source.ip(), destination.ip());
+    udp.set_checksum(checksum);
+
+    let packet = ipv4(
+        *source.ip(),
+        *destination.ip(),
+        IpNextHeaderProtocols::Udp,
+        &datagram,
+    );
+    let destination_mac = if destination.ip().is_multicast() {
+        // The low 23 bits of the group address.
+        let [_, b, c, d] = destination.ip().octets();
+        MacAddr::new(0x01, 0x00, 0x5e, b & 0x7f, c, d)
+    } else {
+        MacAddr::broadcast()
+    };
+    ethernet(destination_mac, source_mac, EtherTypes::Ipv4, &packet)
+}
+
+/// Builds a BOOTP message from `chaddr` with the given ciaddr, yiaddr,
+/// siaddr and giaddr, followed by the DHCP magic cookie and `options`, e.g.
+/// from [`dhcp_options`]. `op` is 1 for client messages and 2 for replies.
+pub fn bootp(op: u8, chaddr: MacAddr, addresses: [Ipv4Addr; 4], options: &[u8]) -> Vec<u8> {
+    let mut message = vec![op, 1, 6, 0];
+    message.extend_from_slice(&[0x3d, 0x1d, 0x9a, 0x5c]); // xid
+    message.extend_from_slice(&[0, 0, 0, 0]); // secs, flags
+    for address in addresses {
+        message.extend_from_slice(&address.octets());
+    }
+    message.extend_from_slice(&chaddr.octets());
+    message.extend_from_slice(&[0; 10 + 64 + 128]); // chaddr padding, sname, file
+    message.extend_from_slice(&MAGIC_COOKIE);
+    message.extend_from_slice(options);
+    message
+}
+
+/// Encodes DHCP options as code, length and value, followed by the end
+/// option.
+pub fn dhcp_options(options: &[(u8, &[u8])]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for (code, value) in options {
+        encoded.push(*code);
+        encoded.push(value.len() as u8);
+        encoded.extend_from_slice(value);
+    }
+    encoded.push(255);
+    encoded
+}
+
+/// A DNS header with the question, answer, authority and additional record
+/// counts in `counts`. NBNS, mDNS and LLMNR share it.
+pub fn dns_header(id: u16, flags: u16, counts: [u16; 4]) -> Vec<u8> {
+    let mut header = [id, flags].map(u16::to_be_bytes).concat();
+    for count in counts {
+        header.extend_from_slice(&count.to_be_bytes());
+    }
+    header
+}
+
+/// Encodes `name` as uncompressed DNS labels.
+pub fn dns_name(name: &str) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for label in name.split('.') {
+        encoded.push(label.len() as u8);
+        encoded.extend_from_slice(label.as_bytes());
+    }
+    encoded.push(0);
+    encoded
+}
+
+/// A resource record for the already encoded `name`, which may be a
+/// compression pointer. mDNS sets the top bit of `class` to flush caches.
+pub fn dns_record(name: &[u8], record_type: u16, class: u16, ttl: u32, data: &[u8]) -> Vec<u8> {
+    [
+        name,
+        &record_type.to_be_bytes(),
+        &class.to_be_bytes(),
+        &ttl.to_be_bytes(),
+        &(data.len() as u16).to_be_bytes(),
+        data,
+    ]
+    .concat()
+}
+
+/// `name` padded to 15 bytes and followed by `suffix`, in the first-level
+/// encoding of RFC 1001.
+pub fn netbios_name(name: &str, suffix: u8) -> Vec<u8> {
+    let mut raw = [b' '; 16];
+    raw[..name.len()].copy_from_slice(name.as_bytes());
+    raw[15] = suffix;
+
+    let mut encoded = vec![32];
+    for byte in raw {
+        encoded.push(b'A' + (byte >> 4));
+        encoded.push(b'A' + (byte & 0x0f));
+    }
+    encoded.push(0);
+    encoded
+}
+
+/// An LLDP TLV: a 7-bit type and 9-bit length, then the value.
+pub fn lldp_tlv(tlv_type: u8, value: &[u8]) -> Vec<u8> {
+    let header = (u16::from(tlv_type) << 9) | value.len() as u16;
+    [&header.to_be_bytes()[..], value].concat()
+}
+
+/// A CDP TLV, whose length counts the 4-byte header too.
+pub fn cdp_tlv(tlv_type: u16, value: &[u8]) -> Vec<u8> {
+    let len = (value.len() + 4) as u16;
+    [&tlv_type.to_be_bytes()[..], &len.to_be_bytes(), value].concat()
+}
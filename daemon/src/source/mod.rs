@@ -0,0 +1,166 @@
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::errors::{DaemonError, Result};
+
+pub mod af_packet;
+/// Builders for the frames a [`SyntheticSource`] replays in tests.
+pub mod frames;
+#[cfg(feature = "libpcap")]
+pub mod libpcap;
+pub mod synthetic;
+
+pub use af_packet::AfPacketSource;
+#[cfg(feature = "libpcap")]
+pub use libpcap::{LivePcapSource, PcapFileSource};
+pub use synthetic::SyntheticSource;
+
+/// A raw Ethernet frame handed out by a [`PacketSource`].
+pub struct Frame<'a> {
+    /// When the frame was captured, if the source knows.
+    pub timestamp: Option<DateTime<Utc>>,
+    pub data: &'a [u8],
+}
+
+/// Result of a single read from a [`PacketSource`].
+pub enum NextFrame<'a> {
+    Frame(Frame<'a>),
+    /// Nothing arrived within the read timeout; the caller should try again.
+    Idle,
+    /// The source has no more frames.
+    Exhausted,
+}
+
+/// Anything that yields Ethernet frames for the daemon to parse. Reads are
+/// blocking, so live sources should return [`NextFrame::Idle`] periodically
+/// instead of waiting forever.
+pub trait PacketSource: Send {
+    /// Short description used in logs, e.g. `pcap on eth0`.
+    fn describe(&self) -> String;
+
+    /// Whether frames come from a live network rather than a recording.
+    /// Recorded sources are replayed in order without dropping frames.
+    fn is_live(&self) -> bool;
+
+    /// Applies a BPF filter. Sources without kernel filtering accept every
+    /// frame and leave it to the parser to ignore what it does not handle.
+    fn set_filter(&mut self, filter: &str) -> Result<()>;
+
+    fn next_frame(&mut self) -> Result<NextFrame<'_>>;
+}
+
+/// The `[daemon] source` setting.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum SourceKind {
+    /// Live capture on the configured interface through libpcap. The
+    /// default when foxd is built with libpcap.
+    #[cfg_attr(feature = "libpcap", default)]
+    Pcap,
+    /// Live capture on the configured interface through a raw AF_PACKET
+    /// socket, without libpcap. The default otherwise.
+    #[cfg_attr(not(feature = "libpcap"), default)]
+    AfPacket,
+    /// Replay of a saved pcap file.
+    File(PathBuf),
+}
+
+impl SourceKind {
+    pub fn open(&self, interface: &str) -> Result<Box<dyn PacketSource>> {
+        Ok(match self {
+            #[cfg(feature = "libpcap")]
+            SourceKind::Pcap => Box::new(LivePcapSource::open(interface)?),
+            #[cfg(feature = "libpcap")]
+            SourceKind::File(path) => Box::new(PcapFileSource::open(path)?),
+            #[cfg(not(feature = "libpcap"))]
+            SourceKind::Pcap | SourceKind::File(_) => return Err(without_libpcap()),
+            SourceKind::AfPacket => Box::new(AfPacketSource::open(interface)?),
+        })
+    }
+
+    /// Checks that `filter` can be applied by this source, so that a bad
+    /// filter is reported before any capture is opened. AF_PACKET sources
+    /// apply no filter, so any expression is accepted for them.
+    #[cfg_attr(not(feature = "libpcap"), allow(unused_variables))]
+    pub fn validate_filter(&self, filter: &str) -> Result<()> {
+        match self {
+            #[cfg(feature = "libpcap")]
+            SourceKind::Pcap | SourceKind::File(_) => libpcap::validate_filter(filter),
+            #[cfg(not(feature = "libpcap"))]
+            SourceKind::Pcap | SourceKind::File(_) => Err(without_libpcap()),
+            SourceKind::AfPacket => Ok(()),
+        }
+    }
+}
+
+impl FromStr for SourceKind {
+    type Err = DaemonError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let kind = match s {
+            "pcap" => SourceKind::Pcap,
+            "af_packet" => SourceKind::AfPacket,
+            _ => match s.strip_prefix("file:") {
+                Some(path) if !path.is_empty() => SourceKind::File(PathBuf::from(path)),
+                _ => {
+                    return Err(DaemonError::Config(format!(
+                        "Invalid source \"{}\": expected pcap, af_packet or file:<path>",
+                        s
+                    )));
+                }
+            },
+        };
+
+        #[cfg(not(feature = "libpcap"))]
+        if kind != SourceKind::AfPacket {
+            return Err(without_libpcap());
+        }
+        Ok(kind)
+    }
+}
+
+#[cfg(not(feature = "libpcap"))]
+fn without_libpcap() -> DaemonError {
+    DaemonError::Config(
+        "foxd was built without libpcap; only source = \"af_packet\" is available".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sources() {
+        assert_eq!(
+            "af_packet".parse::<SourceKind>().unwrap(),
+            SourceKind::AfPacket
+        );
+        assert!("file:".parse::<SourceKind>().is_err());
+        assert!("pcapng".parse::<SourceKind>().is_err());
+
+        #[cfg(feature = "libpcap")]
+        {
+            assert_eq!("pcap".parse::<SourceKind>().unwrap(), SourceKind::Pcap);
+            assert_eq!(
+                "file:/tmp/capture.pcap".parse::<SourceKind>().unwrap(),
+                SourceKind::File(PathBuf::from("/tmp/capture.pcap"))
+            );
+        }
+        #[cfg(not(feature = "libpcap"))]
+        {
+            assert!("pcap".parse::<SourceKind>().is_err());
+            assert_eq!(SourceKind::default(), SourceKind::AfPacket);
+        }
+    }
+
+    #[test]
+    fn af_packet_accepts_any_filter() {
+        // It applies none, so the expression is never compiled.
+        assert!(
+            SourceKind::AfPacket
+                .validate_filter("not ( a filter")
+                .is_ok()
+        );
+    }
+}
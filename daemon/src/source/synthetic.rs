@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+
+use super::{Frame, NextFrame, PacketSource};
+use crate::errors::Result;
+
+/// In-memory source that yields a fixed sequence of frames, for driving the
+/// event pipeline without a network.
+#[derive(Default)]
+pub struct SyntheticSource {
+    frames: VecDeque<(Option<DateTime<Utc>>, Vec<u8>)>,
+    current: Vec<u8>,
+}
+
+impl SyntheticSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a frame without a timestamp.
+    pub fn push(&mut self, data: Vec<u8>) -> &mut Self {
+        self.frames.push_back((None, data));
+        self
+    }
+
+    /// Queues a frame captured at `timestamp`.
+    pub fn push_at(&mut self, timestamp: DateTime<Utc>, data: Vec<u8>) -> &mut Self {
+        self.frames.push_back((Some(timestamp), data));
+        self
+    }
+}
+
+impl PacketSource for SyntheticSource {
+    fn describe(&self) -> String {
+        format!("synthetic source ({} frames queued)", self.frames.len())
+    }
+
+    fn is_live(&self) -> bool {
+        false
+    }
+
+    fn set_filter(&mut self, _filter: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn next_frame(&mut self) -> Result<NextFrame<'_>> {
+        let Some((timestamp, data)) = self.frames.pop_front() else {
+            return Ok(NextFrame::Exhausted);
+        };
+
+        self.current = data;
+        Ok(NextFrame::Frame(Frame {
+            timestamp,
+            data: &self.current,
+        }))
+    }
+}
@@ -0,0 +1,486 @@
+use crate::signatures::SignatureDatabase;
+
+/// What a device's TCP SYN says about its operating system.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TcpClassification {
+    pub os: String,
+    /// Version or variant, e.g. `3.11 and newer`.
+    pub flavor: String,
+    /// The signature only matched once its quirks were ignored.
+    pub fuzzy: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WindowSize {
+    Any,
+    Value(u32),
+    /// A multiple of the MSS.
+    Mss(u32),
+    /// A multiple of the MTU, taken as the MSS plus the IP and TCP headers.
+    Mtu(u32),
+    /// Any multiple of the value.
+    Modulo(u32),
+}
+
+#[derive(Debug, Clone)]
+struct Signature {
+    /// IP version; `None` matches both.
+    version: Option<u8>,
+    ittl: u8,
+    options_len: usize,
+    mss: Option<u16>,
+    window: WindowSize,
+    scale: Option<u8>,
+    layout: String,
+    /// Sorted, so that the order they are listed in does not matter.
+    quirks: Vec<String>,
+    /// Whether the SYN carries data; `None` matches either.
+    payload: Option<bool>,
+    label: Label,
+}
+
+/// A p0f `label` line, which names the signatures that follow it.
+#[derive(Debug, Clone, PartialEq)]
+struct Label {
+    os: String,
+    flavor: String,
+    /// A catch-all signature, only used when no specific one matches.
+    generic: bool,
+}
+
+/// Where [`TcpSignatures`] is within a p0f.fp file.
+#[derive(Debug, Default)]
+pub struct ParseState {
+    /// Inside the `[tcp:request]` section, which holds the SYN signatures.
+    in_requests: bool,
+    label: Option<Label>,
+}
+
+/// A signature as built by [`crate::protocols::tcp`], with every field
+/// known.
+#[derive(Debug, Clone)]
+struct Observed {
+    version: u8,
+    ttl: u8,
+    options_len: usize,
+    mss: Option<u16>,
+    window: u32,
+    scale: u8,
+    layout: String,
+    quirks: Vec<String>,
+    payload: bool,
+}
+
+/// Known TCP SYN signatures, after p0f: the initial TTL, window size and
+/// TCP options a stack opens connections with differ between operating
+/// systems and their versions.
+#[derive(Debug, Default)]
+pub struct TcpSignatures {
+    signatures: Vec<Signature>,
+}
+
+/// The `[tcp:request]` section of a p0f 3 `p0f.fp`, as in `data/p0f.fp`.
+/// Other sections are skipped.
+impl SignatureDatabase for TcpSignatures {
+    const KIND: &'static str = "TCP signature";
+    const EMBEDDED: &'static str = include_str!("../data/p0f.fp");
+
+    type State = ParseState;
+
+    fn add_line(&mut self, line: &str, state: &mut ParseState) -> bool {
+        let line = line.trim();
+        if let Some(section) = line.strip_prefix('[') {
+            state.in_requests = section.trim_end_matches(']').trim() == "tcp:request";
+            state.label = None;
+            return true;
+        }
+        if !state.in_requests {
+            return true;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return false;
+        };
+        match key.trim() {
+            "label" => {
+                state.label = parse_label(value.trim());
+                state.label.is_some()
+            }
+            "sys" => true,
+            "sig" => state
+                .label
+                .clone()
+                .and_then(|label| parse_signature(value.trim(), label))
+                .map(|signature| self.signatures.push(signature))
+                .is_some(),
+            _ => false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.signatures.len()
+    }
+}
+
+impl TcpSignatures {
+    /// Classifies an observed SYN signature. A specific signature matching
+    /// every field wins over a generic one, and either over a signature that
+    /// only matches once quirks are ignored, as they vary with settings such
+    /// as ECN or IP ID generation. Among those, the one whose quirks differ
+    /// least wins; a tie between different systems is no match. Signatures
+    /// read later win exact ties, so a user's file overrides the built-in
+    /// signatures.
+    pub fn classify(&self, observed: &str) -> Option<TcpClassification> {
+        let observed = parse_observed(observed)?;
+        let candidates: Vec<(usize, &Signature)> = self
+            .signatures
+            .iter()
+            .filter(|signature| signature.matches(&observed))
+            .map(|signature| {
+                (
+                    quirk_distance(&signature.quirks, &observed.quirks),
+                    signature,
+                )
+            })
+            .collect();
+
+        let exact = |generic: bool| {
+            candidates
+                .iter()
+                .rev()
+                .find(|(distance, signature)| *distance == 0 && signature.label.generic == generic)
+        };
+        if let Some((_, signature)) = exact(false).or_else(|| exact(true)) {
+            return Some(signature.label.classification(false));
+        }
+
+        let closest = candidates
+            .iter()
+            .map(|(distance, signature)| (*distance, signature.label.generic))
+            .min()?;
+        let mut best = candidates
+            .iter()
+            .filter(|(distance, signature)| (*distance, signature.label.generic) == closest)
+            .map(|(_, signature)| &signature.label);
+        let label = best.next()?;
+        if best.any(|other| other.os != label.os || other.flavor != label.flavor) {
+            return None;
+        }
+        Some(label.classification(true))
+    }
+}
+
+impl Label {
+    fn classification(&self, fuzzy: bool) -> TcpClassification {
+        TcpClassification {
+            os: self.os.clone(),
+            flavor: self.flavor.clone(),
+            fuzzy,
+        }
+    }
+}
+
+/// How many quirks one list has that the other lacks.
+fn quirk_distance(a: &[String], b: &[String]) -> usize {
+    a.iter().filter(|quirk| !b.contains(quirk)).count()
+        + b.iter().filter(|quirk| !a.contains(quirk)).count()
+}
+
+impl Signature {
+    /// Whether every field but the quirks matches.
+    fn matches(&self, observed: &Observed) -> bool {
+        if self
+            .version
+            .is_some_and(|version| version != observed.version)
+            || self.ittl != observed.ttl
+            || self.options_len != observed.options_len
+            || self.layout != observed.layout
+            || self.mss.is_some_and(|mss| Some(mss) != observed.mss)
+            || self.scale.is_some_and(|scale| scale != observed.scale)
+            || self
+                .payload
+                .is_some_and(|payload| payload != observed.payload)
+        {
+            return false;
+        }
+
+        let headers = if observed.version == 4 { 40 } else { 60 };
+        match self.window {
+            WindowSize::Any => true,
+            WindowSize::Value(window) => window == observed.window,
+            WindowSize::Mss(factor) => observed
+                .mss
+                .is_some_and(|mss| mss as u32 * factor == observed.window),
+            WindowSize::Mtu(factor) => observed
+                .mss
+                .is_some_and(|mss| (mss as u32 + headers) * factor == observed.window),
+            WindowSize::Modulo(divisor) => divisor != 0 && observed.window.is_multiple_of(divisor),
+        }
+    }
+}
+
+/// Splits `ver:ittl:olen:mss:wsize,scale:olayout:quirks:pclass`.
+fn fields(signature: &str) -> Option<[&str; 9]> {
+    let fields: Vec<&str> = signature.split(':').collect();
+    let [
+        version,
+        ittl,
+        options_len,
+        mss,
+        window,
+        layout,
+        quirks,
+        payload,
+    ] = fields[..]
+    else {
+        return None;
+    };
+    let (window, scale) = window.split_once(',')?;
+    Some([
+        version,
+        ittl,
+        options_len,
+        mss,
+        window,
+        scale,
+        layout,
+        quirks,
+        payload,
+    ])
+}
+
+fn quirks(quirks: &str) -> Vec<String> {
+    let mut quirks: Vec<String> = quirks
+        .split(',')
+        .map(str::trim)
+        .filter(|quirk| !quirk.is_empty())
+        .map(String::from)
+        .collect();
+    quirks.sort_unstable();
+    quirks
+}
+
+/// Parses `value`, with `*` for any value.
+fn wildcard<T: std::str::FromStr>(value: &str) -> Option<Option<T>> {
+    match value {
+        "*" => Some(None),
+        value => value.parse().ok().map(Some),
+    }
+}
+
+/// Parses `type:class:name:flavor`, e.g. `s:unix:Linux:3.11 and newer`.
+fn parse_label(label: &str) -> Option<Label> {
+    let mut parts = label.splitn(4, ':');
+    let (kind, _class, os, flavor) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    let generic = match kind {
+        "s" => false,
+        "g" => true,
+        _ => return None,
+    };
+
+    let os = os.trim();
+    if os.is_empty() {
+        return None;
+    }
+
+    Some(Label {
+        os: os.to_string(),
+        flavor: flavor.trim().to_string(),
+        generic,
+    })
+}
+
+fn parse_signature(signature: &str, label: Label) -> Option<Signature> {
+    let [
+        version,
+        ittl,
+        options_len,
+        mss,
+        window,
+        scale,
+        layout,
+        quirk_list,
+        payload,
+    ] = fields(signature)?;
+
+    let window = if window == "*" {
+        WindowSize::Any
+    } else if let Some(factor) = window.strip_prefix("mss*") {
+        WindowSize::Mss(factor.parse().ok()?)
+    } else if let Some(factor) = window.strip_prefix("mtu*") {
+        WindowSize::Mtu(factor.parse().ok()?)
+    } else if let Some(divisor) = window.strip_prefix('%') {
+        WindowSize::Modulo(divisor.parse().ok()?)
+    } else {
+        WindowSize::Value(window.parse().ok()?)
+    };
+    let payload = match payload {
+        "0" => Some(false),
+        "+" => Some(true),
+        "*" => None,
+        _ => return None,
+    };
+    // p0f marks TTLs seen at an unknown distance with a trailing `-`.
+    let ittl = ittl.strip_suffix('-').unwrap_or(ittl);
+
+    Some(Signature {
+        version: wildcard(version)?,
+        ittl: ittl.parse().ok()?,
+        options_len: options_len.parse().ok()?,
+        mss: wildcard(mss)?,
+        window,
+        scale: wildcard(scale)?,
+        layout: layout.to_string(),
+        quirks: quirks(quirk_list),
+        payload,
+        label,
+    })
+}
+
+fn parse_observed(signature: &str) -> Option<Observed> {
+    let [
+        version,
+        ttl,
+        options_len,
+        mss,
+        window,
+        scale,
+        layout,
+        quirk_list,
+        payload,
+    ] = fields(signature)?;
+
+    Some(Observed {
+        version: version.parse().ok()?,
+        ttl: ttl.parse().ok()?,
+        options_len: options_len.parse().ok()?,
+        mss: wildcard(mss)?,
+        window: window.parse().ok()?,
+        scale: scale.parse().ok()?,
+        layout: layout.to_string(),
+        quirks: quirks(quirk_list),
+        payload: payload == "+",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNATURES: &str = "\
+[tcp:request]
+
+label = s:unix:Linux:3.11 and newer
+sys   = Linux
+sig   = *:64:0:*:mss*20,7:mss,sok,ts,nop,ws:df,id+:0
+
+label = g:unix:Linux:
+sig   = *:64:0:*:*,*:mss,sok,ts,nop,ws:df,id+:0
+
+label = s:win:Windows:7 or 8
+sig   = *:128:0:*:8192,8:mss,nop,ws,nop,nop,sok:df,id+:0
+
+label = s:win:Windows:10
+sig   = 4:128-:0:*:64240,8:mss,nop,ws,nop,nop,sok:df,id+:0
+sig   = 4:128:0:*:%8192,8:mss,nop,ws,nop,nop,sok:df,id+,ecn:0
+
+[tcp:response]
+
+label = s:unix:Linux:ignored
+sig   = *:64:0:*:*,*:mss,sok,ts,nop,ws:df,id+:0
+";
+
+    fn classify(observed: &str) -> Option<(String, String, bool)> {
+        TcpSignatures::parse(SIGNATURES)
+            .classify(observed)
+            .map(|c| (c.os, c.flavor, c.fuzzy))
+    }
+
+    fn classification(os: &str, flavor: &str, fuzzy: bool) -> Option<(String, String, bool)> {
+        Some((os.to_string(), flavor.to_string(), fuzzy))
+    }
+
+    #[test]
+    fn reads_only_syn_signatures() {
+        assert_eq!(TcpSignatures::parse(SIGNATURES).len(), 5);
+        assert!(!TcpSignatures::embedded().is_empty());
+    }
+
+    #[test]
+    fn prefers_specific_signatures() {
+        assert_eq!(
+            classify("4:64:0:1460:29200,7:mss,sok,ts,nop,ws:df,id+:0"),
+            classification("Linux", "3.11 and newer", false)
+        );
+        // Not 20 times the MSS, so only the generic signature matches.
+        assert_eq!(
+            classify("4:64:0:1460:65535,7:mss,sok,ts,nop,ws:df,id+:0"),
+            classification("Linux", "", false)
+        );
+        assert_eq!(
+            classify("4:128:0:1460:64240,8:mss,nop,ws,nop,nop,sok:df,id+:0"),
+            classification("Windows", "10", false)
+        );
+        assert_eq!(
+            classify("6:128:0:1440:64240,8:mss,nop,ws,nop,nop,sok:df,id+:0"),
+            None
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_closest_quirks() {
+        // Windows 10 with ECN would also match the Windows 7 signature, one
+        // quirk further away.
+        assert_eq!(
+            classify("4:128:0:1460:8192,8:mss,nop,ws,nop,nop,sok:df,id+,ecn:0"),
+            classification("Windows", "10", false)
+        );
+        assert_eq!(
+            classify("4:128:0:1460:64240,8:mss,nop,ws,nop,nop,sok:id-:0"),
+            classification("Windows", "10", true)
+        );
+        assert_eq!(
+            classify("4:128:0:1460:8192,8:mss,nop,ws,nop,nop,sok:df:0"),
+            classification("Windows", "7 or 8", true)
+        );
+    }
+
+    #[test]
+    fn gives_up_on_ambiguous_fuzzy_matches() {
+        let mut signatures = TcpSignatures::parse(SIGNATURES);
+        signatures.read(
+            "[tcp:request]\nlabel = s:win:Windows:Server 2008\nsig = *:128:0:*:8192,8:mss,nop,ws,nop,nop,sok:df,id+:0\n",
+        );
+
+        assert_eq!(
+            signatures.classify("4:128:0:1460:8192,8:mss,nop,ws,nop,nop,sok:df:0"),
+            None
+        );
+    }
+
+    #[test]
+    fn later_signatures_win_ties() {
+        let mut signatures = TcpSignatures::parse(SIGNATURES);
+        signatures.read("[tcp:request]\nlabel = s:unix:Android:10\nsig = *:64:0:*:mss*20,7:mss,sok,ts,nop,ws:df,id+:0\n");
+
+        let classification = signatures
+            .classify("4:64:0:1460:29200,7:mss,sok,ts,nop,ws:df,id+:0")
+            .unwrap();
+        assert_eq!(classification.os, "Android");
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let mut signatures = TcpSignatures::default();
+        let mut state = ParseState::default();
+        assert!(signatures.add_line("[tcp:request]", &mut state));
+        // No label yet.
+        assert!(!signatures.add_line("sig = *:64:0:*:*,*:mss:df:0", &mut state));
+        assert!(!signatures.add_line("label = x:unix:Linux:", &mut state));
+        assert!(signatures.add_line("label = s:unix:Linux:", &mut state));
+        assert!(!signatures.add_line("sig = *:64:0:*:mss*x,*:mss:df:0", &mut state));
+        assert!(!signatures.add_line("sig = *:64:0:*:*:mss:df:0", &mut state));
+        assert!(signatures.add_line("sig = *:64:0:*:*,*:mss:df:*", &mut state));
+        assert_eq!(signatures.len(), 1);
+    }
+}
@@ -0,0 +1,454 @@
+// Frames from a `SyntheticSource` replayed through the daemon into an
+// in-memory database.
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use foxd_daemon::daemon::Daemon;
+use foxd_daemon::db::Database;
+use foxd_daemon::models::{DaemonConfig, DeviceStatus, LogEntry};
+use foxd_daemon::notifier::Notifier;
+use foxd_daemon::source::SyntheticSource;
+use foxd_daemon::source::frames;
+use pnet::packet::arp::ArpOperations;
+use pnet::util::MacAddr;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+const PI: MacAddr = MacAddr(0xb8, 0x27, 0xeb, 0x12, 0x34, 0x56);
+const LAPTOP: MacAddr = MacAddr(0x00, 0x1b, 0x21, 0x3c, 0x4d, 0x5e);
+const GATEWAY: MacAddr = MacAddr(0x00, 0x11, 0x22, 0x33, 0x44, 0x01);
+const ATTACKER: MacAddr = MacAddr(0x02, 0xde, 0xad, 0xbe, 0xef, 0x01);
+// Randomized MACs.
+const PHONE: MacAddr = MacAddr(0x02, 0x6a, 0x11, 0x22, 0x33, 0x01);
+const ROTATED_PHONE: MacAddr = MacAddr(0x02, 0x6a, 0x11, 0x22, 0x33, 0x02);
+const OTHER_PHONE: MacAddr = MacAddr(0x0a, 0x6a, 0x11, 0x22, 0x33, 0x03);
+
+const GATEWAY_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+
+fn config() -> DaemonConfig {
+    DaemonConfig {
+        interface: "lo".to_string(),
+        interfaces: vec![],
+        capture_filter: None,
+        neighbor_check_interval_secs: 30,
+        device_timeout_secs: 60,
+        log_cleanup_enabled: false,
+        log_retention_days: 30,
+        trusted_ipv6_routers: vec![],
+        trusted_dhcp_servers: vec![],
+        trusted_dhcp_relays: vec![],
+        source: None,
+        replay_packet_time: true,
+        ssdp_fetch_descriptions: false,
+        netbios_query_unnamed: false,
+        arp_scan_interval_secs: 0,
+        arp_scan_rate: 100,
+        presence_probes: vec![],
+        arp_watch_ips: vec![],
+        gratuitous_arp_threshold: 10,
+        arp_max_ips_per_mac: 8,
+        lease_files: vec![],
+        tcp_fingerprinting: false,
+        dhcp_fingerprints_file: None,
+        tcp_signatures_file: None,
+    }
+}
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+}
+
+async fn replay(config: &DaemonConfig, source: &mut SyntheticSource) -> Database {
+    let db = Database::in_memory().await.unwrap();
+    let daemon = Daemon::new(db.clone(), Notifier::new(vec![]), config);
+    daemon.replay(source).await.unwrap();
+    db
+}
+
+async fn logs(db: &Database, category: &str) -> Vec<LogEntry> {
+    db.get_logs(None, None)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|log| log.category == category)
+        .collect()
+}
+
+#[tokio::test]
+async fn names_and_classifies_a_dhcp_client() {
+    let options = frames::dhcp_options(&[
+        (53, &[3]),
+        (50, &[192, 168, 1, 40]),
+        (12, b"build-box"),
+        (60, b"MSFT 5.0"),
+        (
+            55,
+            &[1, 3, 6, 15, 31, 33, 43, 44, 46, 47, 119, 121, 249, 252],
+        ),
+    ]);
+    let request = frames::bootp(1, LAPTOP, [Ipv4Addr::UNSPECIFIED; 4], &options);
+
+    let mut source = SyntheticSource::new();
+    source.push_at(
+        start(),
+        frames::udp4(
+            LAPTOP,
+            "0.0.0.0:68".parse().unwrap(),
+            "255.255.255.255:67".parse().unwrap(),
+            &request,
+        ),
+    );
+    source.push_at(
+        start() + Duration::seconds(1),
+        frames::arp(
+            ArpOperations::Request,
+            LAPTOP,
+            Ipv4Addr::new(192, 168, 1, 40),
+            GATEWAY_IP,
+        ),
+    );
+
+    let db = replay(&config(), &mut source).await;
+    let device = db
+        .get_device_by_mac("00:1b:21:3c:4d:5e")
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(device.hostname.as_deref(), Some("build-box"));
+    assert_eq!(device.ip_address.as_deref(), Some("192.168.1.40"));
+    assert_eq!(device.vendor.as_deref(), Some("Intel Corporate"));
+    assert_eq!(device.os_family.as_deref(), Some("Windows"));
+    assert_eq!(device.device_type.as_deref(), Some("computer"));
+    assert_eq!(device.status, DeviceStatus::Online);
+    assert_eq!(device.first_seen, start());
+    assert_eq!(db.get_all_devices().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn records_mdns_services() {
+    let service_type = frames::dns_name("_airplay._tcp.local");
+    let instance = frames::dns_name("Living Room._airplay._tcp.local");
+    let target = frames::dns_name("living-room.local");
+    let srv = [&[0, 0, 0, 0, 0x1b, 0x58][..], &target].concat();
+    let response = [
+        frames::dns_header(0, 0x8400, [0, 4, 0, 0]),
+        frames::dns_record(&service_type, 12, 1, 4500, &instance),
+        frames::dns_record(&instance, 33, 0x8001, 120, &srv),
+        frames::dns_record(&instance, 16, 0x8001, 4500, b"\x10model=AppleTV6,2"),
+        frames::dns_record(&target, 1, 0x8001, 120, &[192, 168, 1, 60]),
+    ]
+    .concat();
+
+    let mut source = SyntheticSource::new();
+    source.push_at(
+        start(),
+        frames::udp4(
+            PI,
+            "192.168.1.60:5353".parse().unwrap(),
+            "224.0.0.251:5353".parse().unwrap(),
+            &response,
+        ),
+    );
+
+    let db = replay(&config(), &mut source).await;
+    let device = db
+        .get_device_by_mac("b8:27:eb:12:34:56")
+        .await
+        .unwrap()
+        .unwrap();
+    let services = db.get_device_services(device.id.unwrap()).await.unwrap();
+
+    assert_eq!(device.hostname.as_deref(), Some("living-room"));
+    assert_eq!(device.vendor.as_deref(), Some("Raspberry Pi Foundation"));
+    assert_eq!(services.len(), 1);
+    assert_eq!(services[0].service.service_type, "_airplay._tcp");
+    assert_eq!(services[0].service.instance, "Living Room");
+    assert_eq!(services[0].service.port, Some(7000));
+    assert_eq!(services[0].service.txt, vec!["model=AppleTV6,2"]);
+}
+
+#[tokio::test]
+async fn takes_silent_devices_offline() {
+    let mut source = SyntheticSource::new();
+    source.push_at(
+        start(),
+        frames::arp(
+            ArpOperations::Request,
+            PI,
+            Ipv4Addr::new(192, 168, 1, 19),
+            GATEWAY_IP,
+        ),
+    );
+    for minute in 0..5 {
+        source.push_at(
+            start() + Duration::minutes(minute),
+            frames::arp(
+                ArpOperations::Reply,
+                GATEWAY,
+                GATEWAY_IP,
+                Ipv4Addr::new(192, 168, 1, 2),
+            ),
+        );
+    }
+
+    let db = replay(&config(), &mut source).await;
+
+    let pi = db
+        .get_device_by_mac("b8:27:eb:12:34:56")
+        .await
+        .unwrap()
+        .unwrap();
+    let gateway = db
+        .get_device_by_mac("00:11:22:33:44:01")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(pi.status, DeviceStatus::Offline);
+    assert_eq!(gateway.status, DeviceStatus::Online);
+    assert_eq!(gateway.last_seen, start() + Duration::minutes(4));
+
+    let disconnections: Vec<_> = logs(&db, "device")
+        .await
+        .into_iter()
+        .filter(|log| log.message.starts_with("Device disconnected"))
+        .collect();
+    assert_eq!(disconnections.len(), 1, "{:?}", disconnections);
+    assert!(disconnections[0].message.contains("b8:27:eb:12:34:56"));
+    assert!(disconnections[0].timestamp > start() + Duration::seconds(60));
+}
+
+#[tokio::test]
+async fn reports_a_spoofed_gateway() {
+    let mut config = config();
+    config.arp_watch_ips = vec![GATEWAY_IP.into()];
+
+    let mut source = SyntheticSource::new();
+    for (offset, mac) in [(0, GATEWAY), (5, ATTACKER)] {
+        source.push_at(
+            start() + Duration::seconds(offset),
+            frames::arp(
+                ArpOperations::Reply,
+                mac,
+                GATEWAY_IP,
+                Ipv4Addr::new(192, 168, 1, 2),
+            ),
+        );
+    }
+
+    let db = replay(&config, &mut source).await;
+    let security = logs(&db, "security").await;
+
+    assert_eq!(security.len(), 1, "{:?}", security);
+    assert!(security[0].message.contains("192.168.1.1"));
+    assert!(security[0].message.contains("02:de:ad:be:ef:01"));
+}
+
+/// A DHCP request from `mac` naming itself `hostname`, with an iPhone's
+/// parameter request list.
+fn iphone_request(mac: MacAddr, hostname: &str) -> Vec<u8> {
+    let options = frames::dhcp_options(&[
+        (53, &[3]),
+        (12, hostname.as_bytes()),
+        (55, &[1, 121, 3, 6, 15, 108, 114, 119, 252]),
+    ]);
+    frames::udp4(
+        mac,
+        "0.0.0.0:68".parse().unwrap(),
+        "255.255.255.255:67".parse().unwrap(),
+        &frames::bootp(1, mac, [Ipv4Addr::UNSPECIFIED; 4], &options),
+    )
+}
+
+#[tokio::test]
+async fn follows_a_silent_device_to_its_new_random_mac() {
+    let mut source = SyntheticSource::new();
+    source.push_at(start(), iphone_request(PHONE, "iPhone"));
+    source.push_at(
+        start() + Duration::minutes(10),
+        iphone_request(ROTATED_PHONE, "iPhone"),
+    );
+
+    let db = replay(&config(), &mut source).await;
+    let devices = db.get_all_devices().await.unwrap();
+
+    assert_eq!(devices.len(), 1, "{:?}", devices);
+    assert_eq!(devices[0].mac_address, "02:6a:11:22:33:02");
+}
+
+#[tokio::test]
+async fn keeps_identical_models_apart() {
+    let mut source = SyntheticSource::new();
+    // Two phones online at the same time, and a new one that shares the
+    // defaults of a silent phone with a globally administered MAC.
+    source.push_at(start(), iphone_request(PHONE, "iPhone"));
+    source.push_at(start(), iphone_request(LAPTOP, "MacBook"));
+    source.push_at(
+        start() + Duration::seconds(20),
+        iphone_request(ROTATED_PHONE, "iPhone"),
+    );
+    source.push_at(
+        start() + Duration::minutes(10),
+        iphone_request(OTHER_PHONE, "MacBook"),
+    );
+
+    let db = replay(&config(), &mut source).await;
+
+    assert_eq!(db.get_all_devices().await.unwrap().len(), 4);
+}
+
+/// A DHCP ACK from `server_mac` at `server`, naming `server_id` as the
+/// server, leasing `address` to the laptop for an hour, through `relay` if
+/// given.
+fn dhcp_ack(
+    server_mac: MacAddr,
+    server: Ipv4Addr,
+    server_id: Ipv4Addr,
+    address: Ipv4Addr,
+    relay: Option<Ipv4Addr>,
+) -> Vec<u8> {
+    let options = frames::dhcp_options(&[
+        (53, &[5]),
+        (54, &server_id.octets()),
+        (51, &3600u32.to_be_bytes()),
+    ]);
+    let addresses = [
+        Ipv4Addr::UNSPECIFIED,
+        address,
+        Ipv4Addr::UNSPECIFIED,
+        relay.unwrap_or(Ipv4Addr::UNSPECIFIED),
+    ];
+    frames::udp4(
+        server_mac,
+        SocketAddrV4::new(server, 67),
+        "255.255.255.255:68".parse().unwrap(),
+        &frames::bootp(2, LAPTOP, addresses, &options),
+    )
+}
+
+#[tokio::test]
+async fn reports_a_dhcp_server_sending_from_the_real_ones_address() {
+    let address = Ipv4Addr::new(192, 168, 1, 40);
+    let mut source = SyntheticSource::new();
+    source.push_at(
+        start(),
+        dhcp_ack(GATEWAY, GATEWAY_IP, GATEWAY_IP, address, None),
+    );
+    source.push_at(
+        start() + Duration::seconds(5),
+        dhcp_ack(ATTACKER, GATEWAY_IP, GATEWAY_IP, address, None),
+    );
+
+    let db = replay(&config(), &mut source).await;
+    let security = logs(&db, "security").await;
+
+    assert_eq!(security.len(), 1, "{:?}", security);
+    assert!(security[0].message.contains("02:de:ad:be:ef:01"));
+    assert!(
+        security[0]
+            .details
+            .as_deref()
+            .is_some_and(|details| details.ends_with("posing as 00:11:22:33:44:01"))
+    );
+}
+
+#[tokio::test]
+async fn reports_a_dhcp_server_copying_a_configured_server_id() {
+    let mut config = config();
+    config.trusted_dhcp_servers = vec![GATEWAY_IP.to_string()];
+
+    let address = Ipv4Addr::new(192, 168, 1, 40);
+    let mut source = SyntheticSource::new();
+    // The first reply from the configured address pins its MAC.
+    source.push_at(
+        start(),
+        dhcp_ack(GATEWAY, GATEWAY_IP, GATEWAY_IP, address, None),
+    );
+    source.push_at(
+        start() + Duration::seconds(5),
+        dhcp_ack(
+            ATTACKER,
+            Ipv4Addr::new(192, 168, 1, 66),
+            GATEWAY_IP,
+            address,
+            None,
+        ),
+    );
+
+    let db = replay(&config, &mut source).await;
+    let security = logs(&db, "security").await;
+
+    assert_eq!(security.len(), 1, "{:?}", security);
+    assert!(security[0].message.contains("02:de:ad:be:ef:01"));
+}
+
+#[tokio::test]
+async fn ignores_leases_from_a_rogue_server() {
+    let mut source = SyntheticSource::new();
+    source.push_at(
+        start(),
+        dhcp_ack(
+            GATEWAY,
+            GATEWAY_IP,
+            GATEWAY_IP,
+            Ipv4Addr::new(192, 168, 1, 40),
+            None,
+        ),
+    );
+    let rogue_ip = Ipv4Addr::new(10, 0, 0, 1);
+    source.push_at(
+        start() + Duration::seconds(5),
+        dhcp_ack(
+            ATTACKER,
+            rogue_ip,
+            rogue_ip,
+            Ipv4Addr::new(10, 0, 0, 40),
+            None,
+        ),
+    );
+
+    let db = replay(&config(), &mut source).await;
+    let laptop = db
+        .get_device_by_mac("00:1b:21:3c:4d:5e")
+        .await
+        .unwrap()
+        .unwrap();
+    let lease = db
+        .get_device_lease(laptop.id.unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(laptop.ip_address.as_deref(), Some("192.168.1.40"));
+    assert_eq!(lease.ip_address, "192.168.1.40");
+    assert_eq!(lease.server_mac, "00:11:22:33:44:01");
+    assert_eq!(lease.acquired_at, start());
+}
+
+#[tokio::test]
+async fn places_relayed_leases_on_the_relay_segment() {
+    let mut source = SyntheticSource::new();
+    source.push_at(
+        start(),
+        dhcp_ack(
+            GATEWAY,
+            GATEWAY_IP,
+            GATEWAY_IP,
+            Ipv4Addr::new(10, 0, 20, 40),
+            Some(Ipv4Addr::new(10, 0, 20, 1)),
+        ),
+    );
+
+    let db = replay(&config(), &mut source).await;
+    let laptop = db
+        .get_device_by_mac("00:1b:21:3c:4d:5e")
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(laptop.segment.as_deref(), Some("relay-10.0.20.1"));
+    assert!(
+        db.get_device_lease(laptop.id.unwrap())
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
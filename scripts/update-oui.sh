@@ -0,0 +1,38 @@
+#!/bin/bash
+
+set -e
+
+# Colors for output
+RED='\033[0;31m'
+GREEN='\033[0;32m'
+YELLOW='\033[1;33m'
+BLUE='\033[0;34m'
+NC='\033[0m' # No Color
+
+OUTPUT="daemon/data/oui.bin"
+REGISTRIES=(
+    "https://standards-oui.ieee.org/oui/oui.csv"
+    "https://standards-oui.ieee.org/oui28/mam.csv"
+    "https://standards-oui.ieee.org/oui36/oui36.csv"
+)
+
+WORK_DIR=$(mktemp -d)
+trap 'rm -rf "$WORK_DIR"' EXIT
+
+echo -e "${BLUE}Downloading IEEE registries...${NC}"
+CSV_ARGS=()
+for url in "${REGISTRIES[@]}"; do
+    file="$WORK_DIR/$(basename "$url")"
+    echo -e "${YELLOW}  $url${NC}"
+    curl -fsSL "$url" -o "$file" || {
+        echo -e "${RED}✗ Failed to download $url${NC}"
+        exit 1
+    }
+    CSV_ARGS+=(--csv "$file")
+done
+
+echo -e "${BLUE}Encoding registry...${NC}"
+cargo run --quiet --manifest-path daemon/Cargo.toml -- \
+    oui-refresh "${CSV_ARGS[@]}" --output "$OUTPUT"
+
+echo -e "${GREEN}✓ Updated $OUTPUT ($(du -h "$OUTPUT" | cut -f1))${NC}"